@@ -20,7 +20,74 @@ pub(crate) enum Commands {
         #[arg(short, long)]
         bolt: String,
         to: PathBuf,
+        /// BOLT markdown used to document the generated code
+        #[arg(long)]
+        spec: Option<PathBuf>,
+        /// Emit also the zero copy views of the messages (rust only)
+        #[arg(long)]
+        zero_copy: bool,
+        /// Derive serde Serialize/Deserialize for the messages (rust only)
+        #[arg(long)]
+        serde: bool,
+        /// Emit a builder for each message (rust only)
+        #[arg(long)]
+        builder: bool,
+        /// Emit a self-contained module without the lnspec_py dependency (python only)
+        #[arg(long)]
+        standalone: bool,
     },
     /// Decode a unsgned lightning message in hex fromat
     Decode { from: String },
+    /// Extract the csv format of the messages from a BOLT markdown
+    Extract {
+        /// The BOLT markdown file, e.g. bolts/01-messaging.md
+        bolt: PathBuf,
+        /// Where to write the csv, by default on the standard output
+        #[arg(short, long)]
+        to: Option<PathBuf>,
+    },
+    /// Print the byte layout of a message
+    Layout {
+        /// The csv file, or the BOLT markdown, where the message is defined
+        #[arg(short, long)]
+        bolt: String,
+        /// The name of the message, e.g. channel_update
+        #[arg(long)]
+        msg: String,
+        /// Write also a svg diagram of the layout at this path
+        #[arg(long)]
+        svg: Option<PathBuf>,
+    },
+    /// Compare two revisions of the spec and report the changes
+    Diff {
+        /// The old csv file, or BOLT markdown
+        old: String,
+        /// The new csv file, or BOLT markdown
+        new: String,
+    },
+    /// Check a csv file that extends the spec with experimental messages
+    Lint {
+        /// The csv file, or BOLT markdown, to check
+        bolt: String,
+        /// Ignore the rule, e.g. `--allow even-tlv-type`
+        #[arg(long)]
+        allow: Vec<String>,
+        /// Report the rule as a warning
+        #[arg(long)]
+        warn: Vec<String>,
+        /// Report the rule as an error
+        #[arg(long)]
+        deny: Vec<String>,
+    },
+    /// Write a csv file in the canonical form
+    Fmt {
+        /// The csv file to format
+        bolt: PathBuf,
+        /// Write the result in the file instead of the standard output
+        #[arg(short, long)]
+        write: bool,
+        /// Fail if the file is not in the canonical form
+        #[arg(long)]
+        check: bool,
+    },
 }
@@ -1,28 +1,140 @@
 //! Implementing the Code generator base on the CSV file.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
 use crate::gen::CodeGenMethod;
 
+use codegen::c::CCodeGen;
 use codegen::codegen::CodeGen;
-use codegen::rust::RustCodeGen;
+use codegen::docs::{DocsCodeGen, DocsFormat};
+use codegen::go::GoCodeGen;
+use codegen::jsonschema::JsonSchemaCodeGen;
+use codegen::kaitai::KaitaiCodeGen;
+use codegen::kotlin::KotlinCodeGen;
+use codegen::proto::ProtoCodeGen;
+use codegen::python::{PythonCodeGen, PythonCodeGenOptions};
+use codegen::rust::{RustCodeGen, RustCodeGenOptions};
+use codegen::swift::SwiftCodeGen;
+use codegen::typescript::TypeScriptCodeGen;
+use codegen::wireshark::WiresharkCodeGen;
+use codegen::zig::ZigCodeGen;
+use csvlang::emitter::emitter::Emitter;
+use csvlang::markdown::docs::SpecDocs;
+use csvlang::parser::ast::LNMsgType;
 use csvlang::parser::parser::Parser;
 use csvlang::scanner::scanner::Scanner;
 
 pub struct CSVCodeGen {
     pub lang: String,
+    pub rust_options: RustCodeGenOptions,
+    pub python_options: PythonCodeGenOptions,
+    /// Documentation of the messages taken from the BOLT markdown.
+    pub spec_docs: Option<SpecDocs>,
+}
+
+/// Parse the csv content of a bolt to the symbol table.
+pub(crate) fn parse_csv(bolt_content: &str) -> anyhow::Result<BTreeMap<String, LNMsgType>> {
+    let mut scanner = Scanner::new();
+    let mut parser = Parser::new();
+    let lines = scanner.scan_lines(bolt_content);
+    parser.parse_lines(&lines)?;
+    Ok(parser.symbol_table)
+}
+
+/// Write the csv content of a bolt in the canonical form.
+pub(crate) fn format_csv(bolt_content: &str) -> anyhow::Result<String> {
+    let mut scanner = Scanner::new();
+    let mut parser = Parser::new();
+    let lines = scanner.scan_lines(bolt_content);
+    parser.parse_lines(&lines)?;
+    Ok(Emitter::with_trailing_trivia(&parser.trailing_trivia).emit(&parser.symbol_table))
+}
+
+impl CSVCodeGen {
+    fn symbol_table(&self, bolt_content: &str) -> anyhow::Result<BTreeMap<String, LNMsgType>> {
+        let mut symbol_table = parse_csv(bolt_content)?;
+        if let Some(docs) = &self.spec_docs {
+            docs.attach(&mut symbol_table);
+        }
+        Ok(symbol_table)
+    }
 }
 
 impl CodeGenMethod for CSVCodeGen {
     fn generate(&self, bolt_content: &str) -> anyhow::Result<String> {
-        let mut scanner = Scanner::new();
-        let mut parser = Parser::new();
-        let tokens = scanner.scan(bolt_content);
-        parser.parse(&tokens);
-
-        let symbol_table = parser.symbol_table;
+        let symbol_table = self.symbol_table(bolt_content)?;
 
         let content = match self.lang.as_str() {
             "rust" | "rs" => {
-                let mut backend = RustCodeGen::new(&symbol_table);
-                backend.generate(&symbol_table);
+                let mut backend =
+                    RustCodeGen::with_options(&symbol_table, self.rust_options.clone());
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "python" | "py" => {
+                let mut backend =
+                    PythonCodeGen::with_options(&symbol_table, self.python_options.clone());
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "go" | "golang" => {
+                let mut backend = GoCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "ts" | "typescript" => {
+                let mut backend = TypeScriptCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "kt" | "kotlin" => {
+                let mut backend = KotlinCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "swift" => {
+                let mut backend = SwiftCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "zig" => {
+                let mut backend = ZigCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "c" => {
+                let mut backend = CCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "proto" | "protobuf" => {
+                let mut backend = ProtoCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "jsonschema" | "json-schema" => {
+                let mut backend = JsonSchemaCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "kaitai" | "ksy" => {
+                let mut backend = KaitaiCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "wireshark" | "lua" => {
+                let mut backend = WiresharkCodeGen::new(&symbol_table);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "markdown" | "md" => {
+                let mut backend = DocsCodeGen::with_format(&symbol_table, DocsFormat::Markdown);
+                backend.generate(&symbol_table)?;
+                backend.to_string()
+            }
+            "html" => {
+                let mut backend = DocsCodeGen::with_format(&symbol_table, DocsFormat::Html);
+                backend.generate(&symbol_table)?;
                 backend.to_string()
             }
             _ => anyhow::bail!("Language not supported"),
@@ -30,4 +142,24 @@ impl CodeGenMethod for CSVCodeGen {
 
         Ok(content)
     }
+
+    fn generate_files(
+        &self,
+        bolt_content: &str,
+        to: &Path,
+    ) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        if self.lang != "c" {
+            return Ok(vec![(to.to_owned(), self.generate(bolt_content)?)]);
+        }
+        // the C backend emits a header/source pair next to each other.
+        let symbol_table = self.symbol_table(bolt_content)?;
+        let header = to.with_extension("h");
+        let header_name = header.file_name().unwrap().to_str().unwrap();
+        let mut backend = CCodeGen::with_header(&symbol_table, header_name);
+        backend.generate(&symbol_table)?;
+        Ok(vec![
+            (header.to_owned(), backend.header().to_owned()),
+            (to.with_extension("c"), backend.source().to_owned()),
+        ])
+    }
 }
@@ -1,5 +1,7 @@
 //! Gen logic to dispach the different way to generate
 //! the lightning network spec.
+use std::path::{Path, PathBuf};
+
 pub(crate) mod csv_method;
 
 pub trait CodeGenMethod {
@@ -7,4 +9,14 @@ pub trait CodeGenMethod {
     /// is defined and generate the python code that contains
     /// all the message define inside it
     fn generate(&self, bolt_content: &str) -> anyhow::Result<String>;
+
+    /// Generate the files to write for the path provided, by default
+    /// the whole code goes inside the file at `to`.
+    fn generate_files(
+        &self,
+        bolt_content: &str,
+        to: &Path,
+    ) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        Ok(vec![(to.to_owned(), self.generate(bolt_content)?)])
+    }
 }
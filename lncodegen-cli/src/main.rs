@@ -1,33 +1,71 @@
 use std::fs;
 
 use clap::Parser;
+use codegen::diff::SpecDiff;
+use codegen::layout::MsgLayout;
+use codegen::lint::{LintLevel, LintRule, Linter};
+use codegen::python::PythonCodeGenOptions;
+use codegen::rust::RustCodeGenOptions;
+use csvlang::markdown::docs::SpecDocs;
+use csvlang::markdown::extractor::Extractor;
+use csvlang::parser::ast::LNMsgType;
 use radicle_term as term;
 
 mod cmd;
 mod gen;
 
 use crate::cmd::cmd_args::{Cli, Commands};
-use crate::gen::csv_method::CSVCodeGen;
+use crate::gen::csv_method::{format_csv, parse_csv, CSVCodeGen};
 use crate::gen::CodeGenMethod;
 
+/// Read the csv content of a bolt, extracting it when the
+/// file is a BOLT markdown.
+fn read_bolt(bolt: &str) -> anyhow::Result<String> {
+    fs::metadata(bolt)?;
+    let file_content = fs::read_to_string(bolt)?;
+    if bolt.ends_with(".md") {
+        return Ok(Extractor::new().extract(&file_content));
+    }
+    Ok(file_content)
+}
+
 fn dispach_cmd(args: &Cli) -> anyhow::Result<()> {
     match &args.command {
         Commands::Generate {
             bolt,
             to: result_path,
+            spec,
+            zero_copy,
+            serde,
+            builder,
+            standalone,
         } => {
-            fs::metadata(bolt)?;
-            let file_content = fs::read_to_string(bolt)?;
+            let file_content = read_bolt(bolt)?;
             let lang = args.lang.clone().unwrap();
+            let spec_docs = match spec {
+                Some(spec) => {
+                    let content = fs::read_to_string(spec)?;
+                    let file_name = spec.file_name().unwrap().to_str().unwrap();
+                    Some(SpecDocs::parse(&content, file_name))
+                }
+                None => None,
+            };
             let generator = CSVCodeGen {
                 lang: lang.to_owned(),
+                rust_options: RustCodeGenOptions {
+                    zero_copy: *zero_copy,
+                    serde: *serde,
+                    builder: *builder,
+                },
+                python_options: PythonCodeGenOptions {
+                    standalone: *standalone,
+                },
+                spec_docs,
             };
-            let result = generator.generate(file_content.as_str())?;
-            term::success!(
-                "Generate {lang} to {}",
-                result_path.as_os_str().to_str().unwrap()
-            );
-            fs::write(result_path, result)?;
+            for (path, result) in generator.generate_files(file_content.as_str(), result_path)? {
+                term::success!("Generate {lang} to {}", path.as_os_str().to_str().unwrap());
+                fs::write(path, result)?;
+            }
             Ok(())
         }
         Commands::Decode { from } => {
@@ -41,16 +79,96 @@ fn dispach_cmd(args: &Cli) -> anyhow::Result<()> {
             term::success!("{:#?}", init);
             Ok(())
         }
+        Commands::Extract { bolt, to } => {
+            let content = fs::read_to_string(bolt)?;
+            let csv = Extractor::new().extract(&content);
+            match to {
+                Some(to) => {
+                    fs::write(to, csv)?;
+                    term::success!("Extract csv to {}", to.as_os_str().to_str().unwrap());
+                }
+                None => print!("{csv}"),
+            }
+            Ok(())
+        }
+        Commands::Layout { bolt, msg, svg } => {
+            let symbol_table = parse_csv(&read_bolt(bolt)?)?;
+            let Some(LNMsgType::Msg(ln_msg)) = symbol_table.get(msg) else {
+                anyhow::bail!("message `{msg}` not found in {bolt}");
+            };
+            let layout = MsgLayout::new(ln_msg);
+            print!("{}", layout.ascii());
+            if let Some(svg) = svg {
+                fs::write(svg, layout.svg())?;
+                term::success!("Write the layout to {}", svg.as_os_str().to_str().unwrap());
+            }
+            Ok(())
+        }
+        Commands::Diff { old, new } => {
+            let old_table = parse_csv(&read_bolt(old)?)?;
+            let new_table = parse_csv(&read_bolt(new)?)?;
+            let diff = SpecDiff::new(&old_table, &new_table);
+            print!("{diff}");
+            if !diff.is_compatible() {
+                anyhow::bail!("{new} is not backward compatible with {old}");
+            }
+            Ok(())
+        }
+        Commands::Lint {
+            bolt,
+            allow,
+            warn,
+            deny,
+        } => {
+            let mut linter = Linter::new();
+            for (rules, level) in [
+                (allow, LintLevel::Allow),
+                (warn, LintLevel::Warn),
+                (deny, LintLevel::Deny),
+            ] {
+                for rule in rules {
+                    let rule = rule.parse::<LintRule>().map_err(anyhow::Error::msg)?;
+                    linter.set_level(rule, level);
+                }
+            }
+            let lints = linter.check(&parse_csv(&read_bolt(bolt)?)?);
+            for lint in &lints {
+                println!("{lint}");
+            }
+            let errors = lints
+                .iter()
+                .filter(|lint| lint.level == LintLevel::Deny)
+                .count();
+            if errors > 0 {
+                anyhow::bail!("{bolt}: {errors} errors found");
+            }
+            Ok(())
+        }
+        Commands::Fmt { bolt, write, check } => {
+            let content = fs::read_to_string(bolt)?;
+            let result = format_csv(&content)?;
+            let path = bolt.as_os_str().to_str().unwrap();
+            if *check {
+                if result != content {
+                    anyhow::bail!("{path} is not formatted");
+                }
+            } else if *write {
+                fs::write(bolt, result)?;
+                term::success!("Format {path}");
+            } else {
+                print!("{result}");
+            }
+            Ok(())
+        }
     }
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() {
     env_logger::init();
 
     let args = Cli::parse();
-    match dispach_cmd(&args) {
-        Ok(_) => {}
-        Err(err) => term::error(format!("{err}")),
+    if let Err(err) = dispach_cmd(&args) {
+        term::error(format!("{err}"));
+        std::process::exit(1);
     }
-    Ok(())
 }
@@ -0,0 +1,79 @@
+//! Check the exit status of the commands.
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus};
+
+/// Write the csv content in a file of the target directory.
+fn write_csv(name: &str, content: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> ExitStatus {
+    Command::new(env!("CARGO_BIN_EXE_lncodegen-cli"))
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+}
+
+#[test]
+fn fmt_check_status() {
+    let formatted = write_csv(
+        "formatted.csv",
+        "msgtype,ping,18\nmsgdata,ping,num_pong_bytes,u16,\n",
+    );
+    let unformatted = write_csv(
+        "unformatted.csv",
+        "msgtype,ping,18\nmsgdata,ping,num_pong_bytes,u16\n",
+    );
+    assert!(run(&["fmt", "--check", formatted.to_str().unwrap()]).success());
+    assert_eq!(
+        run(&["fmt", "--check", unformatted.to_str().unwrap()]).code(),
+        Some(1)
+    );
+}
+
+#[test]
+fn parse_error_status() {
+    let invalid = write_csv("invalid.csv", "msgtype,ping,1e3\n");
+    assert_eq!(run(&["fmt", invalid.to_str().unwrap()]).code(), Some(1));
+}
+
+#[test]
+fn diff_status() {
+    let old = write_csv(
+        "diff_old.csv",
+        "msgtype,ping,18\nmsgdata,ping,num_pong_bytes,u16,\n",
+    );
+    let renamed = write_csv(
+        "diff_renamed.csv",
+        "msgtype,ping,18\nmsgdata,ping,pong_bytes,u16,\n",
+    );
+    let changed = write_csv(
+        "diff_changed.csv",
+        "msgtype,ping,18\nmsgdata,ping,num_pong_bytes,u32,\n",
+    );
+    let (old, renamed, changed) = (
+        old.to_str().unwrap(),
+        renamed.to_str().unwrap(),
+        changed.to_str().unwrap(),
+    );
+    assert!(run(&["diff", old, renamed]).success());
+    assert_eq!(run(&["diff", old, changed]).code(), Some(1));
+}
+
+#[test]
+fn lint_deny_status() {
+    let even = write_csv("lint_even.csv", "msgtype,my_msg,32000\n");
+    let collision = write_csv("lint_collision.csv", "msgtype,my_ping,18\n");
+    let (even, collision) = (even.to_str().unwrap(), collision.to_str().unwrap());
+    assert!(run(&["lint", even]).success());
+    assert_eq!(
+        run(&["lint", "--deny", "even-msg-type", even]).code(),
+        Some(1)
+    );
+    assert_eq!(run(&["lint", collision]).code(), Some(1));
+    assert!(run(&["lint", "--allow", "msg-type-collision", collision]).success());
+}
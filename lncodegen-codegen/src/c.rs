@@ -0,0 +1,774 @@
+//! C code generation for the lightning
+//! network specification.
+//!
+//! The backend emits a header with the message structs and a
+//! source with the `towire_<msg>`/`fromwire_<msg>` functions, that
+//! work over a buffer provided by the caller without heap allocation.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated functions to read and write
+/// the buffer with bounds checking.
+const C_RUNTIME: &str = indoc! {r#"
+    struct wire_writer {
+    	uint8_t *buf;
+    	size_t len;
+    	size_t pos;
+    	bool ok;
+    };
+
+    struct wire_reader {
+    	const uint8_t *buf;
+    	size_t len;
+    	size_t pos;
+    	bool ok;
+    };
+
+    static inline void wire_put_bytes(struct wire_writer *w, const uint8_t *data, size_t len)
+    {
+    	if (!w->ok || w->len - w->pos < len) {
+    		w->ok = false;
+    		return;
+    	}
+    	if (len > 0)
+    		memcpy(w->buf + w->pos, data, len);
+    	w->pos += len;
+    }
+
+    static inline void wire_put_int(struct wire_writer *w, uint64_t value, size_t size)
+    {
+    	uint8_t data[8];
+    	for (size_t i = 0; i < size; i++)
+    		data[i] = (uint8_t)(value >> (8 * (size - i - 1)));
+    	wire_put_bytes(w, data, size);
+    }
+
+    static inline void wire_put_u16(struct wire_writer *w, uint16_t value)
+    {
+    	wire_put_int(w, value, 2);
+    }
+
+    static inline void wire_put_u32(struct wire_writer *w, uint32_t value)
+    {
+    	wire_put_int(w, value, 4);
+    }
+
+    static inline void wire_put_u64(struct wire_writer *w, uint64_t value)
+    {
+    	wire_put_int(w, value, 8);
+    }
+
+    static inline void wire_put_bigsize(struct wire_writer *w, uint64_t value)
+    {
+    	uint8_t prefix;
+    	if (value < 0xfd) {
+    		prefix = (uint8_t)value;
+    		wire_put_bytes(w, &prefix, 1);
+    	} else if (value <= 0xffff) {
+    		prefix = 0xfd;
+    		wire_put_bytes(w, &prefix, 1);
+    		wire_put_u16(w, (uint16_t)value);
+    	} else if (value <= 0xffffffff) {
+    		prefix = 0xfe;
+    		wire_put_bytes(w, &prefix, 1);
+    		wire_put_u32(w, (uint32_t)value);
+    	} else {
+    		prefix = 0xff;
+    		wire_put_bytes(w, &prefix, 1);
+    		wire_put_u64(w, value);
+    	}
+    }
+
+    static inline void wire_put_record(struct wire_writer *w, uint64_t type,
+    				   const uint8_t *value, size_t len)
+    {
+    	wire_put_bigsize(w, type);
+    	wire_put_bigsize(w, len);
+    	wire_put_bytes(w, value, len);
+    }
+
+    static inline const uint8_t *wire_get_bytes(struct wire_reader *r, uint64_t len)
+    {
+    	const uint8_t *data;
+    	if (!r->ok || (uint64_t)(r->len - r->pos) < len) {
+    		r->ok = false;
+    		return NULL;
+    	}
+    	data = r->buf + r->pos;
+    	r->pos += (size_t)len;
+    	return data;
+    }
+
+    static inline void wire_get_array(struct wire_reader *r, uint8_t *out, size_t len)
+    {
+    	const uint8_t *data = wire_get_bytes(r, len);
+    	if (data)
+    		memcpy(out, data, len);
+    	else
+    		memset(out, 0, len);
+    }
+
+    static inline uint64_t wire_get_int(struct wire_reader *r, size_t size)
+    {
+    	uint64_t value = 0;
+    	const uint8_t *data = wire_get_bytes(r, size);
+    	if (!data)
+    		return 0;
+    	for (size_t i = 0; i < size; i++)
+    		value = (value << 8) | data[i];
+    	return value;
+    }
+
+    static inline uint16_t wire_get_u16(struct wire_reader *r)
+    {
+    	return (uint16_t)wire_get_int(r, 2);
+    }
+
+    static inline uint32_t wire_get_u32(struct wire_reader *r)
+    {
+    	return (uint32_t)wire_get_int(r, 4);
+    }
+
+    static inline uint64_t wire_get_u64(struct wire_reader *r)
+    {
+    	return wire_get_int(r, 8);
+    }
+
+    static inline uint64_t wire_get_bigsize(struct wire_reader *r)
+    {
+    	uint64_t value, min;
+    	uint64_t prefix = wire_get_int(r, 1);
+
+    	switch (prefix) {
+    	case 0xfd:
+    		value = wire_get_u16(r);
+    		min = 0xfd;
+    		break;
+    	case 0xfe:
+    		value = wire_get_u32(r);
+    		min = 0x10000;
+    		break;
+    	case 0xff:
+    		value = wire_get_u64(r);
+    		min = 0x100000000;
+    		break;
+    	default:
+    		return prefix;
+    	}
+    	if (value < min)
+    		r->ok = false;
+    	return value;
+    }
+"#};
+
+/// C keywords that can not be used as field names.
+const C_KEYWORDS: [&str; 32] = [
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "int", "long", "register", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void",
+    "volatile", "while",
+];
+
+pub struct CCodeGen {
+    header_name: String,
+    curr_msg: Option<LNMsg>,
+    /// The members of the C struct of the current message, with the
+    /// `_len` members of the arrays.
+    struct_fields: String,
+    encode_fn: String,
+    decode_fn: String,
+    /// Tlv streams are declared before the messages that use them.
+    tlv_header: String,
+    tlv_source: String,
+    msg_header: String,
+    msg_source: String,
+    header: String,
+    source: String,
+}
+
+impl CCodeGen {
+    /// Build a new C backend, the source include the header with the name provided.
+    pub fn with_header(_symbol_table: &BTreeMap<String, LNMsgType>, header_name: &str) -> Self {
+        CCodeGen {
+            header_name: header_name.to_owned(),
+            curr_msg: None,
+            struct_fields: String::new(),
+            encode_fn: String::new(),
+            decode_fn: String::new(),
+            tlv_header: String::new(),
+            tlv_source: String::new(),
+            msg_header: String::new(),
+            msg_source: String::new(),
+            header: String::new(),
+            source: String::new(),
+        }
+    }
+
+    /// Return the content of the header file.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Return the content of the source file.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn c_ident(name: &str) -> String {
+        let name = name.to_case(Case::Snake);
+        if C_KEYWORDS.contains(&name.as_str()) {
+            return format!("{name}_");
+        }
+        name
+    }
+
+    /// Return the expression of the bytes of `count` elements of size `elem`,
+    /// the arrays are kept as raw bytes and the `_len` fields count the elements.
+    fn array_len(count: &str, elem: u64) -> String {
+        if elem == 1 {
+            count.to_owned()
+        } else {
+            format!("(size_t){count} * {elem}")
+        }
+    }
+
+    /// Return the expression of the elements of size `elem` inside `len` bytes.
+    fn array_count(len: &str, elem: u64) -> String {
+        if elem == 1 {
+            len.to_owned()
+        } else {
+            format!("{len} / {elem}")
+        }
+    }
+
+    fn add_struct_field(&mut self, comment: &str, decl: &str) {
+        self.struct_fields += &format!("\t/* {comment} */\n\t{decl};\n");
+    }
+
+    fn build_int(&mut self, name: &str, bits: u8) {
+        let name = Self::c_ident(name);
+        self.add_struct_field(&format!("u{bits}"), &format!("uint{bits}_t {name}"));
+        self.decode_fn += &format!("\tmsg->{name} = wire_get_u{bits}(&r);\n");
+    }
+
+    fn write_int(&mut self, name: &str, bits: u8) {
+        let name = Self::c_ident(name);
+        self.encode_fn += &format!("\twire_put_u{bits}(&w, msg->{name});\n");
+    }
+
+    /// Fields with a size known at compile time are kept inside the struct.
+    fn build_fixed(&mut self, field: &LNMsData, ty: &str) {
+        let size = field.fixed_size().unwrap();
+        let name = Self::c_ident(field.name());
+        self.add_struct_field(ty, &format!("uint8_t {name}[{size}]"));
+        self.decode_fn += &format!("\twire_get_array(&r, msg->{name}, {size});\n");
+    }
+
+    fn write_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        let name = Self::c_ident(field.name());
+        self.encode_fn += &format!("\twire_put_bytes(&w, msg->{name}, {size});\n");
+    }
+
+    /// Build the struct of a tlv stream and the static functions
+    /// to encode and decode it, the values point inside the buffer.
+    fn build_tlv_stream_decl(&mut self, tlv: &LNTlvStream) {
+        let name = Self::c_ident(&tlv.stream_name);
+        let mut fields = String::new();
+        let mut encode = String::new();
+        let mut decode = String::new();
+        for record in &tlv.records {
+            let field = Self::c_ident(&record.type_name);
+            let typ = record.type_len;
+            fields += &format!(
+                "\t/* {}, type {typ} */\n\tbool has_{field};\n\tconst uint8_t *{field};\n\tsize_t {field}_len;\n",
+                record.type_name
+            );
+            encode += &format!(
+                "\tif (tlv->has_{field})\n\t\twire_put_record(w, {typ}, tlv->{field}, tlv->{field}_len);\n"
+            );
+            decode += &format!(
+                "\t\tcase {typ}:\n\t\t\ttlv->has_{field} = true;\n\t\t\ttlv->{field} = value;\n\t\t\ttlv->{field}_len = (size_t)len;\n\t\t\tbreak;\n"
+            );
+        }
+        self.tlv_header += &format!(
+            "/* The `{}` tlv stream, unknown odd records are skipped. */\nstruct tlv_{name} {{\n{fields}}};\n\n",
+            tlv.stream_name
+        );
+        self.tlv_source += &format!(
+            indoc! {"
+                static inline void towire_tlv_{name}(struct wire_writer *w, const struct tlv_{name} *tlv)
+                {{
+                {encode}}}
+
+                static inline void fromwire_tlv_{name}(struct wire_reader *r, struct tlv_{name} *tlv)
+                {{
+                	uint64_t last_type = 0;
+                	bool first = true;
+
+                	memset(tlv, 0, sizeof(*tlv));
+                	while (r->ok && r->pos < r->len) {{
+                		uint64_t type = wire_get_bigsize(r);
+                		uint64_t len = wire_get_bigsize(r);
+                		const uint8_t *value = wire_get_bytes(r, len);
+
+                		if (!r->ok)
+                			return;
+                		/* types must be strictly increasing */
+                		if (!first && type <= last_type) {{
+                			r->ok = false;
+                			return;
+                		}}
+                		first = false;
+                		last_type = type;
+                		switch (type) {{
+                {decode}		default:
+                			/* it is ok to be odd */
+                			if (type % 2 == 0)
+                				r->ok = false;
+                			(void)value;
+                		}}
+                	}}
+                }}
+
+            "},
+            name = name,
+            encode = encode,
+            decode = decode,
+        );
+    }
+
+    fn header_guard(&self) -> String {
+        let guard = self
+            .header_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        format!("LNCODEGEN_{}", guard.to_uppercase())
+    }
+}
+
+impl<'g> CodeGen<'g> for CCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        CCodeGen::with_header(symbol_table, "lnwire.h")
+    }
+
+    fn raw_arrays(&self) -> bool {
+        true
+    }
+
+    fn pre_generation(&mut self) {}
+
+    fn post_generation(&mut self) {
+        let guard = self.header_guard();
+        self.header += "/* code generated by the lncodegen.rs please do not edit */\n";
+        self.header += &format!("#ifndef {guard}\n#define {guard}\n\n");
+        self.header += "#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n\n";
+        self.header += &self.tlv_header;
+        self.header += &self.msg_header;
+        self.header += &format!("#endif /* {guard} */\n");
+
+        self.source += "/* code generated by the lncodegen.rs please do not edit */\n";
+        self.source += &format!(
+            "#include \"{}\"\n\n#include <string.h>\n\n",
+            self.header_name
+        );
+        self.source += C_RUNTIME;
+        self.source += "\n";
+        self.source += &self.tlv_source;
+        self.source += &self.msg_source;
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        self.build_tlv_stream_decl(tlv);
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_msg = Some(msg.to_owned());
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let name = Self::c_ident(&msg.msg_name);
+        if self.struct_fields.is_empty() {
+            // an empty struct is not valid C.
+            self.struct_fields += "\tchar unused;\n";
+        }
+        self.msg_header += &format!(
+            "/* The `{}` message, type {}. */\nstruct msg_{name} {{\n{}}};\n\n",
+            msg.msg_name, msg.msg_typ, self.struct_fields
+        );
+        self.msg_header += indoc! {"
+            /* Write the message inside the buffer, return the number
+             * of bytes written or 0 if the buffer is too small. */
+        "};
+        let towire = format!(
+            "size_t towire_{name}(uint8_t *buf, size_t buflen, const struct msg_{name} *msg)"
+        );
+        self.msg_header += &format!("{towire};\n\n");
+        self.msg_header += indoc! {"
+            /* Read the message from the buffer, return false if the message
+             * is malformed, the variable length fields point inside the buffer. */
+        "};
+        let fromwire = format!(
+            "bool fromwire_{name}(const uint8_t *buf, size_t buflen, struct msg_{name} *msg)"
+        );
+        self.msg_header += &format!("{fromwire};\n\n");
+
+        self.msg_source += &format!("{towire}\n{{\n{}}}\n\n", self.encode_fn);
+        self.msg_source += &format!("{fromwire}\n{{\n{}}}\n\n", self.decode_fn);
+        self.struct_fields.clear();
+        self.encode_fn.clear();
+        self.decode_fn.clear();
+        self.curr_msg = None;
+    }
+
+    fn build_decode_fun(&mut self) {
+        let msg = self.curr_msg.as_ref().unwrap();
+        self.decode_fn += "\tstruct wire_reader r = { buf, buflen, 0, true };\n\n";
+        self.decode_fn += &format!(
+            "\tif (wire_get_u16(&r) != {})\n\t\treturn false;\n",
+            msg.msg_typ
+        );
+    }
+
+    fn end_decode_fn(&mut self) {
+        self.decode_fn += "\treturn r.ok;\n";
+    }
+
+    fn build_encode_fn(&mut self) {
+        let msg = self.curr_msg.as_ref().unwrap();
+        self.encode_fn += "\tstruct wire_writer w = { buf, buflen, 0, true };\n\n";
+        self.encode_fn += &format!("\twire_put_u16(&w, {});\n", msg.msg_typ);
+    }
+
+    fn end_encode_fn(&mut self) {
+        self.encode_fn += "\treturn w.ok ? w.pos : 0;\n";
+    }
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.build_int(name, 16);
+        }
+    }
+
+    fn write_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.write_int(name, 16);
+        }
+    }
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_int(name, 32);
+        }
+    }
+
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_int(name, 32);
+        }
+    }
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_int(name, 64);
+        }
+    }
+
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_int(name, 64);
+        }
+    }
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            let name = Self::c_ident(name);
+            self.add_struct_field("bigsize", &format!("uint64_t {name}"));
+            self.decode_fn += &format!("\tmsg->{name} = wire_get_bigsize(&r);\n");
+        }
+    }
+
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            let name = Self::c_ident(name);
+            self.encode_fn += &format!("\twire_put_bigsize(&w, msg->{name});\n");
+        }
+    }
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.build_fixed(field, "chain_hash");
+    }
+
+    fn write_chain_hash(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field, "channel_id");
+    }
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field, "short_channel_id");
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.build_fixed(field, "signature");
+    }
+
+    fn write_signature(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.build_fixed(field, "point");
+    }
+
+    fn write_point(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.build_fixed(field, "sha256");
+    }
+
+    fn write_sha256(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let name = Self::c_ident(name);
+            let elem = LNMsData::element_size(ty).unwrap();
+            let comment = format!("{size}*{ty}");
+            if let Ok(size) = size.parse::<u64>() {
+                let size = size * elem;
+                self.add_struct_field(&comment, &format!("uint8_t {name}[{size}]"));
+                self.decode_fn += &format!("\twire_get_array(&r, msg->{name}, {size});\n");
+                return;
+            }
+            if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let size = Self::c_ident(size);
+                self.add_struct_field(&comment, &format!("const uint8_t *{name}"));
+                self.decode_fn += &format!(
+                    "\tmsg->{name} = wire_get_bytes(&r, {});\n",
+                    Self::array_len(&format!("msg->{size}"), elem)
+                );
+                return;
+            }
+            self.add_struct_field(&comment, &format!("const uint8_t *{name}"));
+            if size == "..." {
+                self.struct_fields += &format!("\tsize_t {name}_len;\n");
+                self.decode_fn += &format!(
+                    "\tmsg->{name}_len = r.ok ? {} : 0;\n",
+                    Self::array_count("(r.len - r.pos)", elem)
+                );
+            } else {
+                // the length was folded inside the bitfield, so it is a u16 prefix.
+                self.struct_fields += &format!("\tuint16_t {name}_len;\n");
+                self.decode_fn += &format!("\tmsg->{name}_len = wire_get_u16(&r);\n");
+            }
+            self.decode_fn += &format!(
+                "\tmsg->{name} = wire_get_bytes(&r, {});\n",
+                Self::array_len(&format!("msg->{name}_len"), elem)
+            );
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let name = Self::c_ident(name);
+            let elem = LNMsData::element_size(ty).unwrap();
+            if let Ok(size) = size.parse::<u64>() {
+                self.encode_fn += &format!("\twire_put_bytes(&w, msg->{name}, {});\n", size * elem);
+                return;
+            }
+            let count = if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                format!("msg->{}", Self::c_ident(size))
+            } else {
+                if size != "..." {
+                    self.encode_fn += &format!("\twire_put_u16(&w, msg->{name}_len);\n");
+                }
+                format!("msg->{name}_len")
+            };
+            self.encode_fn += &format!(
+                "\twire_put_bytes(&w, msg->{name}, {});\n",
+                Self::array_len(&count, elem)
+            );
+        }
+    }
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = Self::c_ident(&field.stream_name);
+        self.add_struct_field(&field.stream_name, &format!("struct tlv_{name} {name}"));
+        self.decode_fn += &format!("\tfromwire_tlv_{name}(&r, &msg->{name});\n");
+    }
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = Self::c_ident(&field.stream_name);
+        self.encode_fn += &format!("\ttowire_tlv_{name}(&w, &msg->{name});\n");
+    }
+}
+
+impl Display for CCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::c::CCodeGen;
+    use crate::codegen::CodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+
+    #[test]
+    fn c_header_source_pair() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,chain_hash,chain_hash,\n\
+                        msgdata,init,num_sigs,u16,\n\
+                        msgdata,init,sigs,signature,num_sigs\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let mut backend = CCodeGen::with_header(&symbol_table, "bolt1.h");
+        backend.generate(&symbol_table).unwrap();
+        let header = backend.header();
+        assert!(header.contains("#ifndef LNCODEGEN_BOLT1_H"), "{header}");
+        assert!(header.contains("\tuint8_t chain_hash[32];\n"), "{header}");
+        assert!(
+            header.contains("\tconst uint8_t *globalfeatures;\n\tuint16_t globalfeatures_len;\n"),
+            "{header}"
+        );
+        assert!(
+            header.contains(
+                "\t/* num_sigs*signature */\n\tconst uint8_t *sigs;\n\tuint16_t sigs_len;\n"
+            ),
+            "{header}"
+        );
+        assert!(
+            header.find("struct tlv_init_tlvs {") < header.find("struct msg_init {"),
+            "{header}"
+        );
+        assert!(
+            header.contains(
+                "size_t towire_init(uint8_t *buf, size_t buflen, const struct msg_init *msg);"
+            ),
+            "{header}"
+        );
+        assert!(
+            header.contains(
+                "bool fromwire_init(const uint8_t *buf, size_t buflen, struct msg_init *msg);"
+            ),
+            "{header}"
+        );
+        let source = backend.source();
+        assert!(source.contains("#include \"bolt1.h\""), "{source}");
+        assert!(!source.contains("malloc"), "{source}");
+        assert!(
+            source.contains("\twire_get_array(&r, msg->chain_hash, 32);\n"),
+            "{source}"
+        );
+        assert!(
+            source.contains("\tmsg->sigs = wire_get_bytes(&r, (size_t)msg->sigs_len * 64);\n"),
+            "{source}"
+        );
+
+        let dir = scratch_dir("c_header_source_pair");
+        fs::write(dir.join("bolt1.h"), header).unwrap();
+        fs::write(dir.join("bolt1.c"), source).unwrap();
+        let main = indoc! {r#"
+            #include <assert.h>
+            #include <stdio.h>
+            #include <string.h>
+
+            #include "bolt1.h"
+
+            int main(void)
+            {
+            	uint8_t features[] = {0x02, 0x0a};
+            	uint8_t chains[64];
+            	uint8_t sigs[128];
+            	uint8_t buf[512];
+            	struct msg_init msg = {0}, decoded;
+            	size_t len;
+
+            	memset(chains, 0x6f, sizeof(chains));
+            	memset(sigs, 0x5a, sizeof(sigs));
+            	msg.sigs = sigs;
+            	msg.sigs_len = 2;
+            	msg.globalfeatures = features;
+            	msg.globalfeatures_len = sizeof(features);
+            	memset(msg.chain_hash, 0x43, sizeof(msg.chain_hash));
+            	msg.init_tlvs.has_networks = true;
+            	msg.init_tlvs.networks = chains;
+            	msg.init_tlvs.networks_len = sizeof(chains);
+
+            	len = towire_init(buf, sizeof(buf), &msg);
+            	assert(len == 234);
+            	assert(towire_init(buf, 10, &msg) == 0);
+            	assert(fromwire_init(buf, len, &decoded));
+            	assert(decoded.globalfeatures_len == 2);
+            	assert(memcmp(decoded.globalfeatures, features, 2) == 0);
+            	assert(memcmp(decoded.chain_hash, msg.chain_hash, 32) == 0);
+            	assert(decoded.sigs_len == 2);
+            	assert(memcmp(decoded.sigs, sigs, 128) == 0);
+            	assert(decoded.init_tlvs.has_networks);
+            	assert(decoded.init_tlvs.networks_len == 64);
+            	assert(memcmp(decoded.init_tlvs.networks, chains, 64) == 0);
+            	assert(!fromwire_init(buf, len - 1, &decoded));
+            	for (size_t i = 0; i < len; i++)
+            		printf("%02x", buf[i]);
+            	printf("\n");
+            	return 0;
+            }
+        "#};
+        fs::write(dir.join("main.c"), main).unwrap();
+        run_cmd(
+            Command::new("cc")
+                .args(["-std=c99", "-Wall", "-Wextra", "-Werror"])
+                .args(["-o", "init", "main.c", "bolt1.c"])
+                .current_dir(&dir),
+        );
+        let stdout = run_cmd(&mut Command::new(dir.join("init")));
+        let expected = format!(
+            "00100002020a{}0002{}0140{}\n",
+            "43".repeat(32),
+            "5a".repeat(128),
+            "6f".repeat(64)
+        );
+        assert_eq!(stdout, expected);
+    }
+}
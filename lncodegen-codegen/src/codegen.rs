@@ -1,9 +1,38 @@
 //! Code Generation module implementation
 use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvStream};
+
+/// Errors found while generating the code of a symbol table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodeGenError {
+    /// A field of the message refers to a tlv stream that is not declared.
+    UnknownTlvStream { msg: String, stream: String },
+    /// An array of the message has elements without a fixed size,
+    /// that the backend can not read as raw bytes.
+    UnsupportedArray {
+        msg: String,
+        field: String,
+        ty: String,
+    },
+}
 
-use log::debug;
+impl Display for CodeGenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownTlvStream { msg, stream } => {
+                write!(f, "`{msg}` refers to the unknown tlv stream `{stream}`")
+            }
+            Self::UnsupportedArray { msg, field, ty } => write!(
+                f,
+                "`{msg}.{field}` is an array of `{ty}`, that has not a fixed size"
+            ),
+        }
+    }
+}
 
-use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvRecord};
+impl std::error::Error for CodeGenError {}
 
 /// Code Gen trait that specify all the basic method to create a new
 /// code generation target to encode and decode a lightning network message.
@@ -15,6 +44,10 @@ pub trait CodeGen<'g> {
 
     /// Build a LN message the correct format for the actual
     /// code generation for the language.
+    ///
+    /// The fields are visited after this call, so a backend that declares
+    /// a type for the message collects its fields while it writes the
+    /// encode and decode functions, and declares the type in `end_msg`.
     fn build_msg(&mut self, _msg: &LNMsg);
 
     /// Close a LN message building process.
@@ -44,6 +77,10 @@ pub trait CodeGen<'g> {
 
     fn write_u64(&mut self, field: &LNMsData);
 
+    fn build_bigsize(&mut self, field: &LNMsData);
+
+    fn write_bigsize(&mut self, field: &LNMsData);
+
     fn build_chain_hash(&mut self, field: &LNMsData);
 
     fn write_chain_hash(&mut self, filed: &LNMsData);
@@ -72,72 +109,122 @@ pub trait CodeGen<'g> {
 
     fn write_bitfiled(&mut self, field: &LNMsData);
 
-    fn build_tlv_stream(&mut self, field: &LNTlvRecord);
-
-    fn write_tlv_stream(&mut self, field: &LNTlvRecord);
+    fn build_tlv_stream(&mut self, field: &LNTlvStream);
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream);
+
+    /// Return the tlv stream that the field `stream` of the message refers to.
+    fn tlv_stream<'t>(
+        msg: &LNMsg,
+        stream: &str,
+        symbol_table: &'t BTreeMap<String, LNMsgType>,
+    ) -> Result<&'t LNTlvStream, CodeGenError> {
+        match symbol_table.get(stream) {
+            Some(LNMsgType::Tlv(tlv)) => Ok(tlv),
+            _ => Err(CodeGenError::UnknownTlvStream {
+                msg: msg.msg_name.to_owned(),
+                stream: stream.to_owned(),
+            }),
+        }
+    }
 
-    fn generate_decode_fn(&mut self, msg: &LNMsg, symbol_table: &'g BTreeMap<String, LNMsgType>) {
+    fn generate_decode_fn(
+        &mut self,
+        msg: &LNMsg,
+        symbol_table: &'g BTreeMap<String, LNMsgType>,
+    ) -> Result<(), CodeGenError> {
         self.build_decode_fun();
         for field in &msg.msg_data {
             match field {
                 LNMsData::Uint16(_) => self.build_u16(field),
                 LNMsData::Uint32(_) => self.build_u32(field),
                 LNMsData::Uint64(_) => self.build_u64(field),
+                LNMsData::BigSize(_) => self.build_bigsize(field),
                 LNMsData::ChainHash(_, _) => self.build_chain_hash(field),
                 LNMsData::ChannelId(_) => self.build_channel_id(field),
                 LNMsData::ShortChannelId(_) => self.build_short_channel_id(field),
                 LNMsData::Signature(_) => self.build_signature(field),
                 LNMsData::Point(_) => self.build_point(field),
                 LNMsData::Sha256(_) => self.build_sha256(field),
-                LNMsData::BitfieldStream(_, _) => self.build_bitfield(field),
+                LNMsData::BitfieldStream(..) => self.build_bitfield(field),
                 LNMsData::TLVinit(tlv_name, _) => {
-                    let tlv = symbol_table.get(tlv_name).unwrap();
-                    if let LNMsgType::Tlv(tlv) = tlv {
-                        self.build_tlv_stream(tlv);
-                    } else {
-                        panic!("Wrong type, we should look for a tlv record {:?}", tlv);
-                    }
+                    let tlv = Self::tlv_stream(msg, tlv_name, symbol_table)?;
+                    self.build_tlv_stream(tlv);
                 }
             }
         }
         self.end_decode_fn();
+        Ok(())
     }
 
-    fn generate_encode_fn(&mut self, msg: &LNMsg, symbol_table: &'g BTreeMap<String, LNMsgType>) {
+    fn generate_encode_fn(
+        &mut self,
+        msg: &LNMsg,
+        symbol_table: &'g BTreeMap<String, LNMsgType>,
+    ) -> Result<(), CodeGenError> {
         self.build_encode_fn();
         for field in &msg.msg_data {
             match field {
                 LNMsData::Uint16(_) => self.write_u16(field),
                 LNMsData::Uint32(_) => self.write_u32(field),
                 LNMsData::Uint64(_) => self.write_u64(field),
+                LNMsData::BigSize(_) => self.write_bigsize(field),
                 LNMsData::ChainHash(_, _) => self.write_chain_hash(field),
                 LNMsData::ChannelId(_) => self.write_channel_id(field),
                 LNMsData::ShortChannelId(_) => self.write_short_channel_id(field),
                 LNMsData::Signature(_) => self.write_signature(field),
                 LNMsData::Point(_) => self.write_point(field),
                 LNMsData::Sha256(_) => self.write_sha256(field),
-                LNMsData::BitfieldStream(_, _) => self.write_bitfiled(field),
+                LNMsData::BitfieldStream(..) => self.write_bitfiled(field),
                 LNMsData::TLVinit(tlv_name, _) => {
-                    let tlv = symbol_table.get(tlv_name).unwrap();
-                    if let LNMsgType::Tlv(tlv) = tlv {
-                        self.write_tlv_stream(tlv);
-                    } else {
-                        panic!("Wrong type inside the TLV Init {:?}", tlv);
-                    }
+                    let tlv = Self::tlv_stream(msg, tlv_name, symbol_table)?;
+                    self.write_tlv_stream(tlv);
                 }
             }
         }
         self.end_encode_fn();
+        Ok(())
     }
 
-    fn generate_msg(&mut self, msg: &LNMsg, symbol_table: &'g BTreeMap<String, LNMsgType>) {
+    fn generate_msg(
+        &mut self,
+        msg: &LNMsg,
+        symbol_table: &'g BTreeMap<String, LNMsgType>,
+    ) -> Result<(), CodeGenError> {
+        if self.raw_arrays() {
+            for field in &msg.msg_data {
+                if let LNMsData::BitfieldStream(name, _, ty) = field {
+                    if LNMsData::element_size(ty).is_none() {
+                        return Err(CodeGenError::UnsupportedArray {
+                            msg: msg.msg_name.to_owned(),
+                            field: name.to_owned(),
+                            ty: ty.to_owned(),
+                        });
+                    }
+                }
+            }
+        }
         self.build_msg(msg);
-        self.generate_encode_fn(msg, symbol_table);
-        self.generate_decode_fn(msg, symbol_table);
+        self.generate_encode_fn(msg, symbol_table)?;
+        self.generate_decode_fn(msg, symbol_table)?;
         self.end_msg(msg);
+        Ok(())
+    }
+
+    /// Return true if the backend reads the arrays as raw bytes,
+    /// that it is `count` times the size of the element. The arrays
+    /// of elements without a fixed size are then reported as an error,
+    /// so `build_bitfield` always finds the [`LNMsData::element_size`].
+    fn raw_arrays(&self) -> bool {
+        false
     }
 
-    fn generate_tlv(&mut self, _tlv: &LNTlvRecord) {}
+    /// Generate the type of a tlv stream, that the messages refer to by name.
+    ///
+    /// The records are kept as raw bytes, and following the "it's ok to be odd"
+    /// rule the decoder rejects the unknown even records while the unknown odd
+    /// ones are preserved or skipped.
+    fn generate_tlv(&mut self, _tlv: &LNTlvStream) {}
 
     fn generate_subtype(&mut self, _subtyp: &LNSubType) {}
 
@@ -145,17 +232,94 @@ pub trait CodeGen<'g> {
 
     fn post_generation(&mut self) {}
 
-    fn generate(&mut self, symbol_table: &'g BTreeMap<String, LNMsgType>) {
+    fn generate(
+        &mut self,
+        symbol_table: &'g BTreeMap<String, LNMsgType>,
+    ) -> Result<(), CodeGenError> {
         self.pre_generation();
         for ast_item in symbol_table.values() {
             match ast_item {
-                LNMsgType::Msg(msg) => self.generate_msg(msg, symbol_table),
+                LNMsgType::Msg(msg) => self.generate_msg(msg, symbol_table)?,
                 LNMsgType::SubType(sub_typ) => self.generate_subtype(sub_typ),
-                LNMsgType::Tlv(_) => {
-                    debug!("tlv on generate method ignore, and need to be ignore!");
-                }
+                LNMsgType::Tlv(tlv) => self.generate_tlv(tlv),
             }
         }
         self.post_generation();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use csvlang::parser::ast::LNMsgType;
+
+    use crate::c::CCodeGen;
+    use crate::codegen::{CodeGen, CodeGenError};
+    use crate::docs::DocsCodeGen;
+    use crate::go::GoCodeGen;
+    use crate::jsonschema::JsonSchemaCodeGen;
+    use crate::kaitai::KaitaiCodeGen;
+    use crate::kotlin::KotlinCodeGen;
+    use crate::proto::ProtoCodeGen;
+    use crate::python::{PythonCodeGen, PythonCodeGenOptions};
+    use crate::rust::RustCodeGen;
+    use crate::swift::SwiftCodeGen;
+    use crate::testing::{init, parse, read_spec};
+    use crate::typescript::TypeScriptCodeGen;
+    use crate::wireshark::WiresharkCodeGen;
+    use crate::zig::ZigCodeGen;
+
+    fn generate<'g, T: CodeGen<'g>>(
+        symbol_table: &'g BTreeMap<String, LNMsgType>,
+    ) -> Result<(), CodeGenError> {
+        T::new(symbol_table).generate(symbol_table)
+    }
+
+    /// Run every backend over the symbol table.
+    fn generate_all(symbol_table: &BTreeMap<String, LNMsgType>) -> Result<(), CodeGenError> {
+        generate::<CCodeGen>(symbol_table)?;
+        generate::<DocsCodeGen>(symbol_table)?;
+        generate::<GoCodeGen>(symbol_table)?;
+        generate::<JsonSchemaCodeGen>(symbol_table)?;
+        generate::<KaitaiCodeGen>(symbol_table)?;
+        generate::<KotlinCodeGen>(symbol_table)?;
+        generate::<ProtoCodeGen>(symbol_table)?;
+        generate::<PythonCodeGen>(symbol_table)?;
+        let options = PythonCodeGenOptions { standalone: true };
+        PythonCodeGen::with_options(symbol_table, options).generate(symbol_table)?;
+        generate::<RustCodeGen>(symbol_table)?;
+        generate::<SwiftCodeGen>(symbol_table)?;
+        generate::<TypeScriptCodeGen>(symbol_table)?;
+        generate::<WiresharkCodeGen>(symbol_table)?;
+        generate::<ZigCodeGen>(symbol_table)
+    }
+
+    #[test]
+    fn generate_every_backend_bolt4() {
+        init();
+        let symbol_table = parse(&read_spec("bolt4.csv"));
+        generate_all(&symbol_table).unwrap();
+    }
+
+    #[test]
+    fn generate_unknown_tlv_stream() {
+        init();
+        let contents = "msgtype,foo,1\n\
+                        msgdata,foo,tlvs,foo_tlvs,\n";
+        let symbol_table = parse(contents);
+        let err = generate_all(&symbol_table).unwrap_err();
+        assert_eq!(
+            err,
+            CodeGenError::UnknownTlvStream {
+                msg: "foo".to_owned(),
+                stream: "foo_tlvs".to_owned(),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "`foo` refers to the unknown tlv stream `foo_tlvs`"
+        );
     }
 }
@@ -0,0 +1,446 @@
+//! Difference between two revisions of the lightning
+//! network specification.
+//!
+//! The symbol tables are compared item by item, and every change
+//! is classified as compatible or incompatible with the nodes that
+//! are running the old revision, following the "it's ok to be odd" rule.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvRecord, LNTlvStream};
+
+/// A single change between the two revisions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecChange {
+    /// The item changed, e.g. "message `init`".
+    pub item: String,
+    pub description: String,
+    /// True if a node that is running the old revision can
+    /// talk with a node that is running the new one.
+    pub compatible: bool,
+}
+
+impl Display for SpecChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.item, self.description)
+    }
+}
+
+/// All the changes between two revisions of the spec.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpecDiff {
+    pub changes: Vec<SpecChange>,
+}
+
+impl SpecDiff {
+    pub fn new(old: &BTreeMap<String, LNMsgType>, new: &BTreeMap<String, LNMsgType>) -> Self {
+        let mut diff = SpecDiff::default();
+        for (name, old_item) in old {
+            match (old_item, new.get(name)) {
+                // a node ignores the odd messages that it does not understand.
+                (LNMsgType::Msg(msg), None) => {
+                    let item = Self::item_name(old_item, name);
+                    let typ = msg.msg_typ;
+                    let parity = if typ.is_multiple_of(2) { "even" } else { "odd" };
+                    let description = format!("removed, {parity} type {typ}");
+                    diff.push(&item, &description, !typ.is_multiple_of(2));
+                }
+                (_, None) => {
+                    let item = Self::item_name(old_item, name);
+                    diff.push(&item, "removed", false);
+                }
+                (LNMsgType::Msg(old_msg), Some(LNMsgType::Msg(new_msg))) => {
+                    diff.diff_msg(old_msg, new_msg)
+                }
+                (LNMsgType::Tlv(old_tlv), Some(LNMsgType::Tlv(new_tlv))) => {
+                    diff.diff_tlv(old_tlv, new_tlv)
+                }
+                (LNMsgType::SubType(old_ty), Some(LNMsgType::SubType(new_ty))) => {
+                    diff.diff_subtype(old_ty, new_ty)
+                }
+                (_, Some(new_item)) => {
+                    let item = Self::item_name(old_item, name);
+                    let description = format!("is now a {}", Self::item_name(new_item, name));
+                    diff.push(&item, &description, false);
+                }
+            }
+        }
+        for (name, new_item) in new {
+            if old.contains_key(name) {
+                continue;
+            }
+            let item = Self::item_name(new_item, name);
+            match new_item {
+                // a node that does not understand an even message
+                // closes the connection.
+                LNMsgType::Msg(msg) if msg.msg_typ.is_multiple_of(2) => diff.push(
+                    &item,
+                    &format!("added with even type {}", msg.msg_typ),
+                    false,
+                ),
+                LNMsgType::Msg(msg) => {
+                    diff.push(&item, &format!("added with odd type {}", msg.msg_typ), true)
+                }
+                _ => diff.push(&item, "added", true),
+            }
+        }
+        diff
+    }
+
+    fn item_name(item: &LNMsgType, name: &str) -> String {
+        match item {
+            LNMsgType::Msg(_) => format!("message `{name}`"),
+            LNMsgType::Tlv(_) => format!("tlv stream `{name}`"),
+            LNMsgType::SubType(_) => format!("subtype `{name}`"),
+        }
+    }
+
+    fn push(&mut self, item: &str, description: &str, compatible: bool) {
+        self.changes.push(SpecChange {
+            item: item.to_owned(),
+            description: description.to_owned(),
+            compatible,
+        });
+    }
+
+    fn diff_msg(&mut self, old: &LNMsg, new: &LNMsg) {
+        let item = format!("message `{}`", old.msg_name);
+        if old.msg_typ != new.msg_typ {
+            let description = format!("type changed from {} to {}", old.msg_typ, new.msg_typ);
+            self.push(&item, &description, false);
+        }
+        self.diff_fields(&item, &old.msg_data, &new.msg_data, true);
+    }
+
+    fn diff_subtype(&mut self, old: &LNSubType, new: &LNSubType) {
+        let item = format!("subtype `{}`", old.ty);
+        self.diff_fields(&item, &old.ty_data, &new.ty_data, false);
+    }
+
+    /// Compare the fields of a message or of a subtype, the only compatible
+    /// changes are the renames and the fields appended at the end of a message,
+    /// because a node ignores the data after the fields that it expects.
+    fn diff_fields(&mut self, item: &str, old: &[LNMsData], new: &[LNMsData], is_msg: bool) {
+        // the old fields are matched by position when they keep
+        // their types, so a rename does not hide an appended field.
+        let is_prefix = new.len() >= old.len()
+            && old
+                .iter()
+                .zip(new)
+                .all(|(old, new)| old.csv_type() == new.csv_type());
+        if is_prefix {
+            // the old node reads a tlv stream or an array till the end
+            // of the message, so it does not ignore the new fields.
+            let is_trailing = is_msg
+                && !old.last().is_some_and(|field| match field {
+                    LNMsData::TLVinit(..) => true,
+                    LNMsData::BitfieldStream(_, size, _) => size == "...",
+                    _ => false,
+                });
+            for (old, new) in old.iter().zip(new) {
+                if old.name() != new.name() {
+                    let description = format!("field `{}` renamed to `{}`", old.name(), new.name());
+                    self.push(item, &description, true);
+                }
+            }
+            for field in &new[old.len()..] {
+                match field {
+                    LNMsData::TLVinit(stream, _) => {
+                        let description = format!("tlv stream `{stream}` appended");
+                        self.push(item, &description, is_trailing);
+                    }
+                    _ => {
+                        let description =
+                            format!("field `{}` ({}) appended", field.name(), field.csv_type());
+                        self.push(item, &description, is_trailing);
+                    }
+                }
+            }
+            return;
+        }
+
+        for old_field in old {
+            match new.iter().find(|field| field.name() == old_field.name()) {
+                Some(new_field) if new_field.csv_type() != old_field.csv_type() => {
+                    let description = format!(
+                        "field `{}` changed type from {} to {}",
+                        old_field.name(),
+                        old_field.csv_type(),
+                        new_field.csv_type()
+                    );
+                    self.push(item, &description, false);
+                }
+                Some(_) => {}
+                None => {
+                    let description = format!("field `{}` removed", old_field.name());
+                    self.push(item, &description, false);
+                }
+            }
+        }
+        for new_field in new {
+            if !old.iter().any(|field| field.name() == new_field.name()) {
+                let description = format!(
+                    "field `{}` ({}) added",
+                    new_field.name(),
+                    new_field.csv_type()
+                );
+                self.push(item, &description, false);
+            }
+        }
+
+        let old_order = old
+            .iter()
+            .map(LNMsData::name)
+            .filter(|name| new.iter().any(|field| field.name() == *name))
+            .collect::<Vec<_>>();
+        let new_order = new
+            .iter()
+            .map(LNMsData::name)
+            .filter(|name| old.iter().any(|field| field.name() == *name))
+            .collect::<Vec<_>>();
+        if old_order != new_order {
+            let description = format!(
+                "fields reordered from ({}) to ({})",
+                old_order.join(", "),
+                new_order.join(", ")
+            );
+            self.push(item, &description, false);
+        }
+    }
+
+    /// Compare the records of a tlv stream by type, a node ignores
+    /// the odd records that it does not understand.
+    fn diff_tlv(&mut self, old: &LNTlvStream, new: &LNTlvStream) {
+        let item = format!("tlv stream `{}`", old.stream_name);
+        let find = |records: &[LNTlvRecord], typ: u64| {
+            records
+                .iter()
+                .find(|record| record.type_len == typ)
+                .cloned()
+        };
+        for old_record in &old.records {
+            let typ = old_record.type_len;
+            let parity = if typ.is_multiple_of(2) { "even" } else { "odd" };
+            let Some(new_record) = find(&new.records, typ) else {
+                let description = format!(
+                    "record `{}` removed, {parity} type {typ}",
+                    old_record.type_name
+                );
+                self.push(&item, &description, !typ.is_multiple_of(2));
+                continue;
+            };
+            if old_record.type_name != new_record.type_name {
+                let description = format!(
+                    "record `{}` renamed to `{}`",
+                    old_record.type_name, new_record.type_name
+                );
+                self.push(&item, &description, true);
+            }
+            let entries = |record: &LNTlvRecord| {
+                record
+                    .record_entry
+                    .iter()
+                    .map(|entry| format!("{}: {}", entry.entry_name, entry.csv_type()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let (old_entries, new_entries) = (entries(old_record), entries(&new_record));
+            if old_entries != new_entries {
+                let description = format!(
+                    "record `{}` changed from ({old_entries}) to ({new_entries})",
+                    new_record.type_name
+                );
+                self.push(&item, &description, false);
+            }
+        }
+        for new_record in &new.records {
+            let typ = new_record.type_len;
+            if find(&old.records, typ).is_some() {
+                continue;
+            }
+            let parity = if typ.is_multiple_of(2) { "even" } else { "odd" };
+            let description = format!(
+                "record `{}` added, {parity} type {typ}",
+                new_record.type_name
+            );
+            self.push(&item, &description, !typ.is_multiple_of(2));
+        }
+    }
+
+    /// Return true if there are no incompatible changes.
+    pub fn is_compatible(&self) -> bool {
+        self.changes.iter().all(|change| change.compatible)
+    }
+
+    pub fn compatible(&self) -> impl Iterator<Item = &SpecChange> {
+        self.changes.iter().filter(|change| change.compatible)
+    }
+
+    pub fn incompatible(&self) -> impl Iterator<Item = &SpecChange> {
+        self.changes.iter().filter(|change| !change.compatible)
+    }
+}
+
+impl Display for SpecDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        let incompatible = self.incompatible().collect::<Vec<_>>();
+        if !incompatible.is_empty() {
+            writeln!(f, "Backward incompatible changes:")?;
+            for change in incompatible {
+                writeln!(f, "  - {change}")?;
+            }
+        }
+        let compatible = self.compatible().collect::<Vec<_>>();
+        if !compatible.is_empty() {
+            writeln!(f, "Compatible changes:")?;
+            for change in compatible {
+                writeln!(f, "  - {change}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::SpecDiff;
+    use crate::testing::{init, parse};
+
+    #[test]
+    fn spec_diff() {
+        init();
+        let old = parse(
+            "msgtype,init,16\n\
+             msgdata,init,gflen,u16,\n\
+             msgdata,init,globalfeatures,byte,gflen\n\
+             msgdata,init,tlvs,init_tlvs,\n\
+             tlvtype,init_tlvs,networks,1\n\
+             tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+             msgtype,ping,18\n\
+             msgdata,ping,num_pong_bytes,u16,\n\
+             msgdata,ping,byteslen,u16,\n\
+             msgdata,ping,ignored,byte,byteslen\n",
+        );
+        let new = parse(
+            "msgtype,init,16\n\
+             msgdata,init,gflen,u16,\n\
+             msgdata,init,globalfeatures,byte,gflen\n\
+             msgdata,init,tlvs,init_tlvs,\n\
+             tlvtype,init_tlvs,networks,1\n\
+             tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+             tlvtype,init_tlvs,remote_addr,3\n\
+             tlvdata,init_tlvs,remote_addr,data,byte,...\n\
+             tlvtype,init_tlvs,features,4\n\
+             tlvdata,init_tlvs,features,data,byte,...\n\
+             msgtype,ping,18\n\
+             msgdata,ping,num_pong_bytes,u32,\n\
+             msgdata,ping,byteslen,u16,\n\
+             msgdata,ping,ignored,byte,byteslen\n\
+             msgtype,peer_storage,7\n\
+             msgdata,peer_storage,len,u16,\n\
+             msgdata,peer_storage,blob,byte,len\n",
+        );
+        let diff = SpecDiff::new(&old, &new);
+        assert!(!diff.is_compatible());
+        let report = diff.to_string();
+        assert_eq!(
+            report,
+            "Backward incompatible changes:\n\
+             \x20 - tlv stream `init_tlvs`: record `features` added, even type 4\n\
+             \x20 - message `ping`: field `num_pong_bytes` changed type from u16 to u32\n\
+             Compatible changes:\n\
+             \x20 - tlv stream `init_tlvs`: record `remote_addr` added, odd type 3\n\
+             \x20 - message `peer_storage`: added with odd type 7\n",
+            "{report}"
+        );
+        assert_eq!(SpecDiff::new(&old, &old).to_string(), "No changes\n");
+    }
+
+    #[test]
+    fn spec_diff_rename_and_append() {
+        init();
+        let old = parse(
+            "msgtype,open,32\n\
+             msgdata,open,temporary_channel_id,channel_id,\n\
+             msgdata,open,funding_satoshis,u64,\n",
+        );
+        let new = parse(
+            "msgtype,open,32\n\
+             msgdata,open,channel_id,channel_id,\n\
+             msgdata,open,funding_satoshis,u64,\n\
+             msgdata,open,tlvs,open_tlvs,\n\
+             tlvtype,open_tlvs,upfront_shutdown_script,0\n\
+             tlvdata,open_tlvs,upfront_shutdown_script,shutdown_scriptpubkey,byte,...\n",
+        );
+        let diff = SpecDiff::new(&old, &new);
+        let report = diff.to_string();
+        assert_eq!(
+            report,
+            "Compatible changes:\n\
+             \x20 - message `open`: field `temporary_channel_id` renamed to `channel_id`\n\
+             \x20 - message `open`: tlv stream `open_tlvs` appended\n\
+             \x20 - tlv stream `open_tlvs`: added\n",
+            "{report}"
+        );
+        assert!(diff.is_compatible());
+    }
+
+    #[test]
+    fn spec_diff_trailing_and_odd_removal() {
+        init();
+        let old = parse(
+            "msgtype,ping,18\n\
+             msgdata,ping,num_pong_bytes,u16,\n\
+             msgdata,ping,byteslen,u16,\n\
+             msgdata,ping,ignored,byte,byteslen\n\
+             msgtype,init,16\n\
+             msgdata,init,tlvs,init_tlvs,\n\
+             tlvtype,init_tlvs,networks,1\n\
+             tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+             tlvtype,init_tlvs,remote_addr,3\n\
+             tlvdata,init_tlvs,remote_addr,data,byte,...\n\
+             tlvtype,init_tlvs,features,4\n\
+             tlvdata,init_tlvs,features,data,byte,...\n\
+             msgtype,peer_storage,7\n\
+             msgdata,peer_storage,len,u16,\n\
+             msgdata,peer_storage,blob,byte,len\n\
+             msgtype,stfu,2\n\
+             msgdata,stfu,channel_id,channel_id,\n\
+             subtype,witness\n\
+             subtypedata,witness,len,u16,\n",
+        );
+        let new = parse(
+            "msgtype,ping,18\n\
+             msgdata,ping,num_pong_bytes,u16,\n\
+             msgdata,ping,byteslen,u16,\n\
+             msgdata,ping,ignored,byte,byteslen\n\
+             msgdata,ping,flags,u32,\n\
+             msgtype,init,16\n\
+             msgdata,init,tlvs,init_tlvs,\n\
+             msgdata,init,flags,u32,\n\
+             tlvtype,init_tlvs,networks,1\n\
+             tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+             subtype,witness\n\
+             subtypedata,witness,len,u16,\n\
+             subtypedata,witness,flags,u32,\n",
+        );
+        let diff = SpecDiff::new(&old, &new);
+        let report = diff.to_string();
+        assert_eq!(
+            report,
+            "Backward incompatible changes:\n\
+             \x20 - message `init`: field `flags` (u32) appended\n\
+             \x20 - tlv stream `init_tlvs`: record `features` removed, even type 4\n\
+             \x20 - message `stfu`: removed, even type 2\n\
+             \x20 - subtype `witness`: field `flags` (u32) appended\n\
+             Compatible changes:\n\
+             \x20 - tlv stream `init_tlvs`: record `remote_addr` removed, odd type 3\n\
+             \x20 - message `peer_storage`: removed, odd type 7\n\
+             \x20 - message `ping`: field `flags` (u32) appended\n",
+            "{report}"
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Human readable documentation of the lightning
+//! network specification, rendered as markdown or html.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// The format of the documentation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DocsFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl DocsFormat {
+    fn escape(&self, text: &str) -> String {
+        match self {
+            Self::Markdown => text.replace('|', "\\|"),
+            Self::Html => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        }
+    }
+
+    fn code(&self, text: &str) -> String {
+        match self {
+            Self::Markdown => format!("`{}`", self.escape(text)),
+            Self::Html => format!("<code>{}</code>", self.escape(text)),
+        }
+    }
+
+    /// Link to an anchor of the document, or to an external url.
+    fn link(&self, text: &str, target: &str) -> String {
+        match self {
+            Self::Markdown => format!("[{text}]({target})"),
+            Self::Html => format!("<a href=\"{target}\">{text}</a>"),
+        }
+    }
+
+    fn heading(&self, level: usize, anchor: Option<&str>, text: &str) -> String {
+        match (self, anchor) {
+            (Self::Markdown, Some(anchor)) => {
+                format!("<a id=\"{anchor}\"></a>\n{} {text}\n\n", "#".repeat(level))
+            }
+            (Self::Markdown, None) => format!("{} {text}\n\n", "#".repeat(level)),
+            (Self::Html, Some(anchor)) => format!("<h{level} id=\"{anchor}\">{text}</h{level}>\n"),
+            (Self::Html, None) => format!("<h{level}>{text}</h{level}>\n"),
+        }
+    }
+
+    fn paragraph(&self, text: &str) -> String {
+        match self {
+            Self::Markdown => format!("{text}\n\n"),
+            Self::Html => format!("<p>{text}</p>\n"),
+        }
+    }
+
+    fn table(&self, headers: &[&str], rows: &[Vec<String>]) -> String {
+        match self {
+            Self::Markdown => {
+                let mut code = format!("| {} |\n", headers.join(" | "));
+                code += &format!("|{}\n", "---|".repeat(headers.len()));
+                for row in rows {
+                    code += &format!("| {} |\n", row.join(" | "));
+                }
+                code += "\n";
+                code
+            }
+            Self::Html => {
+                let mut code = String::from("<table>\n  <tr>");
+                for header in headers {
+                    code += &format!("<th>{header}</th>");
+                }
+                code += "</tr>\n";
+                for row in rows {
+                    code += "  <tr>";
+                    for cell in row {
+                        code += &format!("<td>{cell}</td>");
+                    }
+                    code += "</tr>\n";
+                }
+                code += "</table>\n";
+                code
+            }
+        }
+    }
+}
+
+fn parity(typ: u64) -> &'static str {
+    if typ.is_multiple_of(2) {
+        "even"
+    } else {
+        "odd"
+    }
+}
+
+fn msg_anchor(name: &str) -> String {
+    format!("msg-{name}")
+}
+
+fn tlv_anchor(name: &str) -> String {
+    format!("tlv-{name}")
+}
+
+fn subtype_anchor(name: &str) -> String {
+    format!("subtype-{name}")
+}
+
+pub struct DocsCodeGen {
+    format: DocsFormat,
+    /// The messages that embed a tlv stream, by stream name.
+    used_by: BTreeMap<String, Vec<String>>,
+    subtypes: BTreeSet<String>,
+    /// The fields of the message or subtype that we are building.
+    curr_fields: Vec<LNMsData>,
+    rows: Vec<Vec<String>>,
+    /// The name and the section of the messages, by type number.
+    msgs: BTreeMap<u64, (String, String)>,
+    tlvs: String,
+    subtype_content: String,
+    file_content: String,
+}
+
+impl DocsCodeGen {
+    /// Build a new documentation backend that renders the format provided.
+    pub fn with_format(symbol_table: &BTreeMap<String, LNMsgType>, format: DocsFormat) -> Self {
+        let mut used_by: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut subtypes = BTreeSet::new();
+        for item in symbol_table.values() {
+            match item {
+                LNMsgType::Msg(msg) => {
+                    for field in &msg.msg_data {
+                        if let LNMsData::TLVinit(stream, _) = field {
+                            used_by
+                                .entry(stream.to_owned())
+                                .or_default()
+                                .push(msg.msg_name.to_owned());
+                        }
+                    }
+                }
+                LNMsgType::SubType(subtyp) => {
+                    subtypes.insert(subtyp.ty.to_owned());
+                }
+                LNMsgType::Tlv(_) => {}
+            }
+        }
+        DocsCodeGen {
+            format,
+            used_by,
+            subtypes,
+            curr_fields: Vec::new(),
+            rows: Vec::new(),
+            msgs: BTreeMap::new(),
+            tlvs: String::new(),
+            subtype_content: String::new(),
+            file_content: String::new(),
+        }
+    }
+
+    /// Render a type of a tlv record entry, linking the subtypes.
+    fn entry_type(&self, ty: &str, csv_type: &str) -> String {
+        let code = self.format.code(csv_type);
+        if self.subtypes.contains(ty) {
+            return self.format.link(&code, &format!("#{}", subtype_anchor(ty)));
+        }
+        code
+    }
+
+    fn add_field(&mut self, field: &LNMsData) {
+        let fmt = self.format;
+        let name = fmt.code(field.name());
+        let csv_type = fmt.code(&field.csv_type());
+        let row = match field {
+            LNMsData::TLVinit(stream, _) => vec![
+                fmt.code(stream),
+                fmt.link(&csv_type, &format!("#{}", tlv_anchor(stream))),
+                "variable".to_owned(),
+            ],
+            LNMsData::BigSize(_) => vec![name, csv_type, "1 to 9".to_owned()],
+            LNMsData::BitfieldStream(_, size, _) if size == "..." => {
+                vec![name, csv_type, "rest of the message".to_owned()]
+            }
+            LNMsData::BitfieldStream(_, size, _)
+                if size.parse::<u64>().is_err()
+                    && !LNMsData::is_length_field(&self.curr_fields, size) =>
+            {
+                // the u16 length was folded inside the bitfield.
+                self.rows
+                    .push(vec![fmt.code(size), fmt.code("u16"), "2".to_owned()]);
+                vec![name, csv_type, fmt.code(size)]
+            }
+            LNMsData::BitfieldStream(_, size, _) if size.parse::<u64>().is_err() => {
+                vec![name, csv_type, fmt.code(size)]
+            }
+            _ => vec![name, csv_type, field.fixed_size().unwrap().to_string()],
+        };
+        self.rows.push(row);
+    }
+
+    fn fields_table(&mut self) -> String {
+        let rows = std::mem::take(&mut self.rows);
+        if rows.is_empty() {
+            return self.format.paragraph("No fields.");
+        }
+        self.format.table(&["Field", "Type", "Size"], &rows)
+    }
+}
+
+impl<'g> CodeGen<'g> for DocsCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        DocsCodeGen::with_format(symbol_table, DocsFormat::Markdown)
+    }
+
+    fn pre_generation(&mut self) {}
+
+    fn post_generation(&mut self) {
+        let fmt = self.format;
+        let title = "Lightning network messages";
+        if fmt == DocsFormat::Html {
+            self.file_content += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+            self.file_content += &format!("<title>{title}</title>\n</head>\n<body>\n");
+        }
+        self.file_content += &fmt.heading(1, None, title);
+        self.file_content +=
+            &fmt.paragraph("Code generated by the lncodegen.rs please do not edit.");
+
+        self.file_content += &fmt.heading(2, None, "Messages");
+        let index = self
+            .msgs
+            .iter()
+            .map(|(typ, (name, _))| {
+                vec![
+                    typ.to_string(),
+                    fmt.link(&fmt.code(name), &format!("#{}", msg_anchor(name))),
+                    parity(*typ).to_owned(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        self.file_content += &fmt.table(&["Type", "Message", "Even/odd"], &index);
+        for (_, section) in self.msgs.values() {
+            self.file_content += section;
+        }
+
+        if !self.tlvs.is_empty() {
+            self.file_content += &fmt.heading(2, None, "TLV streams");
+            self.file_content += &self.tlvs;
+        }
+        if !self.subtype_content.is_empty() {
+            self.file_content += &fmt.heading(2, None, "Subtypes");
+            self.file_content += &self.subtype_content;
+        }
+        if fmt == DocsFormat::Html {
+            self.file_content += "</body>\n</html>\n";
+        }
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        let fmt = self.format;
+        let name = &tlv.stream_name;
+        let mut code = fmt.heading(3, Some(&tlv_anchor(name)), &fmt.code(name));
+        if let Some(msgs) = self.used_by.get(name) {
+            let links = msgs
+                .iter()
+                .map(|msg| fmt.link(&fmt.code(msg), &format!("#{}", msg_anchor(msg))))
+                .collect::<Vec<_>>()
+                .join(", ");
+            code += &fmt.paragraph(&format!("Used by {links}."));
+        }
+        let rows = tlv
+            .records
+            .iter()
+            .map(|record| {
+                let fields = record
+                    .record_entry
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "{}: {}",
+                            fmt.code(&entry.entry_name),
+                            self.entry_type(&entry.entry_ty, &entry.csv_type())
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                vec![
+                    record.type_len.to_string(),
+                    fmt.code(&record.type_name),
+                    parity(record.type_len).to_owned(),
+                    fields,
+                ]
+            })
+            .collect::<Vec<_>>();
+        code += &fmt.table(&["Type", "Record", "Even/odd", "Fields"], &rows);
+        self.tlvs += &code;
+    }
+
+    fn generate_subtype(&mut self, subtyp: &LNSubType) {
+        self.curr_fields = subtyp.ty_data.clone();
+        for field in &subtyp.ty_data {
+            self.add_field(field);
+        }
+        let fmt = self.format;
+        let mut code = fmt.heading(3, Some(&subtype_anchor(&subtyp.ty)), &fmt.code(&subtyp.ty));
+        code += &self.fields_table();
+        self.subtype_content += &code;
+        self.curr_fields.clear();
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_fields = msg.msg_data.clone();
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let fmt = self.format;
+        let name = &msg.msg_name;
+        let mut code = fmt.heading(3, Some(&msg_anchor(name)), &fmt.code(name));
+        code += &fmt.paragraph(&format!("Type {}, {}.", msg.msg_typ, parity(msg.msg_typ)));
+        if let Some(doc) = &msg.doc {
+            for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+                code += &fmt.paragraph(&fmt.escape(paragraph));
+            }
+            let see = fmt.link(&format!("{}: {}", doc.bolt, doc.section), &doc.link);
+            code += &fmt.paragraph(&format!("See {see}."));
+        }
+        code += &self.fields_table();
+        self.msgs.insert(msg.msg_typ, (name.to_owned(), code));
+        self.curr_fields.clear();
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u16(&mut self, _: &LNMsData) {}
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u32(&mut self, _: &LNMsData) {}
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u64(&mut self, _: &LNMsData) {}
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bigsize(&mut self, _: &LNMsData) {}
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_chain_hash(&mut self, _: &LNMsData) {}
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_short_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_signature(&mut self, _: &LNMsData) {}
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_point(&mut self, _: &LNMsData) {}
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_sha256(&mut self, _: &LNMsData) {}
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bitfiled(&mut self, _: &LNMsData) {}
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let field = LNMsData::TLVinit(field.stream_name.to_owned(), field.stream_name.to_owned());
+        self.add_field(&field);
+    }
+
+    fn write_tlv_stream(&mut self, _: &LNTlvStream) {}
+}
+
+impl Display for DocsCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codegen::CodeGen;
+    use crate::docs::{DocsCodeGen, DocsFormat};
+    use crate::testing::{init, parse};
+
+    #[test]
+    fn markdown_docs() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let mut backend = DocsCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("| 16 | [`init`](#msg-init) | even |\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("| `gflen` | `u16` | 2 |\n| `globalfeatures` | `byte[gflen]` | `gflen` |\n| `init_tlvs` | [`init_tlvs`](#tlv-init_tlvs) | variable |\n"),
+            "{code}"
+        );
+        assert!(code.contains("Used by [`init`](#msg-init)."), "{code}");
+        assert!(
+            code.contains("| 1 | `networks` | odd | `chains`: `chain_hash[...]` |\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    fn html_docs() {
+        init();
+        let contents = "msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16,\n";
+        let symbol_table = parse(contents);
+        let mut backend = DocsCodeGen::with_format(&symbol_table, DocsFormat::Html);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("<h3 id=\"msg-ping\"><code>ping</code></h3>\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  <tr><td><code>num_pong_bytes</code></td><td><code>u16</code></td><td>2</td></tr>\n"),
+            "{code}"
+        );
+    }
+}
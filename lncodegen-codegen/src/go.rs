@@ -0,0 +1,774 @@
+//! Go code generation for the lightning
+//! network specification.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated messages to encode and decode
+/// the lightning network types.
+const GO_RUNTIME: &str = indoc! {r#"
+    // Message is a lightning network message that can be encoded and decoded.
+    type Message interface {
+    	MsgType() uint16
+    	Encode(w io.Writer) error
+    	Decode(r io.Reader) error
+    }
+
+    // TlvRecord is a single record of a tlv stream.
+    type TlvRecord struct {
+    	Type  uint64
+    	Value []byte
+    }
+
+    func writeUint16(w io.Writer, value uint16) error {
+    	return binary.Write(w, binary.BigEndian, value)
+    }
+
+    func writeUint32(w io.Writer, value uint32) error {
+    	return binary.Write(w, binary.BigEndian, value)
+    }
+
+    func writeUint64(w io.Writer, value uint64) error {
+    	return binary.Write(w, binary.BigEndian, value)
+    }
+
+    func writeBytes(w io.Writer, value []byte) error {
+    	_, err := w.Write(value)
+    	return err
+    }
+
+    // writeVarBytes writes the elements of elemSize bytes
+    // prefixed by their u16 count.
+    func writeVarBytes(w io.Writer, value []byte, elemSize int) error {
+    	if len(value)%elemSize != 0 {
+    		return fmt.Errorf("%d bytes are not elements of %d bytes", len(value), elemSize)
+    	}
+    	count := len(value) / elemSize
+    	if count > 0xFFFF {
+    		return fmt.Errorf("%d elements do not fit a u16 count", count)
+    	}
+    	if err := writeUint16(w, uint16(count)); err != nil {
+    		return err
+    	}
+    	return writeBytes(w, value)
+    }
+
+    func readUint16(r io.Reader) (uint16, error) {
+    	var value uint16
+    	err := binary.Read(r, binary.BigEndian, &value)
+    	return value, err
+    }
+
+    func readUint32(r io.Reader) (uint32, error) {
+    	var value uint32
+    	err := binary.Read(r, binary.BigEndian, &value)
+    	return value, err
+    }
+
+    func readUint64(r io.Reader) (uint64, error) {
+    	var value uint64
+    	err := binary.Read(r, binary.BigEndian, &value)
+    	return value, err
+    }
+
+    func readBytes(r io.Reader, size int) ([]byte, error) {
+    	value := make([]byte, size)
+    	_, err := io.ReadFull(r, value)
+    	return value, err
+    }
+
+    // readVarBytes reads the elements of elemSize bytes
+    // prefixed by their u16 count.
+    func readVarBytes(r io.Reader, elemSize int) ([]byte, error) {
+    	count, err := readUint16(r)
+    	if err != nil {
+    		return nil, err
+    	}
+    	return readBytes(r, int(count)*elemSize)
+    }
+
+    func writeBigSize(w io.Writer, value uint64) error {
+    	switch {
+    	case value < 0xFD:
+    		return writeBytes(w, []byte{byte(value)})
+    	case value <= 0xFFFF:
+    		if err := writeBytes(w, []byte{0xFD}); err != nil {
+    			return err
+    		}
+    		return writeUint16(w, uint16(value))
+    	case value <= 0xFFFFFFFF:
+    		if err := writeBytes(w, []byte{0xFE}); err != nil {
+    			return err
+    		}
+    		return writeUint32(w, uint32(value))
+    	default:
+    		if err := writeBytes(w, []byte{0xFF}); err != nil {
+    			return err
+    		}
+    		return writeUint64(w, value)
+    	}
+    }
+
+    func readBigSize(r io.Reader) (uint64, error) {
+    	prefix, err := readBytes(r, 1)
+    	if err != nil {
+    		return 0, err
+    	}
+    	var value, min uint64
+    	switch prefix[0] {
+    	case 0xFD:
+    		v, err := readUint16(r)
+    		if err != nil {
+    			return 0, err
+    		}
+    		value, min = uint64(v), 0xFD
+    	case 0xFE:
+    		v, err := readUint32(r)
+    		if err != nil {
+    			return 0, err
+    		}
+    		value, min = uint64(v), 0x10000
+    	case 0xFF:
+    		v, err := readUint64(r)
+    		if err != nil {
+    			return 0, err
+    		}
+    		value, min = v, 0x100000000
+    	default:
+    		return uint64(prefix[0]), nil
+    	}
+    	if value < min {
+    		return 0, fmt.Errorf("bigsize is not minimally encoded")
+    	}
+    	return value, nil
+    }
+
+    // encodeTlvStream writes the records ordered by type.
+    func encodeTlvStream(w io.Writer, records []TlvRecord) error {
+    	sort.Slice(records, func(i, j int) bool {
+    		return records[i].Type < records[j].Type
+    	})
+    	for _, record := range records {
+    		if err := writeBigSize(w, record.Type); err != nil {
+    			return err
+    		}
+    		if err := writeBigSize(w, uint64(len(record.Value))); err != nil {
+    			return err
+    		}
+    		if err := writeBytes(w, record.Value); err != nil {
+    			return err
+    		}
+    	}
+    	return nil
+    }
+
+    // decodeTlvStream reads the records till the end of the stream.
+    func decodeTlvStream(r io.Reader) ([]TlvRecord, error) {
+    	data, err := io.ReadAll(r)
+    	if err != nil {
+    		return nil, err
+    	}
+    	reader := bytes.NewReader(data)
+    	records := make([]TlvRecord, 0)
+    	for reader.Len() > 0 {
+    		typ, err := readBigSize(reader)
+    		if err != nil {
+    			return nil, err
+    		}
+    		if len(records) > 0 && typ <= records[len(records)-1].Type {
+    			return nil, fmt.Errorf("tlv types are not strictly increasing")
+    		}
+    		size, err := readBigSize(reader)
+    		if err != nil {
+    			return nil, err
+    		}
+    		if size > uint64(reader.Len()) {
+    			return nil, io.ErrUnexpectedEOF
+    		}
+    		value, err := readBytes(reader, int(size))
+    		if err != nil {
+    			return nil, err
+    		}
+    		records = append(records, TlvRecord{Type: typ, Value: value})
+    	}
+    	return records, nil
+    }
+"#};
+
+pub struct GoCodeGen {
+    package: String,
+    curr_msg: Option<LNMsg>,
+    /// The fields of the Go struct of the current message.
+    struct_fields: String,
+    encode_fn: String,
+    decode_fn: String,
+    /// The `case` of the type-number switch, one for each message.
+    msg_cases: String,
+    file_content: String,
+}
+
+impl GoCodeGen {
+    /// Build a new Go backend that emits the code inside the package provided.
+    pub fn with_package(_symbol_table: &BTreeMap<String, LNMsgType>, package: &str) -> Self {
+        GoCodeGen {
+            package: package.to_owned(),
+            curr_msg: None,
+            struct_fields: String::new(),
+            encode_fn: String::new(),
+            decode_fn: String::new(),
+            msg_cases: String::new(),
+            file_content: String::new(),
+        }
+    }
+
+    fn go_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    fn build_msg_doc(msg: &LNMsg) -> String {
+        let name = Self::go_name(&msg.msg_name);
+        let mut code = format!(
+            "// {name} is the `{}` message of type {}.\n",
+            msg.msg_name, msg.msg_typ
+        );
+        if let Some(doc) = &msg.doc {
+            for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+                code += &format!("//\n// {paragraph}\n");
+            }
+            code += &format!("//\n// See {}: {} ({}).\n", doc.bolt, doc.section, doc.link);
+        }
+        code
+    }
+
+    fn add_struct_field(&mut self, name: &str, ty: &str) {
+        self.struct_fields += &format!("\t{} {ty}\n", Self::go_name(name));
+    }
+
+    /// Write a statement of the encode function that can fail.
+    fn write_stmt(&mut self, call: &str) {
+        self.encode_fn += &format!("\tif err := {call}; err != nil {{\n\t\treturn err\n\t}}\n");
+    }
+
+    /// Write a statement of the decode function that can fail.
+    fn read_stmt(&mut self, assign: &str) {
+        self.decode_fn += &format!("\tif {assign}; err != nil {{\n\t\treturn err\n\t}}\n");
+    }
+
+    fn build_int(&mut self, name: &str, bits: u8) {
+        self.add_struct_field(name, &format!("uint{bits}"));
+        self.read_stmt(&format!(
+            "m.{}, err = readUint{bits}(r)",
+            Self::go_name(name)
+        ));
+    }
+
+    fn write_int(&mut self, name: &str, bits: u8) {
+        self.write_stmt(&format!("writeUint{bits}(w, m.{})", Self::go_name(name)));
+    }
+
+    /// Fields with a size known at compile time are fixed arrays.
+    fn build_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        self.add_struct_field(field.name(), &format!("[{size}]byte"));
+        let name = Self::go_name(field.name());
+        self.read_stmt(&format!("_, err = io.ReadFull(r, m.{name}[:])"));
+    }
+
+    fn write_fixed(&mut self, field: &LNMsData) {
+        let name = Self::go_name(field.name());
+        self.write_stmt(&format!("writeBytes(w, m.{name}[:])"));
+    }
+
+    /// Build the struct of a tlv stream, with a `[]byte` for each record
+    /// and the `Unknown` map, and its `Encode` and `Decode` methods.
+    fn build_tlv_stream_struct(tlv: &LNTlvStream) -> String {
+        let name = Self::go_name(&tlv.stream_name);
+        let mut fields = String::new();
+        let mut encode = String::new();
+        let mut decode = String::new();
+        for record in &tlv.records {
+            let field = Self::go_name(&record.type_name);
+            let typ = record.type_len;
+            fields += &format!("\t// {field} is the record of type {typ}, nil when missing.\n");
+            fields += &format!("\t{field} []byte\n");
+            encode += &format!(
+                "\tif s.{field} != nil {{\n\t\trecords = append(records, TlvRecord{{Type: {typ}, Value: s.{field}}})\n\t}}\n"
+            );
+            decode += &format!("\t\tcase {typ}:\n\t\t\ts.{field} = record.Value\n");
+        }
+        format!(
+            indoc! {"
+                // {name} is the `{stream}` tlv stream.
+                type {name} struct {{
+                {fields}	// Unknown contains the unknown odd records.
+                	Unknown map[uint64][]byte
+                }}
+
+                // Encode writes the tlv stream to w.
+                func (s *{name}) Encode(w io.Writer) error {{
+                	records := make([]TlvRecord, 0)
+                {encode}	for typ, value := range s.Unknown {{
+                		records = append(records, TlvRecord{{Type: typ, Value: value}})
+                	}}
+                	return encodeTlvStream(w, records)
+                }}
+
+                // Decode reads the tlv stream from r, till the end of the stream.
+                func (s *{name}) Decode(r io.Reader) error {{
+                	records, err := decodeTlvStream(r)
+                	if err != nil {{
+                		return err
+                	}}
+                	for _, record := range records {{
+                		switch record.Type {{
+                {decode}		default:
+                			if record.Type%2 == 0 {{
+                				return fmt.Errorf(\"unknown even tlv type %d\", record.Type)
+                			}}
+                			if s.Unknown == nil {{
+                				s.Unknown = make(map[uint64][]byte)
+                			}}
+                			s.Unknown[record.Type] = record.Value
+                		}}
+                	}}
+                	return nil
+                }}
+
+            "},
+            name = name,
+            stream = tlv.stream_name,
+            fields = fields,
+            encode = encode,
+            decode = decode,
+        )
+    }
+}
+
+impl<'g> CodeGen<'g> for GoCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        GoCodeGen::with_package(symbol_table, "lnwire")
+    }
+
+    fn raw_arrays(&self) -> bool {
+        true
+    }
+
+    fn pre_generation(&mut self) {
+        self.file_content += "// Code generated by lncodegen. DO NOT EDIT.\n\n";
+        self.file_content += &format!("package {}\n\n", self.package);
+        self.file_content += indoc! {r#"
+            import (
+            	"bytes"
+            	"encoding/binary"
+            	"fmt"
+            	"io"
+            	"sort"
+            )
+
+        "#};
+        self.file_content += GO_RUNTIME;
+        self.file_content += "\n";
+    }
+
+    fn post_generation(&mut self) {
+        self.file_content += &format!(
+            indoc! {"
+                // NewMessage returns an empty message for the type provided.
+                func NewMessage(msgType uint16) (Message, error) {{
+                	switch msgType {{
+                {cases}	default:
+                		return nil, fmt.Errorf(\"unknown message type %d\", msgType)
+                	}}
+                }}
+
+                // DecodeMessage decodes the payload, including the type, to the right message.
+                func DecodeMessage(payload []byte) (Message, error) {{
+                	if len(payload) < 2 {{
+                		return nil, io.ErrUnexpectedEOF
+                	}}
+                	msg, err := NewMessage(binary.BigEndian.Uint16(payload))
+                	if err != nil {{
+                		return nil, err
+                	}}
+                	if err := msg.Decode(bytes.NewReader(payload)); err != nil {{
+                		return nil, err
+                	}}
+                	return msg, nil
+                }}
+            "},
+            cases = self.msg_cases
+        );
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        self.file_content += &Self::build_tlv_stream_struct(tlv);
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_msg = Some(msg.to_owned());
+        let name = Self::go_name(&msg.msg_name);
+        self.msg_cases += &format!("\tcase {}:\n\t\treturn &{name}{{}}, nil\n", msg.msg_typ);
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let name = Self::go_name(&msg.msg_name);
+        self.file_content += &Self::build_msg_doc(msg);
+        self.file_content += &format!("type {name} struct {{\n{}}}\n\n", self.struct_fields);
+        self.file_content += "// MsgType returns the type of the message.\n";
+        self.file_content += &format!(
+            "func (m *{name}) MsgType() uint16 {{\n\treturn {}\n}}\n\n",
+            msg.msg_typ
+        );
+        self.file_content += "// Encode writes the message, including the type, to w.\n";
+        self.file_content += &format!(
+            "func (m *{name}) Encode(w io.Writer) error {{\n{}}}\n\n",
+            self.encode_fn
+        );
+        self.file_content += "// Decode reads the message, including the type, from r.\n";
+        self.file_content += &format!(
+            "func (m *{name}) Decode(r io.Reader) error {{\n{}}}\n\n",
+            self.decode_fn
+        );
+        self.struct_fields.clear();
+        self.encode_fn.clear();
+        self.decode_fn.clear();
+        self.curr_msg = None;
+    }
+
+    fn build_decode_fun(&mut self) {
+        self.decode_fn += "\tmsgType, err := readUint16(r)\n";
+        self.decode_fn += "\tif err != nil {\n\t\treturn err\n\t}\n";
+        self.decode_fn += "\tif msgType != m.MsgType() {\n";
+        self.decode_fn += "\t\treturn fmt.Errorf(\"unexpected message type %d\", msgType)\n\t}\n";
+    }
+
+    fn end_decode_fn(&mut self) {
+        self.decode_fn += "\treturn nil\n";
+    }
+
+    fn build_encode_fn(&mut self) {
+        self.write_stmt("writeUint16(w, m.MsgType())");
+    }
+
+    fn end_encode_fn(&mut self) {
+        self.encode_fn += "\treturn nil\n";
+    }
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.build_int(name, 16);
+        }
+    }
+
+    fn write_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.write_int(name, 16);
+        }
+    }
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_int(name, 32);
+        }
+    }
+
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_int(name, 32);
+        }
+    }
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_int(name, 64);
+        }
+    }
+
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_int(name, 64);
+        }
+    }
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.add_struct_field(name, "uint64");
+            self.read_stmt(&format!("m.{}, err = readBigSize(r)", Self::go_name(name)));
+        }
+    }
+
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.write_stmt(&format!("writeBigSize(w, m.{})", Self::go_name(name)));
+        }
+    }
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_chain_hash(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_signature(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_point(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_sha256(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            if size.parse::<u64>().is_ok() {
+                return self.build_fixed(field);
+            }
+            let elem = LNMsData::element_size(ty).unwrap();
+            self.add_struct_field(name, "[]byte");
+            let name = Self::go_name(name);
+            let read = if size == "..." {
+                "io.ReadAll(r)".to_owned()
+            } else if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let size = Self::go_name(size);
+                if elem == 1 {
+                    format!("readBytes(r, int(m.{size}))")
+                } else {
+                    format!("readBytes(r, int(m.{size})*{elem})")
+                }
+            } else {
+                format!("readVarBytes(r, {elem})")
+            };
+            self.read_stmt(&format!("m.{name}, err = {read}"));
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            if size.parse::<u64>().is_ok() {
+                return self.write_fixed(field);
+            }
+            let elem = LNMsData::element_size(ty).unwrap();
+            let name = Self::go_name(name);
+            if size == "..."
+                || self
+                    .curr_msg
+                    .as_ref()
+                    .is_some_and(|msg| msg.is_length_field(size))
+            {
+                self.write_stmt(&format!("writeBytes(w, m.{name})"));
+            } else {
+                self.write_stmt(&format!("writeVarBytes(w, m.{name}, {elem})"));
+            }
+        }
+    }
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = Self::go_name(&field.stream_name);
+        self.add_struct_field(&field.stream_name, &ty);
+        self.read_stmt(&format!("err = m.{ty}.Decode(r)"));
+    }
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = Self::go_name(&field.stream_name);
+        self.write_stmt(&format!("m.{name}.Encode(w)"));
+    }
+}
+
+impl Display for GoCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::codegen::CodeGen;
+    use crate::go::GoCodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+
+    const INIT_CSV: &str = "msgtype,init,16\n\
+                            msgdata,init,gflen,u16,\n\
+                            msgdata,init,globalfeatures,byte,gflen\n\
+                            msgdata,init,chain_hash,chain_hash,\n\
+                            msgdata,init,tlvs,init_tlvs,\n\
+                            tlvtype,init_tlvs,networks,1\n\
+                            tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+
+    #[test]
+    fn go_init_msg() {
+        init();
+        let symbol_table = parse(INIT_CSV);
+        let mut backend = GoCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("package lnwire\n"), "{code}");
+        assert!(
+            code.contains("type Init struct {\n\tGlobalfeatures []byte\n\tChainHash [32]byte\n\tInitTlvs InitTlvs\n}"),
+            "{code}"
+        );
+        assert!(
+            code.contains("func (m *Init) Encode(w io.Writer) error {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("func (m *Init) Decode(r io.Reader) error {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("\tif m.Globalfeatures, err = readVarBytes(r, 1); err != nil {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("func (s *InitTlvs) Decode(r io.Reader) error {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("\t\tcase 1:\n\t\t\ts.Networks = record.Value\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("\tcase 16:\n\t\treturn &Init{}, nil\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires the go toolchain, run by the toolchains job of the CI"]
+    fn go_init_msg_vet() {
+        init();
+        let symbol_table = parse(INIT_CSV);
+        let mut backend = GoCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("go_init_msg");
+        fs::write(dir.join("go.mod"), "module lnwire\n\ngo 1.20\n").unwrap();
+        fs::write(dir.join("lnwire.go"), backend.to_string()).unwrap();
+        run_cmd(Command::new("go").args(["vet", "./..."]).current_dir(&dir));
+    }
+
+    const SIGNATURES_CSV: &str = "msgtype,commitment_signed,132\n\
+                                  msgdata,commitment_signed,channel_id,channel_id,\n\
+                                  msgdata,commitment_signed,num_htlcs,u16,\n\
+                                  msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                                  msgtype,points,1\n\
+                                  msgdata,points,num_points,u16,\n\
+                                  msgdata,points,flags,u16,\n\
+                                  msgdata,points,points,point,num_points\n";
+
+    #[test]
+    fn go_array_elements() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = GoCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains("\tif m.HtlcSignature, err = readVarBytes(r, 64); err != nil {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("writeVarBytes(w, m.HtlcSignature, 64)"),
+            "{code}"
+        );
+        assert!(
+            code.contains("\tif m.Points, err = readBytes(r, int(m.NumPoints)*33); err != nil {"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires the go toolchain, run by the toolchains job of the CI"]
+    fn go_array_elements_round_trip() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = GoCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("go_array_elements");
+        fs::write(dir.join("go.mod"), "module lnwire\n\ngo 1.20\n").unwrap();
+        fs::write(dir.join("lnwire.go"), backend.to_string()).unwrap();
+        let test = indoc! {r#"
+            package lnwire
+
+            import (
+            	"bytes"
+            	"testing"
+            )
+
+            func TestArrayElements(t *testing.T) {
+            	msg := CommitmentSigned{HtlcSignature: bytes.Repeat([]byte{0x5a}, 128)}
+            	var buf bytes.Buffer
+            	if err := msg.Encode(&buf); err != nil {
+            		t.Fatal(err)
+            	}
+            	wire := buf.Bytes()
+            	if len(wire) != 2+32+2+128 || wire[34] != 0 || wire[35] != 2 {
+            		t.Fatalf("unexpected wire %x", wire)
+            	}
+            	var decoded CommitmentSigned
+            	if err := decoded.Decode(bytes.NewReader(wire)); err != nil {
+            		t.Fatal(err)
+            	}
+            	if !bytes.Equal(decoded.HtlcSignature, msg.HtlcSignature) {
+            		t.Fatalf("unexpected signatures %x", decoded.HtlcSignature)
+            	}
+            	if err := decoded.Decode(bytes.NewReader(wire[:len(wire)-1])); err == nil {
+            		t.Fatal("truncated signatures accepted")
+            	}
+            	msg.HtlcSignature = msg.HtlcSignature[1:]
+            	if err := msg.Encode(&buf); err == nil {
+            		t.Fatal("partial signature accepted")
+            	}
+            }
+        "#};
+        fs::write(dir.join("lnwire_test.go"), test).unwrap();
+        run_cmd(Command::new("go").args(["test", "./..."]).current_dir(&dir));
+    }
+}
@@ -0,0 +1,317 @@
+//! JSON Schema export of the lightning
+//! network specification.
+//!
+//! Every message, tlv stream and subtype is a definition of the
+//! schema, the byte arrays are hex strings and every property
+//! describes the original csv type.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+pub struct JsonSchemaCodeGen {
+    /// The properties of the message that we are building,
+    /// with the required flag.
+    properties: Vec<(String, String, bool)>,
+    /// The definitions of the schema.
+    defs: Vec<String>,
+    /// The names of the message definitions.
+    msgs: Vec<String>,
+    file_content: String,
+}
+
+impl JsonSchemaCodeGen {
+    fn def_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    /// Quote and escape the string as a json string.
+    fn json_str(value: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in value.chars() {
+            match c {
+                '"' => quoted += "\\\"",
+                '\\' => quoted += "\\\\",
+                '\n' => quoted += "\\n",
+                c if (c as u32) < 0x20 => quoted += &format!("\\u{:04x}", c as u32),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    fn hex_schema(size: Option<u64>, description: &str) -> String {
+        let pattern = match size {
+            Some(size) => format!("^[0-9a-f]{{{}}}$", size * 2),
+            None => "^([0-9a-f]{2})*$".to_owned(),
+        };
+        format!(
+            "{{ \"type\": \"string\", \"pattern\": {}, \"description\": {} }}",
+            Self::json_str(&pattern),
+            Self::json_str(description)
+        )
+    }
+
+    fn int_schema(max: u64, description: &str) -> String {
+        format!(
+            "{{ \"type\": \"integer\", \"minimum\": 0, \"maximum\": {max}, \"description\": {} }}",
+            Self::json_str(description)
+        )
+    }
+
+    fn field_schema(field: &LNMsData) -> String {
+        let csv_type = field.csv_type();
+        match field {
+            LNMsData::Uint16(_) => Self::int_schema(u16::MAX as u64, &csv_type),
+            LNMsData::Uint32(_) => Self::int_schema(u32::MAX as u64, &csv_type),
+            LNMsData::Uint64(_) | LNMsData::BigSize(_) => Self::int_schema(u64::MAX, &csv_type),
+            LNMsData::TLVinit(stream, _) => format!(
+                "{{ \"$ref\": \"#/$defs/{}\", \"description\": {} }}",
+                Self::def_name(stream),
+                Self::json_str(&csv_type)
+            ),
+            _ => Self::hex_schema(field.fixed_size(), &csv_type),
+        }
+    }
+
+    fn add_field(&mut self, field: &LNMsData) {
+        let (name, required) = match field {
+            LNMsData::TLVinit(stream, _) => (stream.as_str(), false),
+            _ => (field.name(), true),
+        };
+        self.properties
+            .push((name.to_owned(), Self::field_schema(field), required));
+    }
+
+    /// Build the definition of an object with the properties collected.
+    fn build_def(&mut self, name: &str, description: &str, extra: &str) {
+        let properties = self
+            .properties
+            .iter()
+            .map(|(name, schema, _)| format!("        {}: {schema}", Self::json_str(name)))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let required = self
+            .properties
+            .iter()
+            .filter(|(_, _, required)| *required)
+            .map(|(name, _, _)| Self::json_str(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut def = format!("    {}: {{\n", Self::json_str(name));
+        def += &format!("      \"description\": {},\n", Self::json_str(description));
+        def += extra;
+        def += "      \"type\": \"object\",\n";
+        def += &format!("      \"properties\": {{\n{properties}\n      }},\n");
+        def += &format!("      \"required\": [{required}],\n");
+        def += "      \"additionalProperties\": false\n    }";
+        self.defs.push(def);
+        self.properties.clear();
+    }
+}
+
+impl<'g> CodeGen<'g> for JsonSchemaCodeGen {
+    fn new(_symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        JsonSchemaCodeGen {
+            properties: Vec::new(),
+            defs: Vec::new(),
+            msgs: Vec::new(),
+            file_content: String::new(),
+        }
+    }
+
+    fn pre_generation(&mut self) {}
+
+    fn post_generation(&mut self) {
+        let one_of = self
+            .msgs
+            .iter()
+            .map(|name| format!("    {{ \"$ref\": \"#/$defs/{name}\" }}"))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        self.file_content += "{\n";
+        self.file_content += "  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n";
+        self.file_content +=
+            "  \"$comment\": \"code generated by the lncodegen.rs please do not edit\",\n";
+        self.file_content += "  \"title\": \"Lightning network messages\",\n";
+        self.file_content += &format!("  \"oneOf\": [\n{one_of}\n  ],\n");
+        self.file_content += &format!("  \"$defs\": {{\n{}\n  }}\n", self.defs.join(",\n"));
+        self.file_content += "}\n";
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        for record in &tlv.records {
+            let entries = record
+                .record_entry
+                .iter()
+                .map(|entry| format!("{}: {}", entry.entry_name, entry.csv_type()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let description = format!("type {}, {entries}", record.type_len);
+            self.properties.push((
+                record.type_name.to_owned(),
+                Self::hex_schema(None, &description),
+                false,
+            ));
+        }
+        let description = format!("The `{}` tlv stream.", tlv.stream_name);
+        self.build_def(&Self::def_name(&tlv.stream_name), &description, "");
+    }
+
+    fn generate_subtype(&mut self, subtyp: &LNSubType) {
+        for field in &subtyp.ty_data {
+            self.add_field(field);
+        }
+        let description = format!("The `{}` subtype.", subtyp.ty);
+        self.build_def(&Self::def_name(&subtyp.ty), &description, "");
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.msgs.push(Self::def_name(&msg.msg_name));
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let description = format!("The `{}` message, type {}.", msg.msg_name, msg.msg_typ);
+        let extra = format!("      \"x-lightning-type\": {},\n", msg.msg_typ);
+        self.build_def(&Self::def_name(&msg.msg_name), &description, &extra);
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u16(&mut self, _: &LNMsData) {}
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u32(&mut self, _: &LNMsData) {}
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u64(&mut self, _: &LNMsData) {}
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bigsize(&mut self, _: &LNMsData) {}
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_chain_hash(&mut self, _: &LNMsData) {}
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_short_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_signature(&mut self, _: &LNMsData) {}
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_point(&mut self, _: &LNMsData) {}
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_sha256(&mut self, _: &LNMsData) {}
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bitfiled(&mut self, _: &LNMsData) {}
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let field = LNMsData::TLVinit(field.stream_name.to_owned(), field.stream_name.to_owned());
+        self.add_field(&field);
+    }
+
+    fn write_tlv_stream(&mut self, _: &LNTlvStream) {}
+}
+
+impl Display for JsonSchemaCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use crate::codegen::CodeGen;
+    use crate::jsonschema::JsonSchemaCodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+
+    #[test]
+    fn jsonschema_init_msg() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,node_id,point,\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let mut backend = JsonSchemaCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("        \"node_id\": { \"type\": \"string\", \"pattern\": \"^[0-9a-f]{66}$\", \"description\": \"point\" },\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("        \"init_tlvs\": { \"$ref\": \"#/$defs/InitTlvs\", \"description\": \"init_tlvs\" }\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("      \"required\": [\"globalfeatures\", \"node_id\"],\n"),
+            "{code}"
+        );
+        assert!(code.contains("      \"x-lightning-type\": 16,\n"), "{code}");
+
+        let dir = scratch_dir("jsonschema_init_msg");
+        fs::write(dir.join("schema.json"), &code).unwrap();
+        run_cmd(
+            Command::new("python3")
+                .args(["-c", "import json; json.load(open('schema.json'))"])
+                .current_dir(&dir),
+        );
+    }
+}
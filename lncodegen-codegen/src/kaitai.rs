@@ -0,0 +1,497 @@
+//! Kaitai Struct description of the lightning
+//! network specification.
+//!
+//! The output is a `.ksy` file that parses a message, including
+//! the type, till the end of the stream.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvEntry, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Types used by the messages that are not part of kaitai.
+const KAITAI_TYPES: &str = indoc! {r#"
+      bigsize:
+        seq:
+          - id: prefix
+            type: u1
+          - id: u16_value
+            type: u2
+            if: prefix == 0xfd
+          - id: u32_value
+            type: u4
+            if: prefix == 0xfe
+          - id: u64_value
+            type: u8
+            if: prefix == 0xff
+        instances:
+          value:
+            value: 'prefix == 0xfd ? u16_value : prefix == 0xfe ? u32_value : prefix == 0xff ? u64_value : prefix'
+"#};
+
+pub struct KaitaiCodeGen {
+    id: String,
+    /// The fields of the message or the subtype that we are building.
+    curr_fields: Vec<LNMsData>,
+    /// The `seq` of the message that we are building.
+    seq: String,
+    /// The values of the `msg_type` enum.
+    msg_types: Vec<(u64, String)>,
+    /// The subtypes declared in the symbol table.
+    subtypes: BTreeSet<String>,
+    types: String,
+}
+
+impl KaitaiCodeGen {
+    /// Build a new kaitai backend with the id provided in the meta section.
+    pub fn with_id(symbol_table: &BTreeMap<String, LNMsgType>, id: &str) -> Self {
+        let subtypes = symbol_table
+            .values()
+            .filter_map(|item| match item {
+                LNMsgType::SubType(subtyp) => Some(subtyp.ty.to_owned()),
+                _ => None,
+            })
+            .collect();
+        KaitaiCodeGen {
+            id: id.to_owned(),
+            curr_fields: Vec::new(),
+            seq: String::new(),
+            msg_types: Vec::new(),
+            subtypes,
+            types: String::new(),
+        }
+    }
+
+    fn seq_entry(id: &str, attrs: &[String], doc: &str) -> String {
+        let mut code = format!("      - id: {id}\n");
+        for attr in attrs {
+            code += &format!("        {attr}\n");
+        }
+        code += &format!("        doc: {doc}\n");
+        code
+    }
+
+    fn add_field(&mut self, field: &LNMsData) {
+        let name = field.name();
+        let doc = field.csv_type();
+        let attrs = match field {
+            LNMsData::Uint16(_) => vec!["type: u2".to_owned()],
+            LNMsData::Uint32(_) => vec!["type: u4".to_owned()],
+            LNMsData::Uint64(_) => vec!["type: u8".to_owned()],
+            LNMsData::BigSize(_) => vec!["type: bigsize".to_owned()],
+            LNMsData::TLVinit(stream, _) => vec![format!("type: {stream}")],
+            // a single byte that it is the count of an array
+            LNMsData::BitfieldStream(_, size, ty)
+                if size == "1"
+                    && ["byte", "u8"].contains(&ty.as_str())
+                    && self.curr_fields.iter().any(|field| {
+                        matches!(field, LNMsData::BitfieldStream(_, count, _) if count == name)
+                    }) =>
+            {
+                vec!["type: u1".to_owned()]
+            }
+            LNMsData::BitfieldStream(_, size, ty) => {
+                let count = if size == "..."
+                    || size.parse::<u64>().is_ok()
+                    || LNMsData::is_length_field(&self.curr_fields, size)
+                {
+                    size.to_owned()
+                } else {
+                    // the length was folded inside the bitfield, so we
+                    // declare it again to be able to read the elements.
+                    let len = format!("{name}_len");
+                    self.seq += &Self::seq_entry(&len, &["type: u2".to_owned()], "u16");
+                    len
+                };
+                Self::array_attrs(&count, ty, &self.subtypes)
+            }
+            _ => vec![format!("size: {}", field.fixed_size().unwrap())],
+        };
+        let id = match field {
+            LNMsData::TLVinit(stream, _) => stream.as_str(),
+            _ => name,
+        };
+        self.seq += &Self::seq_entry(id, &attrs, &doc);
+    }
+
+    /// Return the attributes of `count` elements of the type `ty`, the subtypes
+    /// are repeated and the other types are read as bytes.
+    fn array_attrs(count: &str, ty: &str, subtypes: &BTreeSet<String>) -> Vec<String> {
+        match (LNMsData::element_size(ty), count) {
+            (Some(_), "...") => vec!["size-eos: true".to_owned()],
+            (Some(1), count) => vec![format!("size: {count}")],
+            (Some(size), count) => match count.parse::<u64>() {
+                Ok(count) => vec![format!("size: {}", count * size)],
+                Err(_) => vec![format!("size: {count} * {size}")],
+            },
+            (None, "...") if subtypes.contains(ty) => {
+                vec![format!("type: {ty}"), "repeat: eos".to_owned()]
+            }
+            (None, count) if subtypes.contains(ty) => vec![
+                format!("type: {ty}"),
+                "repeat: expr".to_owned(),
+                format!("repeat-expr: {count}"),
+            ],
+            // an unknown type uses all the rest of the message
+            (None, _) => vec!["size-eos: true".to_owned()],
+        }
+    }
+
+    /// Map a tlv entry to the kaitai attributes, the entry type is
+    /// a csv type or a subtype.
+    fn entry_attrs(&self, entry: &LNTlvEntry) -> Vec<String> {
+        let (ty, size) = match entry.entry_ty.as_str() {
+            "byte" => (None, Some(1)),
+            "u8" => (Some("u1".to_owned()), None),
+            "u16" => (Some("u2".to_owned()), None),
+            "u32" => (Some("u4".to_owned()), None),
+            "u64" => (Some("u8".to_owned()), None),
+            "s8" => (Some("s1".to_owned()), None),
+            "s16" => (Some("s2".to_owned()), None),
+            "s32" => (Some("s4".to_owned()), None),
+            "s64" => (Some("s8".to_owned()), None),
+            "bigsize" => (Some("bigsize".to_owned()), None),
+            "chain_hash" | "channel_id" | "sha256" => (None, Some(32)),
+            "short_channel_id" => (None, Some(8)),
+            "point" => (None, Some(33)),
+            "signature" => (None, Some(64)),
+            subtype if self.subtypes.contains(subtype) => (Some(subtype.to_owned()), None),
+            // the truncated integers and the unknown types use all the record.
+            _ => return vec!["size-eos: true".to_owned()],
+        };
+        match (ty, size, &entry.count) {
+            (None, Some(size), None) => vec![format!("size: {size}")],
+            (None, Some(_), Some(count)) if count == "..." && entry.entry_ty == "byte" => {
+                vec!["size-eos: true".to_owned()]
+            }
+            (None, Some(1), Some(count)) => vec![format!("size: {count}")],
+            (None, Some(size), Some(count)) if count == "..." => {
+                vec![format!("size: {size}"), "repeat: eos".to_owned()]
+            }
+            (None, Some(size), Some(count)) => vec![
+                format!("size: {size}"),
+                "repeat: expr".to_owned(),
+                format!("repeat-expr: {count}"),
+            ],
+            (Some(ty), _, None) => vec![format!("type: {ty}")],
+            (Some(ty), _, Some(count)) if count == "..." => {
+                vec![format!("type: {ty}"), "repeat: eos".to_owned()]
+            }
+            (Some(ty), _, Some(count)) => vec![
+                format!("type: {ty}"),
+                "repeat: expr".to_owned(),
+                format!("repeat-expr: {count}"),
+            ],
+            (None, None, _) => unreachable!(),
+        }
+    }
+
+    fn build_type(name: &str, doc: &str, seq: &str) -> String {
+        let mut code = format!("  {name}:\n    doc: {doc}\n");
+        if seq.is_empty() {
+            code += "    seq: []\n";
+        } else {
+            code += &format!("    seq:\n{seq}");
+        }
+        code
+    }
+}
+
+impl<'g> CodeGen<'g> for KaitaiCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        KaitaiCodeGen::with_id(symbol_table, "lnwire")
+    }
+
+    fn pre_generation(&mut self) {}
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        let name = &tlv.stream_name;
+        let record = format!("{name}_record");
+        self.types += &Self::build_type(
+            name,
+            &format!("The `{name}` tlv stream."),
+            &format!("      - id: records\n        type: {record}\n        repeat: eos\n"),
+        );
+
+        let mut cases = String::new();
+        for tlv_record in &tlv.records {
+            cases += &format!(
+                "            {}: {name}_{}\n",
+                tlv_record.type_len, tlv_record.type_name
+            );
+        }
+        let seq = format!(
+            indoc! {"
+                      - id: tlv_type
+                        type: bigsize
+                      - id: tlv_length
+                        type: bigsize
+                      - id: value
+                        size: tlv_length.value
+                        type:
+                          switch-on: tlv_type.value
+                          cases:
+                {}"},
+            cases
+        );
+        self.types += &Self::build_type(
+            &record,
+            &format!("A record of the `{name}` tlv stream."),
+            &seq,
+        );
+
+        for tlv_record in &tlv.records {
+            let seq = tlv_record
+                .record_entry
+                .iter()
+                .map(|entry| {
+                    Self::seq_entry(
+                        &entry.entry_name,
+                        &self.entry_attrs(entry),
+                        &entry.csv_type(),
+                    )
+                })
+                .collect::<String>();
+            self.types += &Self::build_type(
+                &format!("{name}_{}", tlv_record.type_name),
+                &format!(
+                    "The `{}` record, type {}.",
+                    tlv_record.type_name, tlv_record.type_len
+                ),
+                &seq,
+            );
+        }
+    }
+
+    fn generate_subtype(&mut self, subtyp: &LNSubType) {
+        self.curr_fields = subtyp.ty_data.to_owned();
+        for field in &subtyp.ty_data {
+            self.add_field(field);
+        }
+        let doc = format!("The `{}` subtype.", subtyp.ty);
+        self.types += &Self::build_type(&subtyp.ty, &doc, &self.seq);
+        self.seq.clear();
+        self.curr_fields.clear();
+    }
+
+    fn post_generation(&mut self) {}
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_fields = msg.msg_data.to_owned();
+        self.msg_types.push((msg.msg_typ, msg.msg_name.to_owned()));
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let doc = format!("The `{}` message, type {}.", msg.msg_name, msg.msg_typ);
+        self.types += &Self::build_type(&msg.msg_name, &doc, &self.seq);
+        self.seq.clear();
+        self.curr_fields.clear();
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u16(&mut self, _: &LNMsData) {}
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u32(&mut self, _: &LNMsData) {}
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u64(&mut self, _: &LNMsData) {}
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bigsize(&mut self, _: &LNMsData) {}
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_chain_hash(&mut self, _: &LNMsData) {}
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_short_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_signature(&mut self, _: &LNMsData) {}
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_point(&mut self, _: &LNMsData) {}
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_sha256(&mut self, _: &LNMsData) {}
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bitfiled(&mut self, _: &LNMsData) {}
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let field = LNMsData::TLVinit(field.stream_name.to_owned(), field.stream_name.to_owned());
+        self.add_field(&field);
+    }
+
+    fn write_tlv_stream(&mut self, _: &LNTlvStream) {}
+}
+
+impl Display for KaitaiCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cases = self
+            .msg_types
+            .iter()
+            .map(|(_, name)| format!("        'msg_type::{name}': {name}\n"))
+            .collect::<String>();
+        let msg_types = self
+            .msg_types
+            .iter()
+            .map(|(typ, name)| format!("    {typ}: {name}\n"))
+            .collect::<String>();
+        write!(
+            f,
+            indoc! {"
+                # code generated by the lncodegen.rs please do not edit
+                meta:
+                  id: {id}
+                  title: Lightning network messages
+                  endian: be
+                seq:
+                  - id: msg_type
+                    type: u2
+                    enum: msg_type
+                  - id: payload
+                    size-eos: true
+                    type:
+                      switch-on: msg_type
+                      cases:
+                {cases}enums:
+                  msg_type:
+                {msg_types}types:
+                {types}{runtime}"},
+            id = self.id,
+            cases = cases,
+            msg_types = msg_types,
+            types = self.types,
+            runtime = KAITAI_TYPES
+                .lines()
+                .map(|line| format!("  {line}\n"))
+                .collect::<String>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codegen::CodeGen;
+    use crate::kaitai::KaitaiCodeGen;
+    use crate::testing::{init, parse};
+
+    #[test]
+    fn kaitai_init_msg() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let mut backend = KaitaiCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("      - id: globalfeatures_len\n        type: u2\n        doc: u16\n      - id: globalfeatures\n        size: globalfeatures_len\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("            1: init_tlvs_networks\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("      - id: chains\n        size: 32\n        repeat: eos\n"),
+            "{code}"
+        );
+        assert!(code.contains("        'msg_type::init': init\n"), "{code}");
+    }
+
+    #[test]
+    fn kaitai_array_elements() {
+        init();
+        let contents = "subtype,witness\n\
+                        subtypedata,witness,len,u16,\n\
+                        subtypedata,witness,witness_data,byte,len\n\
+                        subtype,blinded_path\n\
+                        subtypedata,blinded_path,num_hops,byte,\n\
+                        subtypedata,blinded_path,path,witness,num_hops\n\
+                        msgtype,commitment_signed,132\n\
+                        msgdata,commitment_signed,num_htlcs,u16,\n\
+                        msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                        msgtype,tx_signatures,71\n\
+                        msgdata,tx_signatures,num_witnesses,u16,\n\
+                        msgdata,tx_signatures,witnesses,witness,num_witnesses\n";
+        let symbol_table = parse(contents);
+        let mut backend = KaitaiCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains("      - id: htlc_signature\n        size: htlc_signature_len * 64\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("      - id: witnesses_len\n        type: u2\n        doc: u16\n      - id: witnesses\n        type: witness\n        repeat: expr\n        repeat-expr: witnesses_len\n"),
+            "{code}"
+        );
+        // the count of a subtype is a field of the subtype
+        assert!(
+            code.contains("      - id: num_hops\n        type: u1\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("      - id: path\n        type: witness\n        repeat: expr\n        repeat-expr: num_hops\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("      - id: witness_data_len\n        type: u2\n"),
+            "{code}"
+        );
+    }
+}
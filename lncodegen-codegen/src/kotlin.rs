@@ -0,0 +1,793 @@
+//! Kotlin code generation for the lightning
+//! network specification.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated classes to read and write
+/// the lightning network types.
+const KOTLIN_RUNTIME: &str = indoc! {r#"
+    class WireException(message: String) : Exception(message)
+
+    /** A compressed public key of 33 bytes. */
+    @JvmInline
+    value class Point(val bytes: ByteArray) {
+        init {
+            require(bytes.size == 33) { "a point is 33 bytes, got ${bytes.size}" }
+        }
+    }
+
+    class WireWriter {
+        private val out = ByteArrayOutputStream()
+
+        fun bytes(value: ByteArray) = out.write(value, 0, value.size)
+
+        fun fixed(value: ByteArray, size: Int) {
+            if (value.size != size) throw WireException("expected $size bytes, got ${value.size}")
+            bytes(value)
+        }
+
+        /** Write the elements of [elemSize] bytes prefixed by their u16 count. */
+        fun varBytes(value: ByteArray, elemSize: Int = 1) {
+            if (value.size % elemSize != 0) throw WireException("${value.size} bytes are not elements of $elemSize bytes")
+            val count = value.size / elemSize
+            if (count > 0xFFFF) throw WireException("$count elements do not fit a u16 count")
+            u16(count.toUShort())
+            bytes(value)
+        }
+
+        fun u16(value: UShort) {
+            val v = value.toInt()
+            out.write(v shr 8)
+            out.write(v)
+        }
+
+        fun u32(value: UInt) {
+            for (shift in 24 downTo 0 step 8) out.write((value shr shift).toInt())
+        }
+
+        fun u64(value: ULong) {
+            for (shift in 56 downTo 0 step 8) out.write((value shr shift).toInt())
+        }
+
+        fun bigsize(value: ULong) {
+            when {
+                value < 0xFDuL -> out.write(value.toInt())
+                value <= 0xFFFFuL -> {
+                    out.write(0xFD)
+                    u16(value.toUShort())
+                }
+                value <= 0xFFFFFFFFuL -> {
+                    out.write(0xFE)
+                    u32(value.toUInt())
+                }
+                else -> {
+                    out.write(0xFF)
+                    u64(value)
+                }
+            }
+        }
+
+        fun toByteArray(): ByteArray = out.toByteArray()
+    }
+
+    class WireReader(private val buf: ByteArray) {
+        private var pos = 0
+
+        fun remaining(): Int = buf.size - pos
+
+        fun bytes(size: Int): ByteArray {
+            if (size < 0 || size > remaining()) throw WireException("unexpected end of message")
+            val value = buf.copyOfRange(pos, pos + size)
+            pos += size
+            return value
+        }
+
+        fun rest(): ByteArray = bytes(remaining())
+
+        /** Read the elements of [elemSize] bytes prefixed by their u16 count. */
+        fun varBytes(elemSize: Int = 1): ByteArray = bytes(u16().toInt() * elemSize)
+
+        fun u8(): Int = bytes(1)[0].toInt() and 0xFF
+
+        fun u16(): UShort = bytes(2).fold(0) { acc, b -> (acc shl 8) or (b.toInt() and 0xFF) }.toUShort()
+
+        fun u32(): UInt = bytes(4).fold(0u) { acc, b -> (acc shl 8) or (b.toUInt() and 0xFFu) }
+
+        fun u64(): ULong = bytes(8).fold(0uL) { acc, b -> (acc shl 8) or (b.toULong() and 0xFFuL) }
+
+        fun bigsize(): ULong {
+            val (value, min) = when (val prefix = u8()) {
+                0xFD -> u16().toULong() to 0xFDuL
+                0xFE -> u32().toULong() to 0x10000uL
+                0xFF -> u64() to 0x100000000uL
+                else -> return prefix.toULong()
+            }
+            if (value < min) throw WireException("bigsize is not minimally encoded")
+            return value
+        }
+    }
+
+    /** Decode a truncated integer (tu16, tu32, tu64) of at most `size` bytes. */
+    fun decodeTruncated(value: ByteArray, size: Int): ULong {
+        if (value.size > size) throw WireException("truncated integer longer than $size bytes")
+        if (value.isNotEmpty() && value[0] == 0.toByte()) {
+            throw WireException("truncated integer is not minimally encoded")
+        }
+        return value.fold(0uL) { acc, b -> (acc shl 8) or (b.toULong() and 0xFFuL) }
+    }
+
+    fun encodeTruncated(value: ULong): ByteArray =
+        (56 downTo 0 step 8).map { (value shr it).toByte() }.dropWhile { it == 0.toByte() }.toByteArray()
+
+    /** Read the tlv records till the end of the message. */
+    fun readTlvStream(reader: WireReader): List<Pair<ULong, ByteArray>> {
+        val records = mutableListOf<Pair<ULong, ByteArray>>()
+        while (reader.remaining() > 0) {
+            val type = reader.bigsize()
+            if (records.isNotEmpty() && type <= records.last().first) {
+                throw WireException("tlv types are not strictly increasing")
+            }
+            val len = reader.bigsize()
+            if (len > reader.remaining().toULong()) throw WireException("unexpected end of message")
+            records.add(type to reader.bytes(len.toInt()))
+        }
+        return records
+    }
+
+    /** Compare the tlv records by content, a ByteArray compares by reference. */
+    fun recordsEqual(a: List<Pair<ULong, ByteArray>>, b: List<Pair<ULong, ByteArray>>): Boolean =
+        a.size == b.size && a.zip(b).all { (x, y) -> x.first == y.first && x.second.contentEquals(y.second) }
+
+    fun recordsHashCode(records: List<Pair<ULong, ByteArray>>): Int =
+        records.fold(1) { acc, (type, value) -> 31 * (31 * acc + type.hashCode()) + value.contentHashCode() }
+
+    /** Write the tlv records ordered by type. */
+    fun writeTlvStream(writer: WireWriter, records: List<Pair<ULong, ByteArray>>) {
+        for ((type, value) in records.sortedBy { it.first }) {
+            writer.bigsize(type)
+            writer.bigsize(value.size.toULong())
+            writer.bytes(value)
+        }
+    }
+"#};
+
+/// Kotlin hard keywords, that need to be escaped with backticks.
+const KOTLIN_KEYWORDS: [&str; 28] = [
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+pub struct KotlinCodeGen {
+    package: String,
+    curr_msg: Option<LNMsg>,
+    /// The constructor parameters of the data class, known only
+    /// while we generate the decode function.
+    class_params: String,
+    /// The name and the type of the constructor parameters.
+    class_fields: Vec<(String, String)>,
+    encode_fn: String,
+    decode_fn: String,
+    /// The `when` branches of the dispatcher.
+    msg_cases: String,
+    file_content: String,
+}
+
+impl KotlinCodeGen {
+    /// Build a new Kotlin backend that emits the code inside the package provided.
+    pub fn with_package(_symbol_table: &BTreeMap<String, LNMsgType>, package: &str) -> Self {
+        KotlinCodeGen {
+            package: package.to_owned(),
+            curr_msg: None,
+            class_params: String::new(),
+            class_fields: Vec::new(),
+            encode_fn: String::new(),
+            decode_fn: String::new(),
+            msg_cases: String::new(),
+            file_content: String::new(),
+        }
+    }
+
+    fn kt_type_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    fn kt_ident(name: &str) -> String {
+        let name = name.to_case(Case::Camel);
+        if KOTLIN_KEYWORDS.contains(&name.as_str()) {
+            return format!("`{name}`");
+        }
+        name
+    }
+
+    fn build_msg_doc(msg: &LNMsg) -> String {
+        let mut code = format!(
+            "/**\n * The `{}` message, type {}.\n",
+            msg.msg_name, msg.msg_typ
+        );
+        if let Some(doc) = &msg.doc {
+            for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+                code += &format!(" *\n * {paragraph}\n");
+            }
+            code += &format!(
+                " *\n * See [{}: {}]({}).\n",
+                doc.bolt, doc.section, doc.link
+            );
+        }
+        code += " */\n";
+        code
+    }
+
+    /// Build `equals` and `hashCode` of a data class that holds arrays,
+    /// otherwise the generated ones compare the arrays by reference.
+    fn build_content_equals(name: &str, fields: &[(String, String)]) -> String {
+        let by_content =
+            |ty: &str| ty.starts_with("ByteArray") || ty == "Point" || ty.starts_with("List<");
+        if !fields.iter().any(|(_, ty)| by_content(ty)) {
+            return String::new();
+        }
+        let mut equals = Vec::new();
+        let mut hashes = Vec::new();
+        for (field, ty) in fields {
+            let (eq, hash) = match ty.as_str() {
+                "ByteArray" | "ByteArray?" => (
+                    format!("{field}.contentEquals(other.{field})"),
+                    format!("{field}.contentHashCode()"),
+                ),
+                "Point" => (
+                    format!("{field}.bytes.contentEquals(other.{field}.bytes)"),
+                    format!("{field}.bytes.contentHashCode()"),
+                ),
+                "List<Pair<ULong, ByteArray>>" => (
+                    format!("recordsEqual({field}, other.{field})"),
+                    format!("recordsHashCode({field})"),
+                ),
+                _ => (
+                    format!("{field} == other.{field}"),
+                    format!("{field}.hashCode()"),
+                ),
+            };
+            equals.push(eq);
+            hashes.push(hash);
+        }
+        let mut code = format!(
+            "    override fun equals(other: Any?): Boolean =\n        other is {name} &&\n"
+        );
+        code += &format!("            {}\n\n", equals.join(" &&\n            "));
+        code += "    override fun hashCode(): Int {\n";
+        code += &format!("        var result = {}\n", hashes[0]);
+        for hash in &hashes[1..] {
+            code += &format!("        result = 31 * result + {hash}\n");
+        }
+        code += "        return result\n    }\n\n";
+        code
+    }
+
+    /// Add a parameter to the data class, and decode it inside a local variable.
+    fn build_field(&mut self, name: &str, ty: &str, read: &str) {
+        let name = Self::kt_ident(name);
+        self.class_params += &format!("    val {name}: {ty},\n");
+        self.class_fields.push((name.to_owned(), ty.to_owned()));
+        self.decode_fn += &format!("            val {name} = {read}\n");
+    }
+
+    fn write_field(&mut self, write: &str) {
+        self.encode_fn += &format!("        writer.{write}\n");
+    }
+
+    fn build_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        self.build_field(field.name(), "ByteArray", &format!("reader.bytes({size})"));
+    }
+
+    fn write_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        let name = Self::kt_ident(field.name());
+        self.write_field(&format!("fixed({name}, {size})"));
+    }
+
+    /// Build the data class of a tlv stream, with a nullable `ByteArray` for each record.
+    fn build_tlv_stream_class(tlv: &LNTlvStream) -> String {
+        let name = Self::kt_type_name(&tlv.stream_name);
+        let mut params = String::new();
+        let mut encode = String::new();
+        let mut vars = String::new();
+        let mut cases = String::new();
+        let mut args = Vec::new();
+        let mut fields = Vec::new();
+        for record in &tlv.records {
+            let field = Self::kt_ident(&record.type_name);
+            let typ = record.type_len;
+            params +=
+                &format!("    /** Record of type {typ}. */\n    val {field}: ByteArray? = null,\n");
+            encode += &format!("        {field}?.let {{ records.add({typ}uL to it) }}\n");
+            vars += &format!("            var {field}: ByteArray? = null\n");
+            cases += &format!("                    {typ}uL -> {field} = value\n");
+            fields.push((field.to_owned(), "ByteArray?".to_owned()));
+            args.push(field);
+        }
+        args.push("unknown".to_owned());
+        fields.push((
+            "unknown".to_owned(),
+            "List<Pair<ULong, ByteArray>>".to_owned(),
+        ));
+        format!(
+            indoc! {r#"
+                /** The `{stream}` tlv stream. */
+                data class {name}(
+                {params}    /** The unknown odd records. */
+                    val unknown: List<Pair<ULong, ByteArray>> = emptyList(),
+                ) {{
+                {equals}    fun encode(writer: WireWriter) {{
+                        val records = unknown.toMutableList()
+                {encode}        writeTlvStream(writer, records)
+                    }}
+
+                    companion object {{
+                        fun decode(reader: WireReader): {name} {{
+                {vars}            val unknown = mutableListOf<Pair<ULong, ByteArray>>()
+                            for ((type, value) in readTlvStream(reader)) {{
+                                when (type) {{
+                {cases}                    else -> {{
+                                        if (type % 2uL == 0uL) throw WireException("unknown even tlv type $type")
+                                        unknown.add(type to value)
+                                    }}
+                                }}
+                            }}
+                            return {name}({args})
+                        }}
+                    }}
+                }}
+
+            "#},
+            stream = tlv.stream_name,
+            name = name,
+            params = params,
+            encode = encode,
+            vars = vars,
+            cases = cases,
+            args = args.join(", "),
+            equals = Self::build_content_equals(&name, &fields),
+        )
+    }
+}
+
+impl<'g> CodeGen<'g> for KotlinCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        KotlinCodeGen::with_package(symbol_table, "lnwire")
+    }
+
+    fn raw_arrays(&self) -> bool {
+        true
+    }
+
+    fn pre_generation(&mut self) {
+        self.file_content += "// code generated by the lncodegen.rs please do not edit\n";
+        self.file_content += &format!("package {}\n\n", self.package);
+        self.file_content += "import java.io.ByteArrayOutputStream\n\n";
+        self.file_content += KOTLIN_RUNTIME;
+        self.file_content += "\n";
+    }
+
+    fn post_generation(&mut self) {
+        self.file_content += &format!(
+            indoc! {r#"
+                /** Decode the message, including the type, to the right data class. */
+                fun decodeMessage(wire: ByteArray): Any =
+                    when (val type = WireReader(wire).u16().toInt()) {{
+                {cases}        else -> throw WireException("unknown message type $type")
+                    }}
+            "#},
+            cases = self.msg_cases
+        );
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        self.file_content += &Self::build_tlv_stream_class(tlv);
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_msg = Some(msg.to_owned());
+        let name = Self::kt_type_name(&msg.msg_name);
+        self.msg_cases += &format!("        {} -> {name}.decode(wire)\n", msg.msg_typ);
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let name = Self::kt_type_name(&msg.msg_name);
+        self.file_content += &Self::build_msg_doc(msg);
+        if self.class_params.is_empty() {
+            // a data class needs at least one parameter.
+            self.file_content += &format!("class {name} {{\n");
+        } else {
+            self.file_content += &format!("data class {name}(\n{}) {{\n", self.class_params);
+            self.file_content += &Self::build_content_equals(&name, &self.class_fields);
+        }
+        self.file_content += &format!(
+            "    fun encode(): ByteArray {{\n{}    }}\n\n",
+            self.encode_fn
+        );
+        self.file_content += "    companion object {\n";
+        self.file_content += &format!("        const val TYPE: UShort = {}u\n\n", msg.msg_typ);
+        self.file_content += &format!(
+            "        fun decode(wire: ByteArray): {name} {{\n{}        }}\n",
+            self.decode_fn
+        );
+        self.file_content += "    }\n}\n\n";
+        self.class_params.clear();
+        self.class_fields.clear();
+        self.encode_fn.clear();
+        self.decode_fn.clear();
+        self.curr_msg = None;
+    }
+
+    fn build_decode_fun(&mut self) {
+        self.decode_fn += "            val reader = WireReader(wire)\n";
+        self.decode_fn +=
+            "            if (reader.u16() != TYPE) throw WireException(\"unexpected message type\")\n";
+    }
+
+    fn end_decode_fn(&mut self) {
+        let msg = self.curr_msg.as_ref().unwrap();
+        let name = Self::kt_type_name(&msg.msg_name);
+        let args = msg
+            .msg_data
+            .iter()
+            .map(|field| match field {
+                LNMsData::TLVinit(stream, _) => Self::kt_ident(stream),
+                _ => Self::kt_ident(field.name()),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.decode_fn += &format!("            return {name}({args})\n");
+    }
+
+    fn build_encode_fn(&mut self) {
+        self.encode_fn += "        val writer = WireWriter()\n";
+        self.write_field("u16(TYPE)");
+    }
+
+    fn end_encode_fn(&mut self) {
+        self.encode_fn += "        return writer.toByteArray()\n";
+    }
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.build_field(name, "UShort", "reader.u16()");
+        }
+    }
+
+    fn write_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.write_field(&format!("u16({})", Self::kt_ident(name)));
+        }
+    }
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_field(name, "UInt", "reader.u32()");
+        }
+    }
+
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_field(&format!("u32({})", Self::kt_ident(name)));
+        }
+    }
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_field(name, "ULong", "reader.u64()");
+        }
+    }
+
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_field(&format!("u64({})", Self::kt_ident(name)));
+        }
+    }
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.build_field(name, "ULong", "reader.bigsize()");
+        }
+    }
+
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.write_field(&format!("bigsize({})", Self::kt_ident(name)));
+        }
+    }
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_chain_hash(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_signature(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_point(&mut self, field: &LNMsData) {
+        if let LNMsData::Point(name) = field {
+            self.build_field(name, "Point", "Point(reader.bytes(33))");
+        }
+    }
+
+    fn write_point(&mut self, field: &LNMsData) {
+        if let LNMsData::Point(name) = field {
+            self.write_field(&format!("fixed({}.bytes, 33)", Self::kt_ident(name)));
+        }
+    }
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_sha256(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let elem = LNMsData::element_size(ty).unwrap();
+            let read = if let Ok(size) = size.parse::<u64>() {
+                format!("reader.bytes({})", size * elem)
+            } else if size == "..." {
+                "reader.rest()".to_owned()
+            } else if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let size = Self::kt_ident(size);
+                match elem {
+                    1 => format!("reader.bytes({size}.toInt())"),
+                    _ => format!("reader.bytes({size}.toInt() * {elem})"),
+                }
+            } else {
+                match elem {
+                    1 => "reader.varBytes()".to_owned(),
+                    _ => format!("reader.varBytes({elem})"),
+                }
+            };
+            self.build_field(name, "ByteArray", &read);
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let name = Self::kt_ident(name);
+            let elem = LNMsData::element_size(ty).unwrap();
+            if let Ok(size) = size.parse::<u64>() {
+                self.write_field(&format!("fixed({name}, {})", size * elem));
+            } else if size == "..."
+                || self
+                    .curr_msg
+                    .as_ref()
+                    .is_some_and(|msg| msg.is_length_field(size))
+            {
+                self.write_field(&format!("bytes({name})"));
+            } else if elem == 1 {
+                self.write_field(&format!("varBytes({name})"));
+            } else {
+                self.write_field(&format!("varBytes({name}, {elem})"));
+            }
+        }
+    }
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = Self::kt_type_name(&field.stream_name);
+        let name = Self::kt_ident(&field.stream_name);
+        self.class_params += &format!("    val {name}: {ty} = {ty}(),\n");
+        self.decode_fn += &format!("            val {name} = {ty}.decode(reader)\n");
+        self.class_fields.push((name, ty));
+    }
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = Self::kt_ident(&field.stream_name);
+        self.encode_fn += &format!("        {name}.encode(writer)\n");
+    }
+}
+
+impl Display for KotlinCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::codegen::CodeGen;
+    use crate::kotlin::KotlinCodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+
+    const INIT_CSV: &str = "msgtype,init,16\n\
+                           msgdata,init,gflen,u16,\n\
+                           msgdata,init,globalfeatures,byte,gflen\n\
+                           msgdata,init,node_id,point,\n\
+                           msgdata,init,htlc_maximum_msat,u64,\n\
+                           msgdata,init,tlvs,init_tlvs,\n\
+                           tlvtype,init_tlvs,networks,1\n\
+                           tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+
+    #[test]
+    fn kotlin_init_msg() {
+        init();
+        let symbol_table = parse(INIT_CSV);
+        let mut backend = KotlinCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("value class Point(val bytes: ByteArray) {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("data class Init(\n    val globalfeatures: ByteArray,\n    val nodeId: Point,\n    val htlcMaximumMsat: ULong,\n    val initTlvs: InitTlvs = InitTlvs(),\n) {"),
+            "{code}"
+        );
+        assert!(code.contains("    fun encode(): ByteArray {\n"), "{code}");
+        assert!(
+            code.contains("        fun decode(wire: ByteArray): Init {\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("            val globalfeatures = reader.varBytes()\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    val networks: ByteArray? = null,\n"),
+            "{code}"
+        );
+        assert!(code.contains("        16 -> Init.decode(wire)\n"), "{code}");
+        assert!(
+            code.contains("        other is Init &&\n            globalfeatures.contentEquals(other.globalfeatures) &&\n            nodeId.bytes.contentEquals(other.nodeId.bytes) &&\n            htlcMaximumMsat == other.htlcMaximumMsat &&\n            initTlvs == other.initTlvs\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("        var result = networks.contentHashCode()\n        result = 31 * result + recordsHashCode(unknown)\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires kotlinc, run by the toolchains job of the CI"]
+    fn kotlin_init_msg_kotlinc() {
+        init();
+        let symbol_table = parse(INIT_CSV);
+        let mut backend = KotlinCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("kotlin_init_msg");
+        fs::write(dir.join("Lnwire.kt"), backend.to_string()).unwrap();
+        run_cmd(
+            Command::new("kotlinc")
+                .args(["-d", "out", "Lnwire.kt"])
+                .current_dir(&dir),
+        );
+    }
+
+    const SIGNATURES_CSV: &str = "msgtype,commitment_signed,132\n\
+                                  msgdata,commitment_signed,num_htlcs,u16,\n\
+                                  msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                                  msgtype,points,1\n\
+                                  msgdata,points,num_points,u16,\n\
+                                  msgdata,points,flags,u16,\n\
+                                  msgdata,points,points,point,num_points\n";
+
+    #[test]
+    fn kotlin_array_elements() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = KotlinCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains("            val htlcSignature = reader.varBytes(64)\n"),
+            "{code}"
+        );
+        assert!(code.contains("varBytes(htlcSignature, 64)"), "{code}");
+        assert!(
+            code.contains("            val points = reader.bytes(numPoints.toInt() * 33)\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires kotlinc, run by the toolchains job of the CI"]
+    fn kotlin_array_elements_round_trip() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = KotlinCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("kotlin_array_elements");
+        fs::write(dir.join("Lnwire.kt"), backend.to_string()).unwrap();
+        let main = indoc! {r#"
+            package lnwire
+
+            fun main() {
+                val msg = CommitmentSigned(ByteArray(128) { 0x5a })
+                val wire = msg.encode()
+                check(wire.size == 2 + 2 + 128 && wire[3].toInt() == 2) { "unexpected wire" }
+                check(CommitmentSigned.decode(wire) == msg)
+                try {
+                    CommitmentSigned(ByteArray(127)).encode()
+                    error("partial signature accepted")
+                } catch (err: WireException) {
+                }
+                println("ok")
+            }
+        "#};
+        fs::write(dir.join("Main.kt"), main).unwrap();
+        run_cmd(
+            Command::new("kotlinc")
+                .args(["Lnwire.kt", "Main.kt", "-include-runtime", "-d", "main.jar"])
+                .current_dir(&dir),
+        );
+        let stdout = run_cmd(
+            Command::new("java")
+                .args(["-jar", "main.jar"])
+                .current_dir(&dir),
+        );
+        assert_eq!(stdout, "ok\n");
+    }
+}
@@ -0,0 +1,277 @@
+//! Byte layout of a lightning network message, rendered
+//! as an ascii diagram or as a svg image.
+use std::fmt::Display;
+
+use csvlang::parser::ast::{LNMsData, LNMsg};
+
+/// The offset of a field, that is the size of the fixed part
+/// before the field plus the length of the variable regions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayoutOffset {
+    pub fixed: u64,
+    pub vars: Vec<String>,
+}
+
+impl Display for LayoutOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.fixed)?;
+        for var in &self.vars {
+            write!(f, "+{var}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A region of the message on the wire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutField {
+    pub name: String,
+    pub csv_type: String,
+    pub offset: LayoutOffset,
+    /// The size in bytes, `None` for the variable regions.
+    pub size: Option<u64>,
+    /// The field that contains the length of a variable region, if any.
+    pub length_field: Option<String>,
+}
+
+impl LayoutField {
+    fn size_label(&self) -> String {
+        match (self.size, &self.length_field) {
+            (Some(size), _) => size.to_string(),
+            (None, Some(len)) => len.to_owned(),
+            (None, None) => "...".to_owned(),
+        }
+    }
+
+    fn note(&self, layout: &MsgLayout) -> String {
+        match &self.length_field {
+            Some(len) => match layout.field(len) {
+                Some(field) => format!("length in {len} @ {}", field.offset),
+                None => format!("length in {len}"),
+            },
+            None if self.size.is_none() => "till the end of the message".to_owned(),
+            None => String::new(),
+        }
+    }
+}
+
+/// The layout of a message, starting from the type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MsgLayout {
+    pub msg_name: String,
+    pub msg_typ: u64,
+    pub fields: Vec<LayoutField>,
+}
+
+impl MsgLayout {
+    pub fn new(msg: &LNMsg) -> Self {
+        let mut layout = MsgLayout {
+            msg_name: msg.msg_name.to_owned(),
+            msg_typ: msg.msg_typ,
+            fields: Vec::new(),
+        };
+        let mut offset = LayoutOffset::default();
+        layout.push(&mut offset, "type", "u16", Some(2), None);
+        for field in &msg.msg_data {
+            let name = match field {
+                LNMsData::TLVinit(stream, _) => stream.as_str(),
+                _ => field.name(),
+            };
+            let csv_type = field.csv_type();
+            match field {
+                LNMsData::BitfieldStream(_, size, _) if field.fixed_size().is_none() => {
+                    if size == "..." {
+                        layout.push(&mut offset, name, &csv_type, None, None);
+                        continue;
+                    }
+                    if !msg.is_length_field(size) {
+                        // the u16 length was folded inside the bitfield.
+                        layout.push(&mut offset, size, "u16", Some(2), None);
+                    }
+                    layout.push(&mut offset, name, &csv_type, None, Some(size));
+                }
+                LNMsData::TLVinit(..) => {
+                    layout.push(&mut offset, name, &csv_type, None, None);
+                }
+                _ => layout.push(&mut offset, name, &csv_type, field.fixed_size(), None),
+            }
+        }
+        layout
+    }
+
+    fn push(
+        &mut self,
+        offset: &mut LayoutOffset,
+        name: &str,
+        csv_type: &str,
+        size: Option<u64>,
+        length_field: Option<&str>,
+    ) {
+        self.fields.push(LayoutField {
+            name: name.to_owned(),
+            csv_type: csv_type.to_owned(),
+            offset: offset.clone(),
+            size,
+            length_field: length_field.map(str::to_owned),
+        });
+        match (size, length_field) {
+            (Some(size), _) => offset.fixed += size,
+            (None, Some(len)) => offset.vars.push(len.to_owned()),
+            (None, None) => offset.vars.push(format!("len({name})")),
+        }
+    }
+
+    /// Return the field with the name provided.
+    pub fn field(&self, name: &str) -> Option<&LayoutField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Render the layout as an ascii diagram, the variable regions
+    /// are closed by a `~` border.
+    pub fn ascii(&self) -> String {
+        let headers = ["offset", "size", "field", "type", "note"];
+        let rows = self
+            .fields
+            .iter()
+            .map(|field| {
+                [
+                    field.offset.to_string(),
+                    field.size_label(),
+                    field.name.to_owned(),
+                    field.csv_type.to_owned(),
+                    field.note(self),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let widths = (0..headers.len())
+            .map(|col| {
+                rows.iter()
+                    .map(|row| row[col].len())
+                    .chain([headers[col].len()])
+                    .max()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let border = |fill: char| {
+            let cells = widths
+                .iter()
+                .map(|width| fill.to_string().repeat(width + 2))
+                .collect::<Vec<_>>();
+            format!("+{}+\n", cells.join("+"))
+        };
+        let line = |cells: &[String]| {
+            let cells = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!(" {cell:<width$} "))
+                .collect::<Vec<_>>();
+            format!("|{}|\n", cells.join("|"))
+        };
+
+        let mut code = format!("{} (type {})\n\n", self.msg_name, self.msg_typ);
+        code += &border('-');
+        code += &line(&headers.map(str::to_owned));
+        code += &border('=');
+        for (field, row) in self.fields.iter().zip(&rows) {
+            code += &line(row);
+            code += &border(if field.size.is_some() { '-' } else { '~' });
+        }
+        code
+    }
+
+    /// Render the layout as a svg image, the variable regions are dashed
+    /// and linked to their length field.
+    pub fn svg(&self) -> String {
+        const WIDTH: usize = 640;
+        const ROW: usize = 32;
+        const TOP: usize = 40;
+        let height = TOP + ROW * self.fields.len() + 10;
+        let mut code = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{height}\" font-family=\"monospace\" font-size=\"13\">\n"
+        );
+        code += "  <defs><marker id=\"arrow\" markerWidth=\"8\" markerHeight=\"8\" refX=\"6\" refY=\"4\" orient=\"auto\"><path d=\"M0,0 L8,4 L0,8 z\"/></marker></defs>\n";
+        code += &format!(
+            "  <text x=\"10\" y=\"24\" font-weight=\"bold\">{} (type {})</text>\n",
+            self.msg_name, self.msg_typ
+        );
+        for (idx, field) in self.fields.iter().enumerate() {
+            let y = TOP + idx * ROW;
+            let dash = if field.size.is_some() {
+                ""
+            } else {
+                " stroke-dasharray=\"6 3\""
+            };
+            code += &format!(
+                "  <rect x=\"10\" y=\"{y}\" width=\"{}\" height=\"{ROW}\" fill=\"none\" stroke=\"black\"{dash}/>\n",
+                WIDTH - 60
+            );
+            code += &format!(
+                "  <text x=\"18\" y=\"{}\">{}</text>\n",
+                y + 20,
+                field.offset
+            );
+            code += &format!(
+                "  <text x=\"150\" y=\"{}\">{}: {} [{}]</text>\n",
+                y + 20,
+                field.name,
+                field.csv_type,
+                field.size_label()
+            );
+            let len = field
+                .length_field
+                .as_ref()
+                .and_then(|len| self.fields.iter().position(|field| &field.name == len));
+            if let Some(len) = len {
+                let from = TOP + len * ROW + ROW / 2;
+                let to = y + ROW / 2;
+                let x = WIDTH - 50;
+                code += &format!(
+                    "  <path d=\"M{x},{from} C{},{from} {},{to} {x},{to}\" fill=\"none\" stroke=\"black\" marker-end=\"url(#arrow)\"/>\n",
+                    x + 35,
+                    x + 35
+                );
+            }
+        }
+        code += "</svg>\n";
+        code
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use csvlang::parser::ast::LNMsgType;
+
+    use crate::layout::MsgLayout;
+    use crate::testing::{init, parse};
+
+    #[test]
+    fn ascii_layout_msg() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,node_id,point,\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let Some(LNMsgType::Msg(msg)) = symbol_table.get("init") else {
+            panic!("init message not found");
+        };
+        let layout = MsgLayout::new(msg);
+        let node_id = layout.field("node_id").unwrap();
+        assert_eq!(node_id.offset.to_string(), "4+gflen");
+        assert_eq!(node_id.size, Some(33));
+        let diagram = layout.ascii();
+        assert!(
+            diagram.contains("| 4        | gflen | globalfeatures | byte[gflen] | length in gflen @ 2         |\n\
+                              +~~~~~~~~~~+"),
+            "{diagram}"
+        );
+        assert!(
+            diagram.contains("| 37+gflen | ...   | init_tlvs      | init_tlvs   | till the end of the message |\n"),
+            "{diagram}"
+        );
+        assert!(layout.svg().contains("stroke-dasharray"));
+    }
+}
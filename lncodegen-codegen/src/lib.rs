@@ -1,18 +1,21 @@
 //! Take `AST (-> Verify the validity of the message) -> Generate code`
+pub mod c;
 pub mod codegen;
+pub mod diff;
+pub mod docs;
+pub mod go;
+pub mod jsonschema;
+pub mod kaitai;
+pub mod kotlin;
+pub mod layout;
+pub mod lint;
+pub mod proto;
+pub mod python;
 pub mod rust;
+pub mod swift;
+pub mod typescript;
+pub mod wireshark;
+pub mod zig;
 
 #[cfg(test)]
-mod test {
-    #![allow(dead_code)]
-    use std::sync::Once;
-
-    static INIT: Once = Once::new();
-
-    fn init() {
-        // ignore error
-        INIT.call_once(|| {
-            env_logger::init();
-        });
-    }
-}
+mod testing;
@@ -0,0 +1,421 @@
+//! Lint rules for the csv files that extend the lightning
+//! network specification with experimental messages.
+//!
+//! The rules look at the symbol table, so the file must be
+//! parsed before, and every rule has a level that can be
+//! changed by the caller, like the compiler lints.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use csvlang::parser::ast::{LNMsData, LNMsgType};
+
+/// The messages defined by the BOLTs.
+const BOLT_MSGS: &[(u64, &str)] = &[
+    (1, "warning"),
+    (2, "stfu"),
+    (7, "peer_storage"),
+    (9, "peer_storage_retrieval"),
+    (16, "init"),
+    (17, "error"),
+    (18, "ping"),
+    (19, "pong"),
+    (32, "open_channel"),
+    (33, "accept_channel"),
+    (34, "funding_created"),
+    (35, "funding_signed"),
+    (36, "channel_ready"),
+    (38, "shutdown"),
+    (39, "closing_signed"),
+    (40, "closing_complete"),
+    (41, "closing_sig"),
+    (64, "open_channel2"),
+    (65, "accept_channel2"),
+    (66, "tx_add_input"),
+    (67, "tx_add_output"),
+    (68, "tx_remove_input"),
+    (69, "tx_remove_output"),
+    (70, "tx_complete"),
+    (71, "tx_signatures"),
+    (72, "tx_init_rbf"),
+    (73, "tx_ack_rbf"),
+    (74, "tx_abort"),
+    (77, "splice_locked"),
+    (80, "splice_init"),
+    (81, "splice_ack"),
+    (128, "update_add_htlc"),
+    (130, "update_fulfill_htlc"),
+    (131, "update_fail_htlc"),
+    (132, "commitment_signed"),
+    (133, "revoke_and_ack"),
+    (134, "update_fee"),
+    (135, "update_fail_malformed_htlc"),
+    (136, "channel_reestablish"),
+    (256, "channel_announcement"),
+    (257, "node_announcement"),
+    (258, "channel_update"),
+    (259, "announcement_signatures"),
+    (261, "query_short_channel_ids"),
+    (262, "reply_short_channel_ids_end"),
+    (263, "query_channel_range"),
+    (264, "reply_channel_range"),
+    (265, "gossip_timestamp_filter"),
+    (513, "onion_message"),
+];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+    "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+    "use", "virtual", "where", "while", "yield",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "false", "finally", "for", "from", "global", "if", "import", "in", "is",
+    "lambda", "none", "nonlocal", "not", "or", "pass", "raise", "return", "true", "try", "while",
+    "with", "yield",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintRule {
+    /// A message uses the type of a message defined by the BOLTs,
+    /// or of another message of the file.
+    MsgTypeCollision,
+    /// An experimental message uses an even type, so the peers
+    /// that do not understand it close the connection.
+    EvenMsgType,
+    /// A tlv record uses an even type.
+    EvenTlvType,
+    /// Two records of the same tlv stream use the same type.
+    DuplicateTlvType,
+    /// A subtype that is not used by any message or tlv record.
+    UnusedSubtype,
+    /// A name that is a keyword of the generated languages.
+    KeywordName,
+    /// A name that is not in snake case.
+    NamingConvention,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 7] = [
+        LintRule::MsgTypeCollision,
+        LintRule::EvenMsgType,
+        LintRule::EvenTlvType,
+        LintRule::DuplicateTlvType,
+        LintRule::UnusedSubtype,
+        LintRule::KeywordName,
+        LintRule::NamingConvention,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::MsgTypeCollision => "msg-type-collision",
+            LintRule::EvenMsgType => "even-msg-type",
+            LintRule::EvenTlvType => "even-tlv-type",
+            LintRule::DuplicateTlvType => "duplicate-tlv-type",
+            LintRule::UnusedSubtype => "unused-subtype",
+            LintRule::KeywordName => "keyword-name",
+            LintRule::NamingConvention => "naming-convention",
+        }
+    }
+
+    /// The level of the rule when it is not configured.
+    pub fn default_level(&self) -> LintLevel {
+        match self {
+            LintRule::MsgTypeCollision | LintRule::DuplicateTlvType => LintLevel::Deny,
+            _ => LintLevel::Warn,
+        }
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintRule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| format!("unknown lint rule `{s}`"))
+    }
+}
+
+impl Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// A problem found by a lint rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintDiagnostic {
+    pub rule: LintRule,
+    pub level: LintLevel,
+    pub message: String,
+}
+
+impl Display for LintDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.level {
+            LintLevel::Deny => "error",
+            _ => "warning",
+        };
+        write!(f, "{level}[{}]: {}", self.rule, self.message)
+    }
+}
+
+pub struct Linter {
+    levels: BTreeMap<LintRule, LintLevel>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Linter {
+            levels: LintRule::ALL
+                .into_iter()
+                .map(|rule| (rule, rule.default_level()))
+                .collect(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Change the level of a rule.
+    pub fn set_level(&mut self, rule: LintRule, level: LintLevel) {
+        self.levels.insert(rule, level);
+    }
+
+    fn report(&mut self, rule: LintRule, message: String) {
+        let level = self.levels[&rule];
+        if level == LintLevel::Allow {
+            return;
+        }
+        self.diagnostics.push(LintDiagnostic {
+            rule,
+            level,
+            message,
+        });
+    }
+
+    /// Run all the rules on the symbol table and return the
+    /// problems found.
+    pub fn check(mut self, symbol_table: &BTreeMap<String, LNMsgType>) -> Vec<LintDiagnostic> {
+        self.check_msg_types(symbol_table);
+        self.check_tlv_types(symbol_table);
+        self.check_subtypes(symbol_table);
+        self.check_names(symbol_table);
+        self.diagnostics
+    }
+
+    fn check_msg_types(&mut self, symbol_table: &BTreeMap<String, LNMsgType>) {
+        let mut types: BTreeMap<u64, &str> = BTreeMap::new();
+        for item in symbol_table.values() {
+            let LNMsgType::Msg(msg) = item else {
+                continue;
+            };
+            let typ = msg.msg_typ;
+            let name = msg.msg_name.as_str();
+            if let Some((_, bolt_name)) = BOLT_MSGS.iter().find(|(bolt_typ, _)| *bolt_typ == typ) {
+                self.report(
+                    LintRule::MsgTypeCollision,
+                    format!(
+                        "message `{name}` uses the type {typ} of the BOLT message `{bolt_name}`"
+                    ),
+                );
+                continue;
+            }
+            if let Some(other) = types.insert(typ, name) {
+                self.report(
+                    LintRule::MsgTypeCollision,
+                    format!("message `{name}` uses the type {typ} of the message `{other}`"),
+                );
+            }
+            if typ.is_multiple_of(2) {
+                self.report(
+                    LintRule::EvenMsgType,
+                    format!("experimental message `{name}` uses the even type {typ}, the peers that do not understand it will close the connection"),
+                );
+            }
+        }
+    }
+
+    fn check_tlv_types(&mut self, symbol_table: &BTreeMap<String, LNMsgType>) {
+        for item in symbol_table.values() {
+            let LNMsgType::Tlv(stream) = item else {
+                continue;
+            };
+            let name = &stream.stream_name;
+            let mut types: BTreeMap<u64, &str> = BTreeMap::new();
+            for record in &stream.records {
+                let typ = record.type_len;
+                let record_name = record.type_name.as_str();
+                if let Some(other) = types.insert(typ, record_name) {
+                    self.report(
+                        LintRule::DuplicateTlvType,
+                        format!("record `{record_name}` of `{name}` uses the type {typ} of the record `{other}`"),
+                    );
+                }
+                if typ.is_multiple_of(2) {
+                    self.report(
+                        LintRule::EvenTlvType,
+                        format!("record `{record_name}` of `{name}` uses the even type {typ}, the peers that do not understand it will reject the message"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_subtypes(&mut self, symbol_table: &BTreeMap<String, LNMsgType>) {
+        let is_used = |subtype: &str| {
+            let in_fields = |fields: &[LNMsData]| {
+                fields.iter().any(|field| match field {
+                    LNMsData::BitfieldStream(_, _, ty) => ty == subtype,
+                    _ => field.csv_type() == subtype,
+                })
+            };
+            symbol_table.values().any(|item| match item {
+                LNMsgType::Msg(msg) => in_fields(&msg.msg_data),
+                LNMsgType::SubType(other) => other.ty != subtype && in_fields(&other.ty_data),
+                LNMsgType::Tlv(stream) => stream.records.iter().any(|record| {
+                    record
+                        .record_entry
+                        .iter()
+                        .any(|entry| entry.entry_ty == subtype)
+                }),
+            })
+        };
+        for item in symbol_table.values() {
+            if let LNMsgType::SubType(subtype) = item {
+                if !is_used(&subtype.ty) {
+                    self.report(
+                        LintRule::UnusedSubtype,
+                        format!("subtype `{}` is never used", subtype.ty),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check the name of an item, the parent is the item where it is declared.
+    fn check_name(&mut self, kind: &str, name: &str, parent: Option<&str>) {
+        let item = match parent {
+            Some(parent) => format!("{kind} `{name}` of `{parent}`"),
+            None => format!("{kind} `{name}`"),
+        };
+        let lower = name.to_lowercase();
+        let langs = [("rust", RUST_KEYWORDS), ("python", PYTHON_KEYWORDS)]
+            .into_iter()
+            .filter(|(_, keywords)| keywords.contains(&lower.as_str()))
+            .map(|(lang, _)| lang)
+            .collect::<Vec<_>>();
+        if !langs.is_empty() {
+            self.report(
+                LintRule::KeywordName,
+                format!("{item} is a keyword in {}", langs.join(" and ")),
+            );
+        }
+        let is_snake_case = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            && !name.contains("__")
+            && !name.ends_with('_');
+        if !is_snake_case {
+            self.report(
+                LintRule::NamingConvention,
+                format!("{item} is not in snake case"),
+            );
+        }
+    }
+
+    fn check_names(&mut self, symbol_table: &BTreeMap<String, LNMsgType>) {
+        for item in symbol_table.values() {
+            match item {
+                LNMsgType::Msg(msg) => {
+                    self.check_name("message", &msg.msg_name, None);
+                    for field in &msg.msg_data {
+                        self.check_name("field", field.name(), Some(&msg.msg_name));
+                    }
+                }
+                LNMsgType::SubType(subtype) => {
+                    self.check_name("subtype", &subtype.ty, None);
+                    for field in &subtype.ty_data {
+                        self.check_name("field", field.name(), Some(&subtype.ty));
+                    }
+                }
+                LNMsgType::Tlv(stream) => {
+                    self.check_name("tlv stream", &stream.stream_name, None);
+                    for record in &stream.records {
+                        self.check_name("record", &record.type_name, Some(&stream.stream_name));
+                        for entry in &record.record_entry {
+                            self.check_name("entry", &entry.entry_name, Some(&record.type_name));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lint::{LintLevel, LintRule, Linter};
+    use crate::testing::{init, parse};
+
+    #[test]
+    fn lint_experimental_msgs() {
+        init();
+        let symbol_table = parse(
+            "msgtype,my_ping,18\n\
+             msgdata,my_ping,type,u16,\n\
+             msgtype,swapRequest,32768\n\
+             msgdata,swapRequest,amount,u64,\n\
+             msgdata,swapRequest,tlvs,swap_tlvs,\n\
+             tlvtype,swap_tlvs,fee,1\n\
+             tlvdata,swap_tlvs,fee,fee,u64,\n\
+             tlvtype,swap_tlvs,premium,1\n\
+             tlvdata,swap_tlvs,premium,premium,u64,\n\
+             tlvtype,swap_tlvs,network,2\n\
+             tlvdata,swap_tlvs,network,chain,chain_hash,\n",
+        );
+        let lints = Linter::new()
+            .check(&symbol_table)
+            .iter()
+            .map(|lint| lint.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            [
+                "error[msg-type-collision]: message `my_ping` uses the type 18 of the BOLT message `ping`",
+                "warning[even-msg-type]: experimental message `swapRequest` uses the even type 32768, the peers that do not understand it will close the connection",
+                "error[duplicate-tlv-type]: record `premium` of `swap_tlvs` uses the type 1 of the record `fee`",
+                "warning[even-tlv-type]: record `network` of `swap_tlvs` uses the even type 2, the peers that do not understand it will reject the message",
+                "warning[keyword-name]: field `type` of `my_ping` is a keyword in rust",
+                "warning[naming-convention]: message `swapRequest` is not in snake case",
+            ]
+        );
+
+        let mut linter = Linter::new();
+        linter.set_level(LintRule::EvenTlvType, LintLevel::Allow);
+        linter.set_level("naming-convention".parse().unwrap(), LintLevel::Deny);
+        let lints = linter.check(&symbol_table);
+        assert!(lints.iter().all(|lint| lint.rule != LintRule::EvenTlvType));
+        assert!(lints
+            .iter()
+            .any(|lint| lint.rule == LintRule::NamingConvention && lint.level == LintLevel::Deny));
+    }
+}
@@ -0,0 +1,255 @@
+//! Protocol Buffers export of the lightning
+//! network specification.
+//!
+//! The messages are described as proto3 messages, and every
+//! field carries a comment with the original csv type.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+pub struct ProtoCodeGen {
+    package: String,
+    /// The fields of the message that we are building.
+    fields: Vec<String>,
+    /// The values of the `MessageType` enum.
+    msg_types: String,
+    file_content: String,
+}
+
+impl ProtoCodeGen {
+    /// Build a new proto backend that declares the package provided.
+    pub fn with_package(_symbol_table: &BTreeMap<String, LNMsgType>, package: &str) -> Self {
+        ProtoCodeGen {
+            package: package.to_owned(),
+            fields: Vec::new(),
+            msg_types: String::new(),
+            file_content: String::new(),
+        }
+    }
+
+    fn proto_type_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    /// Map the field to the proto type, all the byte arrays are `bytes`.
+    fn proto_type(field: &LNMsData) -> String {
+        match field {
+            LNMsData::Uint16(_) | LNMsData::Uint32(_) => "uint32".to_owned(),
+            LNMsData::Uint64(_) | LNMsData::BigSize(_) => "uint64".to_owned(),
+            LNMsData::TLVinit(stream, _) => Self::proto_type_name(stream),
+            _ => "bytes".to_owned(),
+        }
+    }
+
+    fn add_field(&mut self, field: &LNMsData) {
+        let name = match field {
+            LNMsData::TLVinit(stream, _) => stream.as_str(),
+            _ => field.name(),
+        };
+        let number = self.fields.len() + 1;
+        self.fields.push(format!(
+            "  {} {name} = {number}; // {}\n",
+            Self::proto_type(field),
+            field.csv_type()
+        ));
+    }
+
+    fn build_message(&mut self, doc: &str, name: &str) -> String {
+        let mut code = format!("// {doc}\nmessage {name} {{\n");
+        for field in self.fields.drain(..) {
+            code += &field;
+        }
+        code += "}\n\n";
+        code
+    }
+}
+
+impl<'g> CodeGen<'g> for ProtoCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        ProtoCodeGen::with_package(symbol_table, "lnwire")
+    }
+
+    fn pre_generation(&mut self) {
+        self.file_content += "// code generated by the lncodegen.rs please do not edit\n";
+        self.file_content += "syntax = \"proto3\";\n\n";
+        self.file_content += &format!("package {};\n\n", self.package);
+    }
+
+    fn post_generation(&mut self) {
+        self.file_content += "// The type of the message on the wire.\n";
+        self.file_content += "enum MessageType {\n  MESSAGE_TYPE_UNSPECIFIED = 0;\n";
+        self.file_content += &self.msg_types;
+        self.file_content += "}\n";
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        let mut code = format!(
+            "// The `{}` tlv stream.\nmessage {} {{\n",
+            tlv.stream_name,
+            Self::proto_type_name(&tlv.stream_name)
+        );
+        for (idx, record) in tlv.records.iter().enumerate() {
+            let entries = record
+                .record_entry
+                .iter()
+                .map(|entry| format!("{}: {}", entry.entry_name, entry.csv_type()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            code += &format!(
+                "  optional bytes {} = {}; // type {}, {entries}\n",
+                record.type_name,
+                idx + 1,
+                record.type_len
+            );
+        }
+        code += "}\n\n";
+        self.file_content += &code;
+    }
+
+    fn generate_subtype(&mut self, subtyp: &LNSubType) {
+        for field in &subtyp.ty_data {
+            self.add_field(field);
+        }
+        let doc = format!("The `{}` subtype.", subtyp.ty);
+        let code = self.build_message(&doc, &Self::proto_type_name(&subtyp.ty));
+        self.file_content += &code;
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.msg_types += &format!(
+            "  MESSAGE_TYPE_{} = {};\n",
+            msg.msg_name.to_case(Case::UpperSnake),
+            msg.msg_typ
+        );
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let doc = format!("The `{}` message, type {}.", msg.msg_name, msg.msg_typ);
+        let code = self.build_message(&doc, &Self::proto_type_name(&msg.msg_name));
+        self.file_content += &code;
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u16(&mut self, _: &LNMsData) {}
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u32(&mut self, _: &LNMsData) {}
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_u64(&mut self, _: &LNMsData) {}
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bigsize(&mut self, _: &LNMsData) {}
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_chain_hash(&mut self, _: &LNMsData) {}
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_short_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_signature(&mut self, _: &LNMsData) {}
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_point(&mut self, _: &LNMsData) {}
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_sha256(&mut self, _: &LNMsData) {}
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        self.add_field(field);
+    }
+
+    fn write_bitfiled(&mut self, _: &LNMsData) {}
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let field = LNMsData::TLVinit(field.stream_name.to_owned(), field.stream_name.to_owned());
+        self.add_field(&field);
+    }
+
+    fn write_tlv_stream(&mut self, _: &LNTlvStream) {}
+}
+
+impl Display for ProtoCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codegen::CodeGen;
+    use crate::proto::ProtoCodeGen;
+    use crate::testing::{init, parse};
+
+    #[test]
+    fn proto_init_msg() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,htlc_maximum_msat,u64,\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let mut backend = ProtoCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("message Init {\n  bytes globalfeatures = 1; // byte[gflen]\n  uint64 htlc_maximum_msat = 2; // u64\n  InitTlvs init_tlvs = 3; // init_tlvs\n}"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  optional bytes networks = 1; // type 1, chains: chain_hash[...]\n"),
+            "{code}"
+        );
+        assert!(code.contains("  MESSAGE_TYPE_INIT = 16;\n"), "{code}");
+    }
+}
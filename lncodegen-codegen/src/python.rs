@@ -10,13 +10,166 @@
 //! author: Vincenzo Palazzo <vincenzopalazzodev@gmail.com>
 use crate::codegen::CodeGen;
 use convert_case::{Case, Casing};
-use frontend_csv::parser::ast::{LNMsData, LNMsg, LNMsgType};
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
 use indoc::indoc;
 use std::collections::{BTreeMap, HashSet};
 
+/// Options that change the shape of the generated python code.
+#[derive(Clone, Debug, Default)]
+pub struct PythonCodeGenOptions {
+    /// Emit a self-contained module with its own runtime and
+    /// `dataclasses` over `bytes`, instead of depending on `lnspec_py`.
+    pub standalone: bool,
+}
+
+/// Runtime used by the standalone module to encode and decode
+/// the lightning network types.
+const PYTHON_RUNTIME: &str = r#"# code generated by the lncodegen.rs please do not edit
+
+import dataclasses
+import io
+import struct
+from typing import Dict, Optional
+
+
+class WireError(ValueError):
+    """The message is not valid on the wire."""
+
+
+def read_exact(stream: io.BytesIO, size: int) -> bytes:
+    data = stream.read(size)
+    if len(data) != size:
+        raise WireError(f"expected {size} bytes, got {len(data)}")
+    return data
+
+
+def write_fixed(value: bytes, size: int) -> bytes:
+    if len(value) != size:
+        raise WireError(f"expected {size} bytes, got {len(value)}")
+    return bytes(value)
+
+
+def read_u16(stream: io.BytesIO) -> int:
+    return struct.unpack(">H", read_exact(stream, 2))[0]
+
+
+def write_u16(value: int) -> bytes:
+    return struct.pack(">H", value)
+
+
+def read_u32(stream: io.BytesIO) -> int:
+    return struct.unpack(">I", read_exact(stream, 4))[0]
+
+
+def write_u32(value: int) -> bytes:
+    return struct.pack(">I", value)
+
+
+def read_u64(stream: io.BytesIO) -> int:
+    return struct.unpack(">Q", read_exact(stream, 8))[0]
+
+
+def write_u64(value: int) -> bytes:
+    return struct.pack(">Q", value)
+
+
+def read_bigsize(stream: io.BytesIO) -> int:
+    prefix = read_exact(stream, 1)[0]
+    if prefix < 0xFD:
+        return prefix
+    if prefix == 0xFD:
+        value, minimum = read_u16(stream), 0xFD
+    elif prefix == 0xFE:
+        value, minimum = read_u32(stream), 0x10000
+    else:
+        value, minimum = read_u64(stream), 0x100000000
+    if value < minimum:
+        raise WireError("bigsize is not minimally encoded")
+    return value
+
+
+def write_bigsize(value: int) -> bytes:
+    if value < 0xFD:
+        return bytes([value])
+    if value <= 0xFFFF:
+        return b"\xfd" + write_u16(value)
+    if value <= 0xFFFFFFFF:
+        return b"\xfe" + write_u32(value)
+    return b"\xff" + write_u64(value)
+
+
+def read_truncated(value: bytes, size: int) -> int:
+    """Decode a truncated integer (tu16, tu32, tu64) of at most `size` bytes."""
+    if len(value) > size:
+        raise WireError(f"truncated integer longer than {size} bytes")
+    if value[:1] == b"\x00":
+        raise WireError("truncated integer is not minimally encoded")
+    return int.from_bytes(value, "big")
+
+
+def write_truncated(value: int) -> bytes:
+    return value.to_bytes((value.bit_length() + 7) // 8, "big")
+
+
+def read_tlv_stream(stream: io.BytesIO) -> Dict[int, bytes]:
+    """Read the tlv records till the end of the stream."""
+    records: Dict[int, bytes] = {}
+    last_type: Optional[int] = None
+    while stream.read(1):
+        stream.seek(-1, io.SEEK_CUR)
+        typ = read_bigsize(stream)
+        if last_type is not None and typ <= last_type:
+            raise WireError("tlv types are not strictly increasing")
+        records[typ] = read_exact(stream, read_bigsize(stream))
+        last_type = typ
+    return records
+
+
+def write_tlv_stream(records: Dict[int, bytes]) -> bytes:
+    raw = b""
+    for typ in sorted(records):
+        value = records[typ]
+        raw += write_bigsize(typ) + write_bigsize(len(value)) + value
+    return raw
+"#;
+
+/// The bigsize integer used by the messages, that it is
+/// not part of `lnspec_py`.
+const PYTHON_BIGSIZE: &str = r#"class BigSize:
+    """An integer encoded in 1 to 9 bytes."""
+
+    def __init__(self, val: int):
+        self.val = val
+
+    @staticmethod
+    def decode_with_hex_str(hex_str: str):
+        prefix = int(hex_str[:2], 16)
+        size, minimum = {0xFD: (2, 0xFD), 0xFE: (4, 0x10000), 0xFF: (8, 0x100000000)}.get(
+            prefix, (0, 0)
+        )
+        if size == 0:
+            return BigSize(prefix), hex_str[2:]
+        if len(hex_str) < 2 + 2 * size:
+            raise ValueError("bigsize is too short")
+        val = int(hex_str[2 : 2 + 2 * size], 16)
+        if val < minimum:
+            raise ValueError("bigsize is not minimally encoded")
+        return BigSize(val), hex_str[2 + 2 * size :]
+
+    def encode(self) -> str:
+        if self.val < 0xFD:
+            return f"{self.val:02x}"
+        if self.val <= 0xFFFF:
+            return f"fd{self.val:04x}"
+        if self.val <= 0xFFFFFFFF:
+            return f"fe{self.val:08x}"
+        return f"ff{self.val:016x}"
+"#;
+
 // FIXME: manage the current message in order to support
 // multiple message defined in the same CSV file.
 pub struct PythonCodeGen {
+    options: PythonCodeGenOptions,
     curr_msg: Option<LNMsg>,
     /// Python class definition, is keep separate
     /// because we do not know how many filed we have inside the constructor
@@ -29,6 +182,13 @@ pub struct PythonCodeGen {
     // if the following field need to be inside the constructor
     // or none by default!
     fields: Vec<String>,
+    /// The dataclass fields of the class, used in the standalone mode.
+    annotations: Vec<String>,
+    /// The tlv stream classes, that need to be defined before the messages.
+    tlv_content: String,
+    /// True if a message has a bigsize field, so the module
+    /// needs the `BigSize` class of [`PYTHON_BIGSIZE`].
+    has_bigsize: bool,
     pub file_content: String,
     full_source: String,
     identation: u16,
@@ -38,7 +198,38 @@ pub struct PythonCodeGen {
 }
 
 impl PythonCodeGen {
-    fn add_identation_to_code(&self, content: &String) -> String {
+    pub fn with_options(
+        _symbol_table: &BTreeMap<String, LNMsgType>,
+        options: PythonCodeGenOptions,
+    ) -> Self {
+        // python keywords, and builtins that we do not want to shadow.
+        let keywords = [
+            "len", "type", "id", "bytes", "hash", "and", "as", "assert", "async", "await", "break",
+            "class", "continue", "def", "del", "elif", "else", "except", "finally", "for", "from",
+            "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass",
+            "raise", "return", "try", "while", "with", "yield",
+        ]
+        .iter()
+        .map(|keyword| keyword.to_string())
+        .collect::<HashSet<_>>();
+        PythonCodeGen {
+            options,
+            curr_msg: None,
+            class_definition: String::new(),
+            class_implementation: String::new(),
+            imports: String::new(),
+            file_content: String::new(),
+            tlv_content: String::new(),
+            has_bigsize: false,
+            full_source: String::new(),
+            identation: 0,
+            fields: Vec::new(),
+            annotations: Vec::new(),
+            keywords,
+        }
+    }
+
+    fn add_identation_to_code(&self, content: &str) -> String {
         let clean_content = String::new();
         content
             .trim()
@@ -65,16 +256,17 @@ impl PythonCodeGen {
             self.identation
         );
         self.identation -= 4;
-        self.add_identation_to_code(&"# close scope".to_string())
+        self.add_identation_to_code("# close scope")
     }
 
     fn build_msg_name(&mut self, msg: &LNMsg) -> String {
         self.curr_msg = Some(msg.to_owned());
-        format!("{}Msg", msg.msg_name.to_case(Case::Title))
+        format!("{}Msg", msg.msg_name.to_case(Case::Pascal))
     }
 
     fn end_class_implementation(&mut self) {
         self.fields.clear();
+        self.annotations.clear();
         self.class_definition.clear();
         self.class_implementation.clear();
         self.curr_msg = None
@@ -86,14 +278,13 @@ impl PythonCodeGen {
         } else {
             String::new()
         };
-        let mut signature = "def __init__(self, ".to_string();
+        let mut params = vec!["self".to_owned()];
         for value in &self.fields {
             let no_keyword = self.transmute_if_keyword(value);
-            signature += format!("{no_keyword}, ").as_str();
             body += format!("self.{value} = {no_keyword}\n").as_str();
+            params.push(no_keyword);
         }
-        signature = signature.trim_end().strip_suffix(',').unwrap().to_owned();
-        signature += "):\n";
+        let signature = format!("def __init__({}):\n", params.join(", "));
         let mut contructor = self.add_identation_to_code(&signature);
         self.open_scope();
         contructor += self.add_identation_to_code(&body).as_str();
@@ -103,18 +294,35 @@ impl PythonCodeGen {
         contructor
     }
 
+    /// Generate the dataclass fields, in the same order of the decoding.
+    fn generate_dataclass_fields(&self) -> String {
+        let fields = self.annotations.join("\n");
+        let mut code = self.add_identation_to_code(&fields);
+        self.add_space_between_blocks(&mut code);
+        code
+    }
+
     fn initialize_class(&mut self) -> String {
-        let mut msg = self.curr_msg.to_owned().unwrap();
-        let class_name = self.build_msg_name(&mut msg);
-        let mut params = String::new();
-        for param in &self.fields {
-            params += format!("{param}, ").as_str();
-        }
-        params = params.trim_end().strip_suffix(',').unwrap().to_owned();
-        format!("{class_name}({params})")
+        let msg = self.curr_msg.to_owned().unwrap();
+        let class_name = self.build_msg_name(&msg);
+        // a message without fields is built without params
+        let params: Vec<String> = self
+            .fields
+            .iter()
+            .map(|param| self.transmute_if_keyword(param))
+            .collect();
+        format!("{class_name}({})", params.join(", "))
     }
 
     fn build_msg_type(&mut self) -> String {
+        if self.options.standalone {
+            let class_name = self.build_msg_name(&self.curr_msg.to_owned().unwrap());
+            return format!(
+                "stream = io.BytesIO(raw_msg)\n\
+                 if read_u16(stream) != {class_name}.MSG_TYPE:\n    \
+                 raise WireError(\"unexpected message type\")\n\n"
+            );
+        }
         let name = "msg_type";
         self.fields.push(name.to_owned());
         format!(
@@ -124,12 +332,124 @@ impl PythonCodeGen {
     }
 
     fn write_msg_type(&self) -> String {
+        if self.options.standalone {
+            return "raw_msg += write_u16(self.MSG_TYPE)\n\n".to_owned();
+        }
         let name = "msg_type";
         format!("raw_msg += '{{}}'.format(self.{name}.encode())\n\n")
     }
 
+    /// Build the docstring of the class from the spec documentation.
+    fn build_docstring(&self, msg: &LNMsg) -> String {
+        let Some(spec) = &msg.doc else {
+            return String::new();
+        };
+        let mut doc = String::from("\"\"\"");
+        for paragraph in spec.prose.split("\n\n").filter(|p| !p.is_empty()) {
+            doc += format!("{paragraph}\n\n").as_str();
+        }
+        doc += format!(
+            "Message type {}, defined in {}: {} ({})",
+            msg.msg_typ, spec.bolt, spec.section, spec.link
+        )
+        .as_str();
+        doc += "\"\"\"\n";
+        self.add_identation_to_code(&doc)
+    }
+
+    /// Decode the field with the python decoder provided, and keep
+    /// track of it for the class constructor.
+    ///
+    /// In the standalone mode the field is read from the stream
+    /// with the `reader` expression, and it is declared with the
+    /// python type `ty` inside the dataclass.
+    fn build_field(&mut self, name: &str, decoder: &str, ty: &str, reader: &str) {
+        let var_name = self.transmute_if_keyword(name);
+        let code = if self.options.standalone {
+            self.annotations.push(format!("{var_name}: {ty}"));
+            format!("{var_name} = {reader}")
+        } else {
+            format!("{var_name}, raw_msg = {decoder}(raw_msg)")
+        };
+        self.class_implementation += self.add_identation_to_code(&code).as_str();
+        self.fields.push(name.to_owned());
+    }
+
+    /// Decode a field with a size known at compile time.
+    fn build_fixed_field(&mut self, field: &LNMsData, decoder: &str) {
+        let size = field.fixed_size().unwrap();
+        let reader = format!("read_exact(stream, {size})");
+        self.build_field(field.name(), decoder, "bytes", &reader);
+    }
+
+    fn write_field(&mut self, field: &LNMsData) {
+        let name = field.name();
+        let code = if self.options.standalone {
+            let size = field.fixed_size().unwrap();
+            let name = self.transmute_if_keyword(name);
+            format!("raw_msg += write_fixed(self.{name}, {size})")
+        } else {
+            format!("raw_msg += self.{name}.encode()")
+        };
+        self.class_implementation += self.add_identation_to_code(&code).as_str();
+    }
+
+    fn write_int_field(&mut self, name: &str, writer: &str) {
+        let code = if self.options.standalone {
+            let name = self.transmute_if_keyword(name);
+            format!("raw_msg += {writer}(self.{name})")
+        } else {
+            format!("raw_msg += '{{}}'.format(self.{}.encode())", name)
+        };
+        self.class_implementation += self.add_identation_to_code(&code).as_str();
+    }
+
+    /// Build the dataclass of a tlv stream for the standalone mode, with
+    /// an optional attribute for each record and the `unknown` dict.
+    fn build_tlv_stream_class(&self, tlv: &LNTlvStream) -> String {
+        let name = tlv.stream_name.to_case(Case::Pascal);
+        let mut types = Vec::new();
+        let mut fields = String::new();
+        for record in &tlv.records {
+            let field = self.transmute_if_keyword(&record.type_name);
+            types.push(format!("{}: \"{field}\"", record.type_len));
+            fields += format!("    {field}: Optional[bytes] = None\n").as_str();
+        }
+        let types = types.join(", ");
+        format!(
+            r#"@dataclasses.dataclass
+class {name}:
+    TYPES = {{{types}}}
+
+{fields}    unknown: Dict[int, bytes] = dataclasses.field(default_factory=dict)
+
+    def encode(self) -> bytes:
+        records = dict(self.unknown)
+        for typ, name in self.TYPES.items():
+            value = getattr(self, name)
+            if value is not None:
+                records[typ] = value
+        return write_tlv_stream(records)
+
+    @staticmethod
+    def decode(stream: io.BytesIO) -> "{name}":
+        tlvs = {name}()
+        for typ, value in read_tlv_stream(stream).items():
+            if typ in {name}.TYPES:
+                setattr(tlvs, {name}.TYPES[typ], value)
+            elif typ % 2 == 0:
+                raise WireError(f"unknown even tlv type {{typ}}")
+            else:
+                tlvs.unknown[typ] = value
+        return tlvs
+
+
+"#
+        )
+    }
+
     fn transmute_if_keyword(&self, var_name: &str) -> String {
-        if self.keywords.contains(&var_name.to_string()) {
+        if self.keywords.contains(var_name) {
             return format!("no_keyword_{var_name}");
         }
         var_name.to_owned()
@@ -143,45 +463,59 @@ impl std::fmt::Display for PythonCodeGen {
 }
 
 impl<'g> CodeGen<'g> for PythonCodeGen {
-    fn new(_symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
-        let mut keywords = HashSet::new();
-        keywords.insert("len".to_owned());
-        PythonCodeGen {
-            curr_msg: None,
-            class_definition: String::new(),
-            class_implementation: String::new(),
-            imports: String::new(),
-            file_content: String::new(),
-            full_source: String::new(),
-            identation: 0,
-            fields: Vec::new(),
-            keywords,
-        }
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        PythonCodeGen::with_options(symbol_table, PythonCodeGenOptions::default())
     }
 
     /// callback called before to generate the file.
     /// A good place to pt the import of the files.
     fn pre_generation(&mut self) {
+        if self.options.standalone {
+            self.imports = format!("{PYTHON_RUNTIME}\n\n");
+            return;
+        }
         self.imports = indoc! {"
 # code generated by the lncodegen.rs please do not edit\n
-from lnspec_py.basic_type.int import U16Int, U32Int
-from lnspec_py.basic_type.hex_type import ChannelId
+from lnspec_py.basic_type.int import U16Int, U32Int, U64Int
+from lnspec_py.basic_type.hex_type import ChannelId, ChainHash, ShortChannelId
+from lnspec_py.basic_type.hex_type import Point, Signature, Sha256
 from lnspec_py.basic_type.bitmask import Bitfield
-from lnspec_py.basic_type.tvl_record import TVLRecord
+from lnspec_py.basic_type.tvl_record import TVLRecord\n\n
 "}
         .to_owned();
     }
 
     fn post_generation(&mut self) {
         self.full_source += self.imports.as_str();
+        if self.has_bigsize && !self.options.standalone {
+            self.full_source += PYTHON_BIGSIZE;
+            self.full_source += "\n\n";
+        }
+        self.full_source += self.tlv_content.as_str();
         self.full_source += self.file_content.as_str();
     }
 
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        if self.options.standalone {
+            self.tlv_content += self.build_tlv_stream_class(tlv).as_str();
+        }
+    }
+
     fn build_msg(&mut self, msg: &LNMsg) {
         let class_name = self.build_msg_name(msg);
-        let class_msg = format!("class {}: \n", class_name);
+        if self.options.standalone {
+            self.class_definition += "@dataclasses.dataclass\n";
+        }
+        let class_msg = format!("class {}:\n", class_name);
         self.class_definition += class_msg.as_str();
         self.open_scope();
+        let docstring = self.build_docstring(msg);
+        self.class_definition += docstring.as_str();
+        if self.options.standalone {
+            let msg_type = format!("MSG_TYPE = {}\n\n", msg.msg_typ);
+            self.class_definition += self.add_identation_to_code(&msg_type).as_str();
+            self.class_definition += "\n";
+        }
     }
 
     fn end_msg(&mut self, _msg: &LNMsg) {
@@ -189,21 +523,31 @@ from lnspec_py.basic_type.tvl_record import TVLRecord
         self.class_implementation += format!("{tag}\n").as_str();
         self.file_content += self.class_definition.as_str();
         self.open_scope();
-        let contructor = self.generate_contructor();
+        let contructor = if self.options.standalone {
+            self.generate_dataclass_fields()
+        } else {
+            self.generate_contructor()
+        };
         self.file_content += contructor.as_str();
         self.file_content += self.class_implementation.as_str();
+        self.file_content += "\n\n";
         self.close_scope();
         self.end_class_implementation();
     }
 
     fn build_decode_fun(&mut self) {
-        let mut code = indoc! {"
+        let mut code = if self.options.standalone {
+            let class_name = self.build_msg_name(&self.curr_msg.to_owned().unwrap());
+            format!("@staticmethod\ndef decode(raw_msg: bytes) -> \"{class_name}\":")
+        } else {
+            indoc! {"
             @staticmethod
             def decode(raw_msg: str):"}
-        .to_string();
+            .to_string()
+        };
         let build_msg_ty = self.build_msg_type();
         code += self.add_identation_to_code(&build_msg_ty).as_str();
-        self.class_implementation += self.add_identation_to_code(&code.to_string()).as_str();
+        self.class_implementation += self.add_identation_to_code(&code).as_str();
         self.open_scope();
     }
 
@@ -212,142 +556,476 @@ from lnspec_py.basic_type.tvl_record import TVLRecord
         let code = format!("return {init_class}");
         self.class_implementation += self.add_identation_to_code(&code).as_str();
         let tag = self.close_scope();
-        self.file_content += tag.as_str();
-        self.file_content += "\n\n";
+        self.class_implementation += tag.as_str();
+        self.class_implementation += "\n\n";
     }
 
     fn build_encode_fn(&mut self) {
-        let mut code = indoc! {"
+        let mut code = if self.options.standalone {
+            "def encode(self) -> bytes:\n    raw_msg = b''".to_owned()
+        } else {
+            indoc! {"
             def encode(self) -> str:
                 raw_msg = ''"}
-        .to_string();
+            .to_string()
+        };
         let write_msg_ty = self.write_msg_type();
         code += self.add_identation_to_code(&write_msg_ty).as_str();
-        self.class_implementation += self.add_identation_to_code(&code.to_string()).as_str();
+        self.class_implementation += self.add_identation_to_code(&code).as_str();
         self.open_scope();
     }
 
     fn end_encode_fn(&mut self) {
         let code = "return raw_msg";
-        self.class_implementation += self.add_identation_to_code(&code.to_string()).as_str();
+        self.class_implementation += self.add_identation_to_code(code).as_str();
         let tag = self.close_scope();
         self.class_implementation += tag.as_str();
         self.class_implementation += "\n\n";
     }
 
-    fn build_u16(&mut self, field: &frontend_csv::parser::ast::LNMsData) {
+    fn build_u16(&mut self, field: &LNMsData) {
         if let LNMsData::Uint16(name) = field {
-            let name = self.transmute_if_keyword(name);
-            let code = format!("{}, raw_msg = U16Int.decode_with_hex_str(raw_msg)", name);
-            self.class_implementation += self.add_identation_to_code(&code).as_str();
-            self.fields.push(name);
+            self.build_field(
+                name,
+                "U16Int.decode_with_hex_str",
+                "int",
+                "read_u16(stream)",
+            );
         }
     }
 
-    fn write_u16(&mut self, field: &frontend_csv::parser::ast::LNMsData) {
+    fn write_u16(&mut self, field: &LNMsData) {
         if let LNMsData::Uint16(name) = field {
-            let name = self.transmute_if_keyword(name);
-            let code = format!("raw_msg += '{{}}'.format(self.{}.encode())", name);
-            self.class_implementation += self.add_identation_to_code(&code).as_str();
+            self.write_int_field(name, "write_u16");
         }
     }
 
-    fn build_u32(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_field(
+                name,
+                "U32Int.decode_with_hex_str",
+                "int",
+                "read_u32(stream)",
+            );
+        }
     }
 
-    fn write_u32(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_int_field(name, "write_u32");
+        }
     }
 
-    fn build_u64(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_field(
+                name,
+                "U64Int.decode_with_hex_str",
+                "int",
+                "read_u64(stream)",
+            );
+        }
     }
 
-    fn write_u64(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_int_field(name, "write_u64");
+        }
     }
 
-    fn write_bitfiled(&mut self, field: &frontend_csv::parser::ast::LNMsData) {
-        if let LNMsData::BitfieldStream(name, _) = field {
-            let name = self.transmute_if_keyword(name);
-            let code = format!(
-                "if len(self.{}.bitfield) > 0:\n \
-                 \t raw_msg += Bitfield.encode(self.{}.bitfield)",
-                name, name
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.has_bigsize = true;
+            self.build_field(
+                name,
+                "BigSize.decode_with_hex_str",
+                "int",
+                "read_bigsize(stream)",
             );
-            self.class_implementation += self.add_identation_to_code(&code).as_str();
         }
     }
 
-    fn build_bitfield(&mut self, field: &frontend_csv::parser::ast::LNMsData) {
-        if let LNMsData::BitfieldStream(name, _) = field {
-            let name = self.transmute_if_keyword(name);
-            let code = format!("{name}, raw_msg = Bitfield.decode_with_len(raw_msg)");
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.write_int_field(name, "write_bigsize");
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, _) = field {
+            if self.options.standalone {
+                let name = self.transmute_if_keyword(name);
+                let code = if let Ok(size) = size.parse::<u64>() {
+                    format!("raw_msg += write_fixed(self.{name}, {size})")
+                } else if size == "..."
+                    || self
+                        .curr_msg
+                        .as_ref()
+                        .is_some_and(|msg| msg.is_length_field(size))
+                {
+                    format!("raw_msg += self.{name}")
+                } else {
+                    format!("raw_msg += write_u16(len(self.{name})) + self.{name}")
+                };
+                self.class_implementation += self.add_identation_to_code(&code).as_str();
+                return;
+            }
+            // the length is always read back by the decoder, also when it is zero.
+            let code = format!("raw_msg += Bitfield.encode(self.{name}.bitfield)");
             self.class_implementation += self.add_identation_to_code(&code).as_str();
-            self.fields.push(name);
         }
     }
 
-    fn write_point(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, _) = field {
+            let reader = if size.parse::<u64>().is_ok() {
+                format!("read_exact(stream, {size})")
+            } else if size == "..." {
+                "stream.read()".to_owned()
+            } else if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let size = self.transmute_if_keyword(size);
+                format!("read_exact(stream, {size})")
+            } else {
+                "read_exact(stream, read_u16(stream))".to_owned()
+            };
+            self.build_field(name, "Bitfield.decode_with_len", "bytes", &reader);
+        }
     }
 
-    fn build_point(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn write_point(&mut self, field: &LNMsData) {
+        if let LNMsData::Point(_) = field {
+            self.write_field(field);
+        }
     }
 
-    fn build_chain_hash(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn build_point(&mut self, field: &LNMsData) {
+        if let LNMsData::Point(_) = field {
+            self.build_fixed_field(field, "Point.decode_from_hex");
+        }
     }
 
-    fn write_chain_hash(&mut self, _filed: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        if let LNMsData::ChainHash(..) = field {
+            self.build_fixed_field(field, "ChainHash.decode_from_hex");
+        }
     }
 
-    fn build_channel_id(&mut self, filed: &frontend_csv::parser::ast::LNMsData) {
-        if let LNMsData::ChannelId(name) = filed {
-            let name = self.transmute_if_keyword(name);
-            let code = format!("{name}, raw_msg = ChannelId.decode_from_hex(raw_msg)");
-            self.class_implementation += self.add_identation_to_code(&code).as_str();
-            self.fields.push(name);
+    fn write_chain_hash(&mut self, filed: &LNMsData) {
+        if let LNMsData::ChainHash(..) = filed {
+            self.write_field(filed);
         }
     }
 
-    fn write_channel_id(&mut self, field: &frontend_csv::parser::ast::LNMsData) {
-        if let LNMsData::ChannelId(name) = field {
-            let name = self.transmute_if_keyword(name);
-            let code = format!("raw_msg += self.{name}.encode()");
-            self.class_implementation += self.add_identation_to_code(&code).as_str();
+    fn build_channel_id(&mut self, filed: &LNMsData) {
+        if let LNMsData::ChannelId(_) = filed {
+            self.build_fixed_field(filed, "ChannelId.decode_from_hex");
         }
     }
-    fn build_short_channel_id(&mut self, _filed: &LNMsData) {
-        todo!()
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        if let LNMsData::ChannelId(_) = field {
+            self.write_field(field);
+        }
+    }
+
+    fn build_short_channel_id(&mut self, filed: &LNMsData) {
+        if let LNMsData::ShortChannelId(_) = filed {
+            self.build_fixed_field(filed, "ShortChannelId.decode_from_hex");
+        }
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        if let LNMsData::ShortChannelId(_) = field {
+            self.write_field(field);
+        }
+    }
+
+    fn build_signature(&mut self, filed: &LNMsData) {
+        if let LNMsData::Signature(_) = filed {
+            self.build_fixed_field(filed, "Signature.decode_from_hex");
+        }
     }
 
-    fn write_short_channel_id(&mut self, _: &LNMsData) {
-        todo!()
+    fn write_signature(&mut self, field: &LNMsData) {
+        if let LNMsData::Signature(_) = field {
+            self.write_field(field);
+        }
     }
 
-    fn build_signature(&mut self, _filed: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn build_sha256(&mut self, field: &LNMsData) {
+        if let LNMsData::Sha256(_) = field {
+            self.build_fixed_field(field, "Sha256.decode_from_hex");
+        }
     }
 
-    fn write_signature(&mut self, _field: &frontend_csv::parser::ast::LNMsData) {
-        todo!()
+    fn write_sha256(&mut self, field: &LNMsData) {
+        if let LNMsData::Sha256(_) = field {
+            self.write_field(field);
+        }
     }
 
     // TODO: make a double check if the API are safe
-    fn build_tlv_stream(&mut self, field: &frontend_csv::parser::ast::LNTlvRecord) {
-        let mut source = format!("{} = TVLRecord(raw_msg)\n", field.stream_name);
-        source += format!("{}.decode()", field.stream_name).as_str();
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = self.transmute_if_keyword(&field.stream_name);
+        if self.options.standalone {
+            let class_name = field.stream_name.to_case(Case::Pascal);
+            self.annotations.push(format!(
+                "{name}: {class_name} = dataclasses.field(default_factory={class_name})"
+            ));
+            let source = format!("{name} = {class_name}.decode(stream)");
+            self.class_implementation += self.add_identation_to_code(&source).as_str();
+            self.fields.push(field.stream_name.to_owned());
+            return;
+        }
+        let mut source = format!("{name} = TVLRecord(raw_msg)\n");
+        source += format!("{name}.decode()").as_str();
         self.class_implementation += self.add_identation_to_code(&source).as_str();
         self.fields.push(field.stream_name.to_owned());
     }
 
     // TODO: make a double check if the API are safe
-    fn write_tlv_stream(&mut self, field: &frontend_csv::parser::ast::LNTlvRecord) {
-        let source = format!("raw_msg += self.{}.encode()", field.stream_name);
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = if self.options.standalone {
+            self.transmute_if_keyword(&field.stream_name)
+        } else {
+            field.stream_name.to_owned()
+        };
+        let source = format!("raw_msg += self.{name}.encode()");
         self.class_implementation += self.add_identation_to_code(&source).as_str();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::codegen::CodeGen;
+    use crate::python::{PythonCodeGen, PythonCodeGenOptions};
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+
+    #[test]
+    fn python_channel_update_msg() {
+        init();
+        let contents = "msgtype,channel_update,258\n\
+                        msgdata,channel_update,signature,signature,\n\
+                        msgdata,channel_update,chain_hash,chain_hash,\n\
+                        msgdata,channel_update,short_channel_id,short_channel_id,\n\
+                        msgdata,channel_update,timestamp,u32,\n\
+                        msgdata,channel_update,htlc_maximum_msat,u64,\n\
+                        msgdata,channel_update,len,u16,\n\
+                        msgdata,channel_update,node_id,point,\n";
+        let symbol_table = parse(contents);
+        let mut backend = PythonCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("class ChannelUpdateMsg:"), "{code}");
+        assert!(
+            code.contains(
+                "        short_channel_id, raw_msg = ShortChannelId.decode_from_hex(raw_msg)"
+            ),
+            "{code}"
+        );
+        assert!(
+            code.contains(
+                "        htlc_maximum_msat, raw_msg = U64Int.decode_with_hex_str(raw_msg)"
+            ),
+            "{code}"
+        );
+        assert!(code.contains("        self.len = no_keyword_len"), "{code}");
+        assert!(
+            code.contains("        raw_msg += self.node_id.encode()"),
+            "{code}"
+        );
+        assert!(!code.contains("todo"), "{code}");
+
+        // the module needs `lnspec_py` to run, so we check only the syntax.
+        let dir = scratch_dir("python_channel_update_msg");
+        fs::write(dir.join("generated.py"), &code).unwrap();
+        run_cmd(
+            Command::new("python3")
+                .args(["-m", "py_compile", "generated.py"])
+                .current_dir(&dir),
+        );
+    }
+
+    #[test]
+    fn python_standalone_msg() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+        let symbol_table = parse(contents);
+        let options = PythonCodeGenOptions { standalone: true };
+        let mut backend = PythonCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(!code.contains("lnspec_py"), "{code}");
+        assert!(
+            code.contains("def read_bigsize(stream: io.BytesIO) -> int:"),
+            "{code}"
+        );
+        assert!(
+            code.contains("@dataclasses.dataclass\nclass InitMsg:"),
+            "{code}"
+        );
+        assert!(code.contains("    globalfeatures: bytes\n"), "{code}");
+        assert!(
+            code.contains("    init_tlvs: InitTlvs = dataclasses.field(default_factory=InitTlvs)"),
+            "{code}"
+        );
+        assert!(
+            code.contains("        globalfeatures = read_exact(stream, read_u16(stream))"),
+            "{code}"
+        );
+        // the tlv stream class need to be defined before the message
+        assert!(
+            code.find("class InitTlvs:") < code.find("class InitMsg:"),
+            "{code}"
+        );
+
+        let dir = scratch_dir("python_standalone_msg");
+        fs::write(dir.join("generated.py"), &code).unwrap();
+        let script = indoc! {r#"
+            import generated
+
+            tlvs = generated.InitTlvs(networks=b"\x6f" * 32, unknown={3: b"\x7f\x01"})
+            msg = generated.InitMsg(b"\x02\x0a", tlvs)
+            wire = msg.encode()
+            print(wire.hex())
+            assert generated.InitMsg.decode(wire) == msg
+            try:
+                generated.InitMsg.decode(wire + bytes([4, 0]))
+                raise SystemExit("unknown even record accepted")
+            except generated.WireError:
+                pass
+        "#};
+        let stdout = run_cmd(
+            Command::new("python3")
+                .args(["-c", script])
+                .current_dir(&dir),
+        );
+        let expected = format!("00100002020a0120{}03027f01\n", "6f".repeat(32));
+        assert_eq!(stdout, expected);
+    }
+
+    #[test]
+    fn python_msg_without_fields() {
+        init();
+        let contents = "msgtype,temporary_node_failure,8194\n";
+        let symbol_table = parse(contents);
+        let mut backend = PythonCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("TemporaryNodeFailureMsg(msg_type)"), "{code}");
+
+        let options = PythonCodeGenOptions { standalone: true };
+        let mut backend = PythonCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("TemporaryNodeFailureMsg()"), "{code}");
+
+        let dir = scratch_dir("python_msg_without_fields");
+        fs::write(dir.join("generated.py"), &code).unwrap();
+        let script = indoc! {r#"
+            import generated
+
+            wire = generated.TemporaryNodeFailureMsg().encode()
+            print(wire.hex())
+            assert generated.TemporaryNodeFailureMsg.decode(wire) == generated.TemporaryNodeFailureMsg()
+        "#};
+        let stdout = run_cmd(
+            Command::new("python3")
+                .args(["-c", script])
+                .current_dir(&dir),
+        );
+        assert_eq!(stdout, "2002\n");
+    }
+
+    #[test]
+    fn python_empty_bitfield() {
+        init();
+        let contents = "msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16,\n\
+                        msgdata,ping,byteslen,u16,\n\
+                        msgdata,ping,ignored,byte,byteslen\n";
+        let symbol_table = parse(contents);
+        let mut backend = PythonCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("        raw_msg += Bitfield.encode(self.ignored.bitfield)\n"),
+            "{code}"
+        );
+
+        // a minimal `lnspec_py` with the hex encoding of the library.
+        let dir = scratch_dir("python_empty_bitfield");
+        let lib = dir.join("lnspec_py").join("basic_type");
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(dir.join("lnspec_py").join("__init__.py"), "").unwrap();
+        fs::write(lib.join("__init__.py"), "").unwrap();
+        let int = indoc! {r#"
+            class U16Int:
+                def __init__(self, val):
+                    self.val = val
+
+                def encode(self):
+                    return "{:04x}".format(self.val)
+
+                @staticmethod
+                def decode_with_hex_str(raw_msg):
+                    return U16Int(int(raw_msg[:4], 16)), raw_msg[4:]
+
+            U32Int = U64Int = U16Int
+        "#};
+        fs::write(lib.join("int.py"), int).unwrap();
+        let bitmask = indoc! {r#"
+            class Bitfield:
+                def __init__(self, bitfield):
+                    self.bitfield = bitfield
+
+                @staticmethod
+                def encode(bitfield):
+                    return "{:04x}".format(len(bitfield)) + bitfield.hex()
+
+                @staticmethod
+                def decode_with_len(raw_msg):
+                    size = int(raw_msg[:4], 16) * 2
+                    return Bitfield(bytes.fromhex(raw_msg[4:4 + size])), raw_msg[4 + size:]
+        "#};
+        fs::write(lib.join("bitmask.py"), bitmask).unwrap();
+        let hex_type =
+            "ChannelId = ChainHash = ShortChannelId = Point = Signature = Sha256 = None\n";
+        fs::write(lib.join("hex_type.py"), hex_type).unwrap();
+        fs::write(lib.join("tvl_record.py"), "TVLRecord = None\n").unwrap();
+        fs::write(dir.join("generated.py"), &code).unwrap();
+        let script = indoc! {r#"
+            import generated
+            from lnspec_py.basic_type.int import U16Int
+            from lnspec_py.basic_type.bitmask import Bitfield
+
+            msg = generated.PingMsg(U16Int(18), U16Int(4), Bitfield(b""))
+            wire = msg.encode()
+            print(wire)
+            decoded = generated.PingMsg.decode(wire)
+            assert decoded.ignored.bitfield == b""
+            assert decoded.encode() == wire
+        "#};
+        let stdout = run_cmd(
+            Command::new("python3")
+                .args(["-c", script])
+                .current_dir(&dir),
+        );
+        assert_eq!(stdout, "001200040000\n");
+    }
+}
@@ -4,15 +4,42 @@ use std::collections::BTreeMap;
 use std::fmt::Display;
 
 use convert_case::{Case, Casing};
+use indoc::indoc;
 
-use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvRecord};
+use csvlang::parser::ast::{
+    LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvEntry, LNTlvRecord, LNTlvStream,
+};
 
 use super::codegen::CodeGen;
 
+/// Options that change the shape of the generated rust code.
+#[derive(Clone, Debug, Default)]
+pub struct RustCodeGenOptions {
+    /// Emit also a `borrowed` module with `Foo<'a>` views over
+    /// the input buffer, where the bytes fields are slices.
+    pub zero_copy: bool,
+    /// Derive `Serialize`/`Deserialize` for the messages, the
+    /// generated code requires the `serde` crate with the `derive` feature.
+    pub serde: bool,
+    /// Emit a `FooBuilder` for each message, that compute the
+    /// length fields and validate the fixed size fields.
+    pub builder: bool,
+}
+
 pub struct RustCodeGen {
-    #[allow(dead_code)]
     symbol_table: BTreeMap<String, LNMsgType>,
+    options: RustCodeGenOptions,
     file_content: String,
+    /// Content of the `borrowed` module, filled only
+    /// when the zero copy option is enabled.
+    borrowed_content: String,
+    /// True if at least one tlv stream was generated,
+    /// so the tlv helpers are needed.
+    has_tlv: bool,
+    /// True if at least one builder was generated.
+    has_builder: bool,
+    /// True if at least one field uses the serde helpers.
+    has_serde_with: bool,
     identation: u16,
 }
 
@@ -28,7 +55,524 @@ macro_rules! fmt_struct_filed_with_attr {
     };
 }
 
+/// Helpers used to encode and decode the tlv streams.
+const TLV_HELPERS: &str = indoc! {r#"
+    #[allow(dead_code)]
+    mod tlv_helpers {
+        use std::io::{Error, ErrorKind, Write};
+
+        pub fn invalid(msg: &str) -> Error {
+            Error::new(ErrorKind::InvalidData, msg.to_owned())
+        }
+
+        pub fn read_bytes<'a>(buf: &mut &'a [u8], len: usize) -> std::io::Result<&'a [u8]> {
+            if buf.len() < len {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "tlv value too short"));
+            }
+            let (head, tail) = buf.split_at(len);
+            *buf = tail;
+            Ok(head)
+        }
+
+        pub fn read_array<const N: usize>(buf: &mut &[u8]) -> std::io::Result<[u8; N]> {
+            Ok(read_bytes(buf, N)?.try_into().unwrap())
+        }
+
+        pub fn read_bigsize(buf: &mut &[u8]) -> std::io::Result<u64> {
+            let (value, min) = match read_array::<1>(buf)?[0] {
+                0xFF => (u64::from_be_bytes(read_array(buf)?), 0x100000000),
+                0xFE => (u32::from_be_bytes(read_array(buf)?) as u64, 0x10000),
+                0xFD => (u16::from_be_bytes(read_array(buf)?) as u64, 0xFD),
+                value => return Ok(value as u64),
+            };
+            if value < min {
+                return Err(invalid("bigsize not minimally encoded"));
+            }
+            Ok(value)
+        }
+
+        pub fn write_bigsize(buf: &mut Vec<u8>, value: u64) {
+            match value {
+                0..=0xFC => buf.push(value as u8),
+                0xFD..=0xFFFF => {
+                    buf.push(0xFD);
+                    buf.extend_from_slice(&(value as u16).to_be_bytes());
+                }
+                0x10000..=0xFFFFFFFF => {
+                    buf.push(0xFE);
+                    buf.extend_from_slice(&(value as u32).to_be_bytes());
+                }
+                _ => {
+                    buf.push(0xFF);
+                    buf.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        }
+
+        /// Read a truncated integer, that it takes the rest of the record.
+        pub fn read_truncated(buf: &mut &[u8], max: usize) -> std::io::Result<u64> {
+            let bytes = std::mem::take(buf);
+            if bytes.len() > max {
+                return Err(invalid("truncated integer too long"));
+            }
+            if bytes.first() == Some(&0) {
+                return Err(invalid("truncated integer not minimally encoded"));
+            }
+            Ok(bytes.iter().fold(0, |value, byte| (value << 8) | *byte as u64))
+        }
+
+        pub fn write_truncated(buf: &mut Vec<u8>, value: u64) {
+            let bytes = value.to_be_bytes();
+            let zeros = (value.leading_zeros() / 8) as usize;
+            buf.extend_from_slice(&bytes[zeros..]);
+        }
+
+        pub fn write_record<W: Write>(writer: &mut W, ty: u64, value: &[u8]) -> std::io::Result<()> {
+            let mut buf = Vec::new();
+            write_bigsize(&mut buf, ty);
+            write_bigsize(&mut buf, value.len() as u64);
+            buf.extend_from_slice(value);
+            writer.write_all(&buf)
+        }
+    }
+    "#};
+
+/// Helpers used by the generated builders to report
+/// the errors and validate the fields.
+const BUILDER_HELPERS: &str = indoc! {r#"
+    #[allow(dead_code)]
+    mod builder_helpers {
+        use std::io::{Error, ErrorKind};
+
+        use crate::core::ToWire;
+
+        pub fn missing_field(name: &str) -> Error {
+            Error::new(ErrorKind::InvalidInput, format!("missing field `{name}`"))
+        }
+
+        /// Size of the bytes inside a bitfield, without
+        /// the u16 length that it is used as prefix.
+        pub fn payload_len<T: ToWire>(value: &T) -> std::io::Result<usize> {
+            let mut buf = Vec::new();
+            value.to_wire(&mut buf)?;
+            Ok(buf.len().saturating_sub(2))
+        }
+
+        pub fn check_fixed_size<T: ToWire>(name: &str, value: &T, size: usize) -> std::io::Result<()> {
+            let len = payload_len(value)?;
+            if len != size {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("field `{name}` must be {size} bytes, found {len}"),
+                ));
+            }
+            Ok(())
+        }
+    }
+    "#};
+
+/// Serde representation of the types that are not
+/// plain integers, the bytes are hex strings of the wire
+/// encoding and the short channel id is `<block>x<tx>x<output>`.
+///
+/// Every representation has also the `option`, `vec` and `option_vec`
+/// variants, used by the records of the tlv streams.
+const SERDE_HELPERS: &str = indoc! {r#"
+    #[allow(dead_code)]
+    mod serde_helpers {
+        use std::marker::PhantomData;
+
+        use serde::de::Error;
+        use serde::ser::Error as _;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use crate::core::{FromWire, ToWire};
+
+        /// A value that is serialized as a string.
+        pub trait Repr<T> {
+            fn encode(value: &T) -> Result<String, String>;
+
+            fn decode(value: &str) -> Result<T, String>;
+        }
+
+        fn to_hex(bytes: &[u8]) -> String {
+            bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+        }
+
+        fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+            if hex.len() % 2 != 0 {
+                return Err("hex string with odd length".to_owned());
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|err| err.to_string()))
+                .collect()
+        }
+
+        fn to_wire<T: ToWire>(value: &T) -> Result<Vec<u8>, String> {
+            let mut buf = Vec::new();
+            value.to_wire(&mut buf).map_err(|err| err.to_string())?;
+            Ok(buf)
+        }
+
+        /// The hex string of the wire encoding.
+        pub struct WireHex;
+
+        impl<T: ToWire + FromWire> Repr<T> for WireHex {
+            fn encode(value: &T) -> Result<String, String> {
+                Ok(to_hex(&to_wire(value)?))
+            }
+
+            fn decode(value: &str) -> Result<T, String> {
+                T::from_wire(&mut from_hex(value)?.as_slice()).map_err(|err| err.to_string())
+            }
+        }
+
+        /// The bitfield is encoded with the u16 length as prefix,
+        /// that it is not part of the hex string.
+        pub struct BitflagHex;
+
+        impl<T: ToWire + FromWire> Repr<T> for BitflagHex {
+            fn encode(value: &T) -> Result<String, String> {
+                Ok(to_hex(&to_wire(value)?[2..]))
+            }
+
+            fn decode(value: &str) -> Result<T, String> {
+                let bytes = from_hex(value)?;
+                let len = u16::try_from(bytes.len()).map_err(|err| err.to_string())?;
+                let mut buf = len.to_be_bytes().to_vec();
+                buf.extend(bytes);
+                T::from_wire(&mut buf.as_slice()).map_err(|err| err.to_string())
+            }
+        }
+
+        /// The hex string of a byte array.
+        pub struct BytesHex;
+
+        impl<T: AsRef<[u8]> + TryFrom<Vec<u8>>> Repr<T> for BytesHex {
+            fn encode(value: &T) -> Result<String, String> {
+                Ok(to_hex(value.as_ref()))
+            }
+
+            fn decode(value: &str) -> Result<T, String> {
+                T::try_from(from_hex(value)?).map_err(|_| "wrong number of bytes".to_owned())
+            }
+        }
+
+        /// The short channel id as `<block>x<tx>x<output>`.
+        pub struct Scid;
+
+        impl<T: ToWire + FromWire> Repr<T> for Scid {
+            fn encode(value: &T) -> Result<String, String> {
+                let buf: [u8; 8] = to_wire(value)?
+                    .try_into()
+                    .map_err(|_| "short channel id is not 8 bytes".to_owned())?;
+                let scid = u64::from_be_bytes(buf);
+                Ok(format!("{}x{}x{}", scid >> 40, (scid >> 16) & 0xFFFFFF, scid & 0xFFFF))
+            }
+
+            fn decode(value: &str) -> Result<T, String> {
+                let parts = value
+                    .split('x')
+                    .map(|part| part.parse::<u64>().map_err(|err| err.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let [block, tx, output] = parts[..] else {
+                    return Err("short channel id must be <block>x<tx>x<output>".to_owned());
+                };
+                if block > 0xFFFFFF || tx > 0xFFFFFF || output > 0xFFFF {
+                    return Err("short channel id out of range".to_owned());
+                }
+                let scid = (block << 40) | (tx << 16) | output;
+                T::from_wire(&mut scid.to_be_bytes().as_slice()).map_err(|err| err.to_string())
+            }
+        }
+
+        struct Ser<'a, R, T>(&'a T, PhantomData<R>);
+
+        impl<R: Repr<T>, T> Serialize for Ser<'_, R, T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&R::encode(self.0).map_err(S::Error::custom)?)
+            }
+        }
+
+        struct SerSeq<'a, R, T>(&'a [T], PhantomData<R>);
+
+        impl<R: Repr<T>, T> Serialize for SerSeq<'_, R, T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.iter().map(|value| Ser::<R, T>(value, PhantomData)))
+            }
+        }
+
+        struct De<R, T>(T, PhantomData<R>);
+
+        impl<'de, R: Repr<T>, T> Deserialize<'de> for De<R, T> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                let value = R::decode(&value).map_err(D::Error::custom)?;
+                Ok(De(value, PhantomData))
+            }
+        }
+
+        pub fn serialize<R: Repr<T>, T, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+            Ser::<R, T>(value, PhantomData).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, R: Repr<T>, T, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+            Ok(De::<R, T>::deserialize(deserializer)?.0)
+        }
+
+        pub fn serialize_option<R: Repr<T>, T, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+            value.as_ref().map(|value| Ser::<R, T>(value, PhantomData)).serialize(serializer)
+        }
+
+        pub fn deserialize_option<'de, R: Repr<T>, T, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+            Ok(Option::<De<R, T>>::deserialize(deserializer)?.map(|value| value.0))
+        }
+
+        pub fn serialize_vec<R: Repr<T>, T, S: Serializer>(value: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+            SerSeq::<R, T>(value, PhantomData).serialize(serializer)
+        }
+
+        pub fn deserialize_vec<'de, R: Repr<T>, T, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+            let values = Vec::<De<R, T>>::deserialize(deserializer)?;
+            Ok(values.into_iter().map(|value| value.0).collect())
+        }
+
+        pub fn serialize_option_vec<R: Repr<T>, T, S: Serializer>(value: &Option<Vec<T>>, serializer: S) -> Result<S::Ok, S::Error> {
+            value.as_ref().map(|value| SerSeq::<R, T>(value, PhantomData)).serialize(serializer)
+        }
+
+        pub fn deserialize_option_vec<'de, R: Repr<T>, T, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<T>>, D::Error> {
+            let values = Option::<Vec<De<R, T>>>::deserialize(deserializer)?;
+            Ok(values.map(|values| values.into_iter().map(|value| value.0).collect()))
+        }
+
+        /// Build the module used with `#[serde(with = ...)]` for
+        /// a representation, with the `option`, `vec` and `option_vec`
+        /// modules inside it.
+        macro_rules! repr_module {
+            ($name:ident, $repr:ty) => {
+                pub mod $name {
+                    use super::*;
+
+                    pub fn serialize<T, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+                    where
+                        $repr: Repr<T>,
+                    {
+                        super::serialize::<$repr, T, S>(value, serializer)
+                    }
+
+                    pub fn deserialize<'de, T, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error>
+                    where
+                        $repr: Repr<T>,
+                    {
+                        super::deserialize::<$repr, T, D>(deserializer)
+                    }
+
+                    pub mod option {
+                        use super::super::*;
+
+                        pub fn serialize<T, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            $repr: Repr<T>,
+                        {
+                            serialize_option::<$repr, T, S>(value, serializer)
+                        }
+
+                        pub fn deserialize<'de, T, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error>
+                        where
+                            $repr: Repr<T>,
+                        {
+                            deserialize_option::<$repr, T, D>(deserializer)
+                        }
+                    }
+
+                    pub mod vec {
+                        use super::super::*;
+
+                        pub fn serialize<T, S: Serializer>(value: &[T], serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            $repr: Repr<T>,
+                        {
+                            serialize_vec::<$repr, T, S>(value, serializer)
+                        }
+
+                        pub fn deserialize<'de, T, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error>
+                        where
+                            $repr: Repr<T>,
+                        {
+                            deserialize_vec::<$repr, T, D>(deserializer)
+                        }
+                    }
+
+                    pub mod option_vec {
+                        use super::super::*;
+
+                        pub fn serialize<T, S: Serializer>(value: &Option<Vec<T>>, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            $repr: Repr<T>,
+                        {
+                            serialize_option_vec::<$repr, T, S>(value, serializer)
+                        }
+
+                        pub fn deserialize<'de, T, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+                        where
+                            $repr: Repr<T>,
+                        {
+                            deserialize_option_vec::<$repr, T, D>(deserializer)
+                        }
+                    }
+                }
+            };
+        }
+
+        repr_module!(wire_hex, WireHex);
+        repr_module!(bitflag_hex, BitflagHex);
+        repr_module!(bytes_hex, BytesHex);
+        repr_module!(short_channel_id, Scid);
+    }
+    "#};
+
+/// Escape the name if it is a rust keyword.
+fn rust_ident(name: &str) -> String {
+    const KEYWORDS: [&str; 38] = [
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while", "yield",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Return the rust type of a single element of the entry.
+fn tlv_elem_type(ty: &str) -> String {
+    let ty = match ty {
+        "byte" => "u8",
+        "u16" => "u16",
+        "u32" | "tu32" => "u32",
+        "u64" | "tu64" | "bigsize" => "u64",
+        "s64" => "i64",
+        "chain_hash" => "ChainHash",
+        "channel_id" => "ChannelId",
+        "sha256" => "Sha256",
+        "signature" => "Signature",
+        "point" => "Point",
+        "short_channel_id" => "ShortChannelId",
+        // subtype defined in the spec
+        other => return other.to_case(Case::Pascal),
+    };
+    ty.to_owned()
+}
+
+/// Return the rust type of the tlv entry, taking in account the count.
+fn tlv_entry_type(entry: &LNTlvEntry) -> String {
+    let ty = tlv_elem_type(&entry.entry_ty);
+    match entry.count.as_deref() {
+        None => ty,
+        Some(count) => match count.parse::<u64>() {
+            Ok(size) if entry.entry_ty == "byte" => format!("[u8; {size}]"),
+            _ => format!("Vec<{ty}>"),
+        },
+    }
+}
+
+/// Return the serde helpers module used for the tlv entry, the same
+/// representation of the message fields with the same type.
+fn tlv_entry_serde_with(entry: &LNTlvEntry) -> Option<String> {
+    let module = match entry.entry_ty.as_str() {
+        // an array of bytes is a single hex string
+        "byte" => return entry.count.as_ref().map(|_| "bytes_hex".to_owned()),
+        "chain_hash" | "channel_id" | "sha256" | "signature" | "point" => "wire_hex",
+        "short_channel_id" => "short_channel_id",
+        _ => return None,
+    };
+    match entry.count {
+        Some(_) => Some(format!("{module}::vec")),
+        None => Some(module.to_owned()),
+    }
+}
+
+/// Return the code that write a single element referenced by `value` inside `buf`.
+fn tlv_elem_encode(ty: &str, value: &str) -> String {
+    match ty {
+        "byte" => format!("buf.push(*{value});"),
+        "u16" | "u32" | "u64" | "s64" => format!("buf.extend_from_slice(&{value}.to_be_bytes());"),
+        "tu32" | "tu64" => format!("tlv_helpers::write_truncated(&mut buf, *{value} as u64);"),
+        "bigsize" => format!("tlv_helpers::write_bigsize(&mut buf, *{value});"),
+        _ => format!("{value}.to_wire(&mut buf)?;"),
+    }
+}
+
+/// Return the code that write the entry referenced by `value` inside `buf`.
+fn tlv_entry_encode(entry: &LNTlvEntry, value: &str) -> String {
+    match entry.count.as_deref() {
+        None => format!("            {}\n", tlv_elem_encode(&entry.entry_ty, value)),
+        Some(_) if entry.entry_ty == "byte" => {
+            format!("            buf.extend_from_slice(&{value}[..]);\n")
+        }
+        Some(_) => format!(
+            "            for item in {value} {{\n                {}\n            }}\n",
+            tlv_elem_encode(&entry.entry_ty, "item")
+        ),
+    }
+}
+
+/// Return the expression that read a single element from `value`.
+fn tlv_elem_decode(ty: &str) -> String {
+    match ty {
+        "byte" => "tlv_helpers::read_array::<1>(&mut value)?[0]".to_owned(),
+        "u16" | "u32" | "u64" | "s64" => format!(
+            "{}::from_be_bytes(tlv_helpers::read_array(&mut value)?)",
+            tlv_elem_type(ty)
+        ),
+        "tu32" => "tlv_helpers::read_truncated(&mut value, 4)? as u32".to_owned(),
+        "tu64" => "tlv_helpers::read_truncated(&mut value, 8)?".to_owned(),
+        "bigsize" => "tlv_helpers::read_bigsize(&mut value)?".to_owned(),
+        _ => format!("{}::from_wire(&mut value)?", tlv_elem_type(ty)),
+    }
+}
+
+/// Return the expression that read the entry from `value`.
+fn tlv_entry_decode(entry: &LNTlvEntry) -> String {
+    let elem = tlv_elem_decode(&entry.entry_ty);
+    match (entry.count.as_deref(), entry.entry_ty.as_str()) {
+        (None, _) => elem,
+        (Some("..."), "byte") => "std::mem::take(&mut value).to_vec()".to_owned(),
+        (Some("..."), _) => format!(
+            "{{\n                        let mut items = Vec::new();\n                        while !value.is_empty() {{\n                            items.push({elem});\n                        }}\n                        items\n                    }}"
+        ),
+        (Some(count), "byte") => match count.parse::<u64>() {
+            Ok(size) => format!("tlv_helpers::read_array::<{size}>(&mut value)?"),
+            Err(_) => format!("tlv_helpers::read_bytes(&mut value, {count} as usize)?.to_vec()"),
+        },
+        (Some(count), _) => format!(
+            "(0..{count} as usize)\n                        .map(|_| -> std::io::Result<_> {{ Ok({elem}) }})\n                        .collect::<std::io::Result<Vec<_>>>()?"
+        ),
+    }
+}
+
 impl RustCodeGen {
+    /// Build a new rust code generator with the options provided.
+    pub fn with_options(
+        symbol_table: &BTreeMap<String, LNMsgType>,
+        options: RustCodeGenOptions,
+    ) -> Self {
+        RustCodeGen {
+            symbol_table: symbol_table.to_owned(),
+            options,
+            file_content: String::new(),
+            borrowed_content: String::new(),
+            has_tlv: false,
+            has_builder: false,
+            has_serde_with: false,
+            identation: 0,
+        }
+    }
+
     fn add_identation_to_code(&self, content: &str) -> String {
         let clean_content = String::new();
         content
@@ -55,15 +599,519 @@ impl RustCodeGen {
         self.identation -= 4;
         self.add_identation_to_code("\n}")
     }
+
+    /// Add a field to the struct that it is generated, with
+    /// the serde representation when the serde option is enabled.
+    fn add_struct_field(&mut self, name: &str, ty: &str, serde_with: Option<&str>) {
+        let name = rust_ident(name);
+        let code = match serde_with {
+            Some(module) if self.options.serde => {
+                self.has_serde_with = true;
+                let attr = format!("serde(with = \"serde_helpers::{module}\")");
+                fmt_struct_filed_with_attr!(name, ty, attr)
+            }
+            _ => fmt_struct_filed!(name, ty),
+        };
+        self.file_content += self.add_identation_to_code(&code).as_str();
+    }
+
+    /// Build the doc comment of the message from the spec documentation.
+    fn build_msg_doc(msg: &LNMsg) -> String {
+        let mut code = String::new();
+        let Some(doc) = &msg.doc else {
+            return code;
+        };
+        for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+            code += &format!("/// {paragraph}\n///\n");
+        }
+        code += &format!(
+            "/// Message type `{}`, defined in [{}: {}]({}).\n",
+            msg.msg_typ, doc.bolt, doc.section, doc.link
+        );
+        code
+    }
+
+    /// Return the rust type used inside the message struct.
+    fn field_type(field: &LNMsData) -> String {
+        let ty = match field {
+            LNMsData::Uint16(_) => "u16",
+            LNMsData::Uint32(_) => "u32",
+            LNMsData::Uint64(_) => "u64",
+            LNMsData::BigSize(_) => "BigSize",
+            LNMsData::ChainHash(..) => "ChainHash",
+            LNMsData::ChannelId(_) => "ChannelId",
+            LNMsData::Signature(_) => "Signature",
+            LNMsData::ShortChannelId(_) => "ShortChannelId",
+            LNMsData::Point(_) => "Point",
+            LNMsData::Sha256(_) => "Sha256",
+            LNMsData::BitfieldStream(..) => "BitFlag",
+            LNMsData::TLVinit(stream_name, _) => return stream_name.to_case(Case::Pascal),
+        };
+        ty.to_owned()
+    }
+
+    /// Return true if the subtype has a struct, that it is when all the
+    /// fields have a fixed size and they are not arrays.
+    fn is_struct_subtype(subtype: &LNSubType) -> bool {
+        subtype.ty_data.iter().all(|field| {
+            !matches!(field, LNMsData::BitfieldStream(..)) && field.fixed_size().is_some()
+        })
+    }
+
+    /// Return the tlv stream with the entries that the generated code can
+    /// decode: `u8` is a `byte`, and a subtype without struct is kept as
+    /// the raw bytes of the rest of the record.
+    fn normalize_tlv_stream(&self, tlv: &LNTlvStream) -> LNTlvStream {
+        let mut tlv = tlv.clone();
+        for entry in tlv
+            .records
+            .iter_mut()
+            .flat_map(|r| r.record_entry.iter_mut())
+        {
+            if entry.entry_ty == "u8" {
+                entry.entry_ty = "byte".to_owned();
+            }
+            if let Some(LNMsgType::SubType(subtype)) = self.symbol_table.get(&entry.entry_ty) {
+                if !Self::is_struct_subtype(subtype) {
+                    entry.entry_ty = "byte".to_owned();
+                    entry.count = Some("...".to_owned());
+                }
+            }
+        }
+        tlv
+    }
+
+    /// Return the name of the struct used for a record with more than one entry.
+    fn tlv_record_struct_name(record: &LNTlvRecord) -> String {
+        format!("{}_{}", record.stream_name, record.type_name).to_case(Case::Pascal)
+    }
+
+    /// Return the type of the struct field used for the record.
+    fn tlv_record_type(record: &LNTlvRecord) -> String {
+        match &record.record_entry[..] {
+            // the record has no value, so it is just a flag
+            [] => "()".to_owned(),
+            [entry] => tlv_entry_type(entry),
+            _ => Self::tlv_record_struct_name(record),
+        }
+    }
+
+    /// Build the struct of the tlv stream with one optional field for each
+    /// record, and the struct of the records that have more than one entry.
+    fn build_tlv_stream_struct(&mut self, tlv: &LNTlvStream) -> String {
+        let name = tlv.stream_name.to_case(Case::Pascal);
+        let derive = if self.options.serde {
+            "#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]\n"
+        } else {
+            "#[derive(Debug, Clone, Default, PartialEq)]\n"
+        };
+        let mut code = String::new();
+        for record in tlv
+            .records
+            .iter()
+            .filter(|record| record.record_entry.len() > 1)
+        {
+            // the record is optional inside the stream, so it has no default
+            code += &derive.replace(" Default,", "");
+            code += &format!("pub struct {} {{\n", Self::tlv_record_struct_name(record));
+            for entry in &record.record_entry {
+                if let Some(module) = tlv_entry_serde_with(entry).filter(|_| self.options.serde) {
+                    code += &format!("    #[serde(with = \"serde_helpers::{module}\")]\n");
+                    self.has_serde_with = true;
+                }
+                code += &format!(
+                    "    pub {}: {},\n",
+                    rust_ident(&entry.entry_name),
+                    tlv_entry_type(entry)
+                );
+            }
+            code += "}\n\n";
+        }
+
+        code += derive;
+        code += &format!("pub struct {name} {{\n");
+        for record in &tlv.records {
+            if self.options.serde {
+                code += "    #[serde(default, skip_serializing_if = \"Option::is_none\"";
+                // the record struct has already the representation of its fields
+                if let [entry] = &record.record_entry[..] {
+                    if let Some(module) = tlv_entry_serde_with(entry) {
+                        let module = match module.strip_suffix("::vec") {
+                            Some(module) => format!("{module}::option_vec"),
+                            None => format!("{module}::option"),
+                        };
+                        code += &format!(", with = \"serde_helpers::{module}\"");
+                        self.has_serde_with = true;
+                    }
+                }
+                code += ")]\n";
+            }
+            code += &format!(
+                "    pub {}: Option<{}>,\n",
+                rust_ident(&record.type_name),
+                Self::tlv_record_type(record)
+            );
+        }
+        code += "}\n\n";
+
+        // encoding of the stream, the records are already sorted by type
+        code += &format!("impl ToWire for {name} {{\n");
+        code += "    fn to_wire<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {\n";
+        for record in &tlv.records {
+            let field = rust_ident(&record.type_name);
+            if record.record_entry.is_empty() {
+                code += &format!(
+                    "        if self.{field}.is_some() {{\n            tlv_helpers::write_record(writer, {}, &[])?;\n        }}\n",
+                    record.type_len
+                );
+                continue;
+            }
+            code += &format!("        if let Some({field}) = &self.{field} {{\n");
+            code += "            let mut buf = Vec::new();\n";
+            match &record.record_entry[..] {
+                [entry] => code += &tlv_entry_encode(entry, &field),
+                entries => {
+                    for entry in entries {
+                        code += &tlv_entry_encode(
+                            entry,
+                            &format!("(&{field}.{})", rust_ident(&entry.entry_name)),
+                        );
+                    }
+                }
+            }
+            code += &format!(
+                "            tlv_helpers::write_record(writer, {}, &buf)?;\n        }}\n",
+                record.type_len
+            );
+        }
+        code += "        Ok(())\n    }\n}\n\n";
+
+        // decoding of the stream, that it is always the last field of the message
+        code += &format!("impl FromWire for {name} {{\n");
+        code += "    fn from_wire<R: Read>(reader: &mut R) -> std::io::Result<Self> {\n";
+        code += "        let mut buf = Vec::new();\n";
+        code += "        reader.read_to_end(&mut buf)?;\n";
+        code += "        let mut stream = buf.as_slice();\n";
+        code += "        let mut tlvs = Self::default();\n";
+        code += "        let mut last_type = None;\n";
+        code += "        while !stream.is_empty() {\n";
+        code += "            let ty = tlv_helpers::read_bigsize(&mut stream)?;\n";
+        code += "            if last_type.is_some_and(|last| ty <= last) {\n";
+        code += "                return Err(tlv_helpers::invalid(\"tlv records are not strictly increasing\"));\n";
+        code += "            }\n";
+        code += "            last_type = Some(ty);\n";
+        code += "            let len = tlv_helpers::read_bigsize(&mut stream)?;\n";
+        code += "            #[allow(unused_mut)]\n";
+        code +=
+            "            let mut value = tlv_helpers::read_bytes(&mut stream, len as usize)?;\n";
+        code += "            match ty {\n";
+        for record in &tlv.records {
+            code += &format!("                {} => {{\n", record.type_len);
+            for entry in &record.record_entry {
+                code += &format!(
+                    "                    let {} = {};\n",
+                    rust_ident(&entry.entry_name),
+                    tlv_entry_decode(entry)
+                );
+            }
+            let value = match &record.record_entry[..] {
+                [] => "()".to_owned(),
+                [entry] => rust_ident(&entry.entry_name),
+                entries => {
+                    let fields: Vec<String> = entries
+                        .iter()
+                        .map(|entry| rust_ident(&entry.entry_name))
+                        .collect();
+                    format!(
+                        "{} {{ {} }}",
+                        Self::tlv_record_struct_name(record),
+                        fields.join(", ")
+                    )
+                }
+            };
+            code += &format!(
+                "                    tlvs.{} = Some({value});\n                }}\n",
+                rust_ident(&record.type_name)
+            );
+        }
+        code += "                ty if ty % 2 == 0 => return Err(tlv_helpers::invalid(\"unknown even tlv type\")),\n";
+        code += "                // it is ok to be odd\n";
+        code += "                _ => continue,\n";
+        code += "            }\n";
+        code += "            if !value.is_empty() {\n";
+        code += "                return Err(tlv_helpers::invalid(\"tlv record with trailing bytes\"));\n";
+        code += "            }\n";
+        code += "        }\n";
+        code += "        Ok(tlvs)\n    }\n}\n\n";
+        code
+    }
+
+    /// Build the `FooBuilder` of the message, the message type and
+    /// the length fields are not exposed because they are computed
+    /// in the `build()` method.
+    fn build_msg_builder(&self, msg: &LNMsg) -> String {
+        let name = msg.msg_name.to_case(Case::Pascal);
+        // length fields that are still part of the message
+        let len_fields: BTreeMap<&str, &str> = msg
+            .msg_data
+            .iter()
+            .filter_map(|field| match field {
+                LNMsData::BitfieldStream(name, size, _) => Some((size.as_str(), name.as_str())),
+                _ => None,
+            })
+            .filter(|(size, _)| msg.is_length_field(size))
+            .collect();
+
+        let mut fields = String::new();
+        let mut setters = String::new();
+        let mut build = String::new();
+        // the length fields are computed after the bytes that they describe
+        let mut build_lens = String::new();
+        let mut names = vec!["ty".to_owned()];
+        for field in &msg.msg_data {
+            let raw_name = match field {
+                LNMsData::TLVinit(stream_name, _) => stream_name.as_str(),
+                _ => field.name(),
+            };
+            let field_name = rust_ident(raw_name);
+            let ty = Self::field_type(field);
+            names.push(field_name.to_owned());
+            if let Some(bytes) = len_fields.get(raw_name) {
+                let bytes = rust_ident(bytes);
+                build_lens += &format!(
+                    "        let {field_name} = {ty}::try_from(builder_helpers::payload_len(&{bytes})?)\n            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;\n"
+                );
+                continue;
+            }
+            fields += &format!("    {field_name}: Option<{ty}>,\n");
+            setters += &format!(
+                "\n    pub fn {field_name}(mut self, {field_name}: {ty}) -> Self {{\n        self.{field_name} = Some({field_name});\n        self\n    }}\n"
+            );
+            if let LNMsData::TLVinit(stream_name, _) = field {
+                setters += &self.build_tlv_record_setters(msg, stream_name);
+            }
+            let value = match field {
+                LNMsData::Uint16(_)
+                | LNMsData::Uint32(_)
+                | LNMsData::Uint64(_)
+                | LNMsData::BigSize(_) => format!("self.{field_name}.unwrap_or_default()"),
+                LNMsData::TLVinit(..) => format!("self.{field_name}.unwrap_or_default()"),
+                _ => format!(
+                    "self.{field_name}\n            .ok_or_else(|| builder_helpers::missing_field(\"{raw_name}\"))?"
+                ),
+            };
+            build += &format!("        let {field_name} = {value};\n");
+            if let LNMsData::BitfieldStream(_, size, _) = field {
+                if let Ok(size) = size.parse::<u64>() {
+                    build += &format!(
+                        "        builder_helpers::check_fixed_size(\"{raw_name}\", &{field_name}, {size})?;\n"
+                    );
+                }
+            }
+        }
+        let mut code = format!("/// Builder for [`{name}`].\n");
+        code += "#[derive(Debug, Clone, Default)]\n";
+        code += &format!("pub struct {name}Builder {{\n{fields}}}\n\n");
+        code += &format!("impl {name}Builder {{\n");
+        code += "    pub fn new() -> Self {\n        Self::default()\n    }\n";
+        code += &setters;
+        code += &format!("\n    pub fn build(self) -> std::io::Result<{name}> {{\n");
+        code += &build;
+        code += &build_lens;
+        code += &format!("        Ok({name} {{\n            ty: {},\n", msg.msg_typ);
+        for field_name in names.iter().skip(1) {
+            code += &format!("            {field_name},\n");
+        }
+        code += "        })\n    }\n}\n\n";
+        code += &format!(
+            "impl {name} {{\n    pub fn builder() -> {name}Builder {{\n        {name}Builder::new()\n    }}\n}}\n\n"
+        );
+        code
+    }
+
+    /// Build one setter for each record of the tlv stream, the setter
+    /// has the name of the record unless it is already used by the message.
+    fn build_tlv_record_setters(&self, msg: &LNMsg, stream_name: &str) -> String {
+        let Some(LNMsgType::Tlv(tlv)) = self.symbol_table.get(stream_name) else {
+            return String::new();
+        };
+        let stream = rust_ident(stream_name);
+        let mut setters = String::new();
+        for record in &tlv.records {
+            let name = rust_ident(&record.type_name);
+            let setter = if msg
+                .msg_data
+                .iter()
+                .any(|field| field.name() == record.type_name)
+            {
+                format!("{stream_name}_{}", record.type_name)
+            } else {
+                name.to_owned()
+            };
+            let ty = Self::tlv_record_type(record);
+            setters += &format!(
+                "\n    pub fn {setter}(mut self, {name}: {ty}) -> Self {{\n        self.{stream}.get_or_insert_with(Default::default).{name} = Some({name});\n        self\n    }}\n"
+            );
+        }
+        setters
+    }
+
+    /// Build the zero copy view of the message, the struct keeps
+    /// the integers decoded and the bytes fields as slices of the
+    /// input buffer.
+    fn build_borrowed_msg(&self, msg: &LNMsg) -> String {
+        let name = msg.msg_name.to_case(Case::Pascal);
+        // the length fields that are not folded inside the bitfield
+        // are still part of the message, so we can use them directly.
+        let int_fields: Vec<&str> = msg
+            .msg_data
+            .iter()
+            .filter(|field| {
+                matches!(
+                    field,
+                    LNMsData::Uint16(_) | LNMsData::Uint32(_) | LNMsData::Uint64(_)
+                )
+            })
+            .map(|field| field.name())
+            .collect();
+
+        let mut fields = String::from("    pub ty: u16,\n");
+        let mut decode = format!(
+            "        let ty = take_u16(&mut buf)?;\n        if ty != {} {{\n            return Err(std::io::Error::new(ErrorKind::InvalidData, \"unexpected message type\"));\n        }}\n",
+            msg.msg_typ
+        );
+        let mut names = vec!["ty".to_owned()];
+        for field in &msg.msg_data {
+            let (field_name, ty, read) = match field {
+                LNMsData::Uint16(name) => {
+                    (name.to_owned(), "u16", "take_u16(&mut buf)?".to_owned())
+                }
+                LNMsData::Uint32(name) => {
+                    (name.to_owned(), "u32", "take_u32(&mut buf)?".to_owned())
+                }
+                LNMsData::Uint64(name) => {
+                    (name.to_owned(), "u64", "take_u64(&mut buf)?".to_owned())
+                }
+                LNMsData::BigSize(name) => {
+                    (name.to_owned(), "u64", "take_bigsize(&mut buf)?".to_owned())
+                }
+                LNMsData::BitfieldStream(name, size, ty) => {
+                    // the count is in elements, the slice is in bytes
+                    let elem = LNMsData::element_size(ty).unwrap();
+                    let read = if size == "..." {
+                        "std::mem::take(&mut buf)".to_owned()
+                    } else if let Ok(size) = size.parse::<u64>() {
+                        format!("take(&mut buf, {})?", size * elem)
+                    } else if int_fields.contains(&size.as_str()) {
+                        format!("take(&mut buf, {} as usize * {elem})?", rust_ident(size))
+                    } else {
+                        // the length was folded inside the bitfield, so
+                        // it is a u16 prefix of the elements.
+                        format!("{{\n            let len = take_u16(&mut buf)?;\n            take(&mut buf, len as usize * {elem})?\n        }}")
+                    };
+                    (name.to_owned(), "&'a [u8]", read)
+                }
+                LNMsData::TLVinit(stream_name, _) => (
+                    stream_name.to_owned(),
+                    "&'a [u8]",
+                    "std::mem::take(&mut buf)".to_owned(),
+                ),
+                _ => {
+                    let size = field.fixed_size().unwrap();
+                    (
+                        field.name().to_owned(),
+                        "&'a [u8]",
+                        format!("take(&mut buf, {size})?"),
+                    )
+                }
+            };
+            let field_name = rust_ident(&field_name);
+            fields += &format!("    pub {field_name}: {ty},\n");
+            decode += &format!("        let {field_name} = {read};\n");
+            names.push(field_name);
+        }
+        fields += "    /// wire encoding of the message.\n    raw: &'a [u8],\n";
+        decode += "        let raw = &raw[..raw.len() - buf.len()];\n";
+        names.push("raw".to_owned());
+
+        let mut code = String::from("#[derive(Debug, Clone, Copy)]\n");
+        code += &format!("pub struct {name}<'a> {{\n{fields}}}\n\n");
+        code += &format!("impl<'a> {name}<'a> {{\n");
+        code += "    pub fn decode(mut buf: &'a [u8]) -> std::io::Result<Self> {\n";
+        code += "        let raw = buf;\n";
+        code += &decode;
+        code += &format!("        Ok(Self {{ {} }})\n    }}\n\n", names.join(", "));
+        code += "    /// Return the wire encoding of the message.\n";
+        code += "    pub fn as_bytes(&self) -> &'a [u8] {\n        self.raw\n    }\n\n";
+        code += &format!("    pub fn to_owned(&self) -> std::io::Result<super::{name}> {{\n");
+        code += &format!("        super::{name}::from_wire(&mut &self.raw[..])\n    }}\n}}\n");
+        code
+    }
+
+    /// Wrap the zero copy views inside the `borrowed` module
+    /// with the helpers that they use to read the buffer.
+    fn build_borrowed_module(&self) -> String {
+        let mut code = indoc! {"
+            pub mod borrowed {
+                //! Zero copy views of the messages, the bytes fields
+                //! borrow from the buffer that is decoded.
+                use std::io::ErrorKind;
+
+                use super::*;
+
+                fn take<'a>(buf: &mut &'a [u8], len: usize) -> std::io::Result<&'a [u8]> {
+                    if buf.len() < len {
+                        return Err(std::io::Error::new(ErrorKind::UnexpectedEof, \"buffer too short\"));
+                    }
+                    let (head, tail) = buf.split_at(len);
+                    *buf = tail;
+                    Ok(head)
+                }
+
+                fn take_u16(buf: &mut &[u8]) -> std::io::Result<u16> {
+                    Ok(u16::from_be_bytes(take(buf, 2)?.try_into().unwrap()))
+                }
+
+                #[allow(dead_code)]
+                fn take_u32(buf: &mut &[u8]) -> std::io::Result<u32> {
+                    Ok(u32::from_be_bytes(take(buf, 4)?.try_into().unwrap()))
+                }
+
+                #[allow(dead_code)]
+                fn take_u64(buf: &mut &[u8]) -> std::io::Result<u64> {
+                    Ok(u64::from_be_bytes(take(buf, 8)?.try_into().unwrap()))
+                }
+
+                #[allow(dead_code)]
+                fn take_bigsize(buf: &mut &[u8]) -> std::io::Result<u64> {
+                    let (value, min) = match take(buf, 1)?[0] {
+                        0xFF => (take_u64(buf)?, 0x100000000),
+                        0xFE => (take_u32(buf)? as u64, 0x10000),
+                        0xFD => (take_u16(buf)? as u64, 0xFD),
+                        value => return Ok(value as u64),
+                    };
+                    if value < min {
+                        return Err(std::io::Error::new(ErrorKind::InvalidData, \"bigsize not minimally encoded\"));
+                    }
+                    Ok(value)
+                }
+        "}
+        .to_owned();
+        for line in self.borrowed_content.lines() {
+            code += "\n";
+            if !line.is_empty() {
+                code += "    ";
+                code += line;
+            }
+        }
+        code += "}\n";
+        code
+    }
 }
 
 impl<'g> CodeGen<'g> for RustCodeGen {
     fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
-        RustCodeGen {
-            symbol_table: symbol_table.to_owned(),
-            file_content: String::new(),
-            identation: 0,
-        }
+        RustCodeGen::with_options(symbol_table, RustCodeGenOptions::default())
     }
 
     fn pre_generation(&mut self) {
@@ -72,13 +1120,23 @@ impl<'g> CodeGen<'g> for RustCodeGen {
         code += "use fundamentals_derive::{DecodeWire, EncodeWire};\n\n";
         code += "use crate::core::{FromWire, ToWire};\n";
         code += "use crate::prelude::*;\n";
+        if self.options.serde {
+            code += "\nuse serde::{Deserialize, Serialize};\n";
+        }
         self.file_content += code.as_str();
         self.file_content += "\n\n";
     }
 
     fn build_msg(&mut self, msg: &LNMsg) {
-        let mut code = String::from("#[derive(DecodeWire, EncodeWire, Debug, Clone)]\n");
+        let mut code = if self.options.serde {
+            String::from(
+                "#[derive(DecodeWire, EncodeWire, Debug, Clone, Serialize, Deserialize)]\n",
+            )
+        } else {
+            String::from("#[derive(DecodeWire, EncodeWire, Debug, Clone)]\n")
+        };
         code += &format!("pub struct {} ", msg.msg_name.to_case(Case::Pascal));
+        self.file_content += &Self::build_msg_doc(msg);
         self.file_content += &code;
         self.open_scope();
         let attr = format!("msg_type={}", msg.msg_typ);
@@ -86,10 +1144,38 @@ impl<'g> CodeGen<'g> for RustCodeGen {
         self.file_content += self.add_identation_to_code(&code).as_str();
     }
 
-    fn end_msg(&mut self, _: &LNMsg) {
+    fn end_msg(&mut self, msg: &LNMsg) {
         let code = self.close_scope();
         self.file_content += code.as_str();
         self.file_content += "\n\n";
+        if self.options.builder {
+            let code = self.build_msg_builder(msg);
+            self.file_content += &code;
+            self.has_builder = true;
+        }
+        if self.options.zero_copy {
+            let code = self.build_borrowed_msg(msg);
+            self.borrowed_content += &code;
+            self.borrowed_content += "\n";
+        }
+    }
+
+    fn post_generation(&mut self) {
+        // every helper module is followed by an empty line, like the messages
+        for (used, helpers) in [
+            (self.has_tlv, TLV_HELPERS),
+            (self.has_builder, BUILDER_HELPERS),
+            (self.has_serde_with, SERDE_HELPERS),
+        ] {
+            if used {
+                self.file_content += helpers;
+                self.file_content += "\n";
+            }
+        }
+        if self.options.zero_copy && !self.borrowed_content.is_empty() {
+            let code = self.build_borrowed_module();
+            self.file_content += &code;
+        }
     }
 
     fn build_encode_fn(&mut self) {}
@@ -102,8 +1188,7 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_u16(&mut self, field: &LNMsData) {
         if let LNMsData::Uint16(name) = field {
-            let code = fmt_struct_filed!(name, "u16");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "u16", None);
         }
     }
 
@@ -111,8 +1196,7 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_u32(&mut self, field: &LNMsData) {
         if let LNMsData::Uint32(name) = field {
-            let code = fmt_struct_filed!(name, "u32");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "u32", None);
         }
     }
 
@@ -120,17 +1204,23 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_u64(&mut self, field: &csvlang::parser::ast::LNMsData) {
         if let LNMsData::Uint64(name) = field {
-            let code = fmt_struct_filed!(name, "u64");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "u64", None);
         }
     }
 
     fn write_u64(&mut self, _: &LNMsData) {}
 
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.add_struct_field(name, "BigSize", Some("wire_hex"));
+        }
+    }
+
+    fn write_bigsize(&mut self, _: &LNMsData) {}
+
     fn build_chain_hash(&mut self, field: &LNMsData) {
         if let LNMsData::ChainHash(name, _) = field {
-            let code = fmt_struct_filed!(name, "ChainHash");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "ChainHash", Some("wire_hex"));
         }
     }
 
@@ -138,8 +1228,7 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_channel_id(&mut self, filed: &LNMsData) {
         if let LNMsData::ChannelId(name) = filed {
-            let code = fmt_struct_filed!(name, "ChannelId");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "ChannelId", Some("wire_hex"));
         }
     }
 
@@ -147,8 +1236,7 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_short_channel_id(&mut self, filed: &LNMsData) {
         if let LNMsData::ShortChannelId(name) = filed {
-            let code = fmt_struct_filed!(name, "ShortChannelId");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "ShortChannelId", Some("short_channel_id"));
         }
     }
 
@@ -156,15 +1244,13 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_point(&mut self, field: &LNMsData) {
         if let LNMsData::Point(name) = field {
-            let code = fmt_struct_filed!(name, "Point");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "Point", Some("wire_hex"));
         }
     }
 
     fn build_sha256(&mut self, field: &LNMsData) {
         if let LNMsData::Sha256(name) = field {
-            let code = fmt_struct_filed!(name, "Sha256");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "Sha256", Some("wire_hex"));
         }
     }
 
@@ -174,24 +1260,67 @@ impl<'g> CodeGen<'g> for RustCodeGen {
 
     fn build_signature(&mut self, filed: &LNMsData) {
         if let LNMsData::Signature(name) = filed {
-            let code = fmt_struct_filed!(name, "Signature");
-            self.file_content += self.add_identation_to_code(&code).as_str();
+            self.add_struct_field(name, "Signature", Some("wire_hex"));
         }
     }
 
     fn write_signature(&mut self, _: &LNMsData) {}
 
-    fn build_tlv_stream(&mut self, field: &LNTlvRecord) {
-        let code = fmt_struct_filed!(field.stream_name, "Stream");
-        self.file_content += &self.add_identation_to_code(&code);
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = field.stream_name.to_case(Case::Pascal);
+        self.add_struct_field(&field.stream_name, &ty, None);
+    }
+
+    fn write_tlv_stream(&mut self, _: &LNTlvStream) {}
+
+    fn raw_arrays(&self) -> bool {
+        // only the borrowed views slice the arrays
+        self.options.zero_copy
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        let tlv = self.normalize_tlv_stream(tlv);
+        let code = self.build_tlv_stream_struct(&tlv);
+        self.file_content += &code;
+        self.has_tlv = true;
     }
 
-    fn write_tlv_stream(&mut self, _: &LNTlvRecord) {}
+    fn generate_subtype(&mut self, subtype: &LNSubType) {
+        // the subtypes are used only by the tlv records
+        if !Self::is_struct_subtype(subtype) {
+            return;
+        }
+        self.file_content += if self.options.serde {
+            "#[derive(DecodeWire, EncodeWire, Debug, Clone, PartialEq, Serialize, Deserialize)]\n"
+        } else {
+            "#[derive(DecodeWire, EncodeWire, Debug, Clone, PartialEq)]\n"
+        };
+        self.file_content += &format!("pub struct {} ", subtype.ty.to_case(Case::Pascal));
+        self.open_scope();
+        for field in &subtype.ty_data {
+            match field {
+                LNMsData::Uint16(_) => self.build_u16(field),
+                LNMsData::Uint32(_) => self.build_u32(field),
+                LNMsData::Uint64(_) => self.build_u64(field),
+                LNMsData::ChainHash(..) => self.build_chain_hash(field),
+                LNMsData::ChannelId(_) => self.build_channel_id(field),
+                LNMsData::ShortChannelId(_) => self.build_short_channel_id(field),
+                LNMsData::Point(_) => self.build_point(field),
+                LNMsData::Sha256(_) => self.build_sha256(field),
+                LNMsData::Signature(_) => self.build_signature(field),
+                LNMsData::BigSize(_) | LNMsData::BitfieldStream(..) | LNMsData::TLVinit(..) => {
+                    unreachable!("the subtype `{}` has not a struct", subtype.ty)
+                }
+            }
+        }
+        let code = self.close_scope();
+        self.file_content += code.as_str();
+        self.file_content += "\n\n";
+    }
 
     fn build_bitfield(&mut self, field: &LNMsData) {
-        if let LNMsData::BitfieldStream(name, _) = field {
-            let code = fmt_struct_filed!(name, "BitFlag");
-            self.file_content += &self.add_identation_to_code(&code);
+        if let LNMsData::BitfieldStream(name, ..) = field {
+            self.add_struct_field(name, "BitFlag", Some("bitflag_hex"));
         }
     }
 
@@ -203,3 +1332,597 @@ impl Display for RustCodeGen {
         write!(f, "{}", self.file_content)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use csvlang::markdown::docs::SpecDocs;
+
+    use crate::codegen::{CodeGen, CodeGenError};
+    use crate::rust::{RustCodeGen, RustCodeGenOptions};
+    use crate::testing::{init, parse, run_rust};
+
+    #[test]
+    fn rust_zero_copy_msg() {
+        init();
+        let contents = "msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16,\n\
+                        msgdata,ping,byteslen,u16,\n\
+                        msgdata,ping,ignored,byte,byteslen\n";
+        let symbol_table = parse(contents);
+        let options = RustCodeGenOptions {
+            zero_copy: true,
+            ..Default::default()
+        };
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("pub struct Ping {"), "{code}");
+        assert!(code.contains("pub mod borrowed {"), "{code}");
+        assert!(code.contains("    pub struct Ping<'a> {"), "{code}");
+        assert!(code.contains("        pub ignored: &'a [u8],"), "{code}");
+        assert!(code.contains("pub fn to_owned(&self) -> std::io::Result<super::Ping>"));
+
+        let main = r#"
+            use crate::core::ToWire;
+            use crate::generated::{borrowed, Ping};
+            use crate::prelude::BitFlag;
+
+            fn main() {
+                let ping = Ping {
+                    ty: 18,
+                    num_pong_bytes: 4,
+                    ignored: BitFlag(vec![1, 2, 3]),
+                };
+                let mut wire = Vec::new();
+                ping.to_wire(&mut wire).unwrap();
+                let len = wire.len();
+                // the bytes after the message are not part of the view
+                wire.push(0xff);
+                let view = borrowed::Ping::decode(&wire).unwrap();
+                assert_eq!(view.num_pong_bytes, 4);
+                assert_eq!(view.ignored, [1, 2, 3]);
+                assert_eq!(view.as_bytes(), &wire[..len]);
+                let owned = view.to_owned().unwrap();
+                assert_eq!(owned.ignored, ping.ignored);
+                assert!(borrowed::Ping::decode(&wire[..len - 2]).is_err());
+                let err = borrowed::Ping::decode(&[0, 19, 0, 4]).unwrap_err();
+                println!("{err}");
+            }
+        "#;
+        let stdout = run_rust("zero_copy_msg", &code, main);
+        assert_eq!(stdout, "unexpected message type\n");
+    }
+
+    #[test]
+    fn rust_zero_copy_array_elements() {
+        init();
+        let contents = "msgtype,commitment_signed,132\n\
+                        msgdata,commitment_signed,channel_id,channel_id,\n\
+                        msgdata,commitment_signed,num_htlcs,u16,\n\
+                        msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n";
+        let symbol_table = parse(contents);
+        let options = RustCodeGenOptions {
+            zero_copy: true,
+            ..Default::default()
+        };
+        let mut backend = RustCodeGen::with_options(&symbol_table, options.clone());
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        let main = r#"
+            use crate::generated::borrowed;
+
+            fn main() {
+                let mut wire = vec![0, 132];
+                wire.extend([1; 32]);
+                wire.extend([0, 2]);
+                wire.extend([7; 128]);
+                let view = borrowed::CommitmentSigned::decode(&wire).unwrap();
+                assert_eq!(view.htlc_signature, &wire[36..]);
+                assert!(borrowed::CommitmentSigned::decode(&wire[..wire.len() - 1]).is_err());
+                println!("{}", view.htlc_signature.len());
+            }
+        "#;
+        let stdout = run_rust("zero_copy_array_elements", &code, main);
+        assert_eq!(stdout, "128\n");
+
+        let contents = "subtype,witness\n\
+                        subtypedata,witness,len,u16,\n\
+                        subtypedata,witness,witness_data,byte,len\n\
+                        msgtype,tx_signatures,71\n\
+                        msgdata,tx_signatures,num_witnesses,u16,\n\
+                        msgdata,tx_signatures,witnesses,witness,num_witnesses\n";
+        let symbol_table = parse(contents);
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        let err = backend.generate(&symbol_table).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`tx_signatures.witnesses` is an array of `witness`, that has not a fixed size"
+        );
+    }
+
+    #[test]
+    fn rust_serde_msg() {
+        init();
+        let contents = "msgtype,channel_update,258\n\
+                        msgdata,channel_update,signature,signature,\n\
+                        msgdata,channel_update,chain_hash,chain_hash,\n\
+                        msgdata,channel_update,short_channel_id,short_channel_id,\n\
+                        msgdata,channel_update,timestamp,u32,\n";
+        let symbol_table = parse(contents);
+        let options = RustCodeGenOptions {
+            serde: true,
+            ..Default::default()
+        };
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("Clone, Serialize, Deserialize)]"), "{code}");
+        assert!(
+            code.contains("    #[serde(with = \"serde_helpers::short_channel_id\")]\n    pub short_channel_id: ShortChannelId,"),
+            "{code}"
+        );
+        assert!(code.contains("    pub timestamp: u32,"), "{code}");
+        assert!(code.contains("mod serde_helpers {"), "{code}");
+
+        let main = r#"
+            use crate::core::ToWire;
+            use crate::generated::ChannelUpdate;
+
+            fn main() {
+                let msg = ChannelUpdate {
+                    ty: 258,
+                    signature: [0x30; 64],
+                    chain_hash: [0x6f; 32],
+                    short_channel_id: [0, 0, 1, 0, 0, 2, 0, 3],
+                    timestamp: 1700000000,
+                };
+                let json = serde_json::to_string(&msg).unwrap();
+                println!("{json}");
+                let decoded: ChannelUpdate = serde_json::from_str(&json).unwrap();
+                let (mut wire, mut decoded_wire) = (Vec::new(), Vec::new());
+                msg.to_wire(&mut wire).unwrap();
+                decoded.to_wire(&mut decoded_wire).unwrap();
+                assert_eq!(wire, decoded_wire);
+                let json = json.replace("1x2x3", "1x2");
+                assert!(serde_json::from_str::<ChannelUpdate>(&json).is_err());
+            }
+        "#;
+        let json = run_rust("serde_msg", &code, main);
+        let expected = format!(
+            "{{\"ty\":258,\"signature\":\"{}\",\"chain_hash\":\"{}\",\"short_channel_id\":\"1x2x3\",\"timestamp\":1700000000}}\n",
+            "30".repeat(64),
+            "6f".repeat(32)
+        );
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn rust_msg_builder() {
+        init();
+        let contents = "msgtype,node_announcement,257\n\
+                        msgdata,node_announcement,signature,signature,\n\
+                        msgdata,node_announcement,flen,u16,\n\
+                        msgdata,node_announcement,features,byte,flen\n\
+                        msgdata,node_announcement,timestamp,u32,\n\
+                        msgdata,node_announcement,rgb_color,byte,3\n\
+                        msgdata,node_announcement,addrlen,u16,\n\
+                        msgdata,node_announcement,padding,u32,\n\
+                        msgdata,node_announcement,addresses,byte,addrlen\n";
+        let symbol_table = parse(contents);
+        let options = RustCodeGenOptions {
+            builder: true,
+            ..Default::default()
+        };
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("pub struct NodeAnnouncementBuilder {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    pub fn timestamp(mut self, timestamp: u32) -> Self {"),
+            "{code}"
+        );
+        // the length field is computed by the builder
+        assert!(!code.contains("    addrlen: Option<u16>,"), "{code}");
+        assert!(
+            code.contains("let addrlen = u16::try_from(builder_helpers::payload_len(&addresses)?)"),
+            "{code}"
+        );
+        assert!(
+            code.contains("builder_helpers::check_fixed_size(\"rgb_color\", &rgb_color, 3)?;"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    pub fn builder() -> NodeAnnouncementBuilder {"),
+            "{code}"
+        );
+
+        let main = r#"
+            use crate::core::{FromWire, ToWire};
+            use crate::generated::NodeAnnouncement;
+            use crate::prelude::BitFlag;
+
+            fn main() {
+                let builder = NodeAnnouncement::builder()
+                    .signature([0x30; 64])
+                    .features(BitFlag(vec![0x80]))
+                    .timestamp(1700000000)
+                    .rgb_color(BitFlag(vec![1, 2, 3]))
+                    .addresses(BitFlag(vec![1, 127, 0, 0, 1, 0x26, 0x07]));
+                let msg = builder.clone().build().unwrap();
+                assert_eq!(msg.ty, 257);
+                assert_eq!(msg.addrlen, 7);
+                assert_eq!(msg.padding, 0);
+                let mut wire = Vec::new();
+                msg.to_wire(&mut wire).unwrap();
+                let decoded = NodeAnnouncement::from_wire(&mut wire.as_slice()).unwrap();
+                assert_eq!(decoded.addresses, msg.addresses);
+                assert_eq!(decoded.addrlen, 7);
+
+                let err = NodeAnnouncement::builder().build().unwrap_err();
+                println!("{err}");
+                let err = builder.rgb_color(BitFlag(vec![1])).build().unwrap_err();
+                println!("{err}");
+            }
+        "#;
+        let stdout = run_rust("msg_builder", &code, main);
+        assert_eq!(
+            stdout,
+            "missing field `signature`\nfield `rgb_color` must be 3 bytes, found 1\n"
+        );
+    }
+
+    #[test]
+    fn rust_msg_builder_keywords() {
+        init();
+        let contents = "msgtype,keywords,32769\n\
+                        msgdata,keywords,type,u16,\n\
+                        msgdata,keywords,match,channel_id,\n";
+        let symbol_table = parse(contents);
+        let options = RustCodeGenOptions {
+            builder: true,
+            ..Default::default()
+        };
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("    r#type: Option<u16>,"), "{code}");
+        assert!(
+            code.contains("    pub fn r#match(mut self, r#match: ChannelId) -> Self {"),
+            "{code}"
+        );
+        assert!(code.contains("missing_field(\"match\")"), "{code}");
+        assert!(
+            code.contains("            r#type,\n            r#match,\n"),
+            "{code}"
+        );
+
+        let main = r#"
+            use crate::generated::Keywords;
+
+            fn main() {
+                let msg = Keywords::builder().r#type(7).r#match([1; 32]).build().unwrap();
+                println!("{} {}", msg.r#type, msg.r#match[0]);
+            }
+        "#;
+        assert_eq!(run_rust("msg_builder_keywords", &code, main), "7 1\n");
+    }
+
+    #[test]
+    fn rust_typed_tlv_stream() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+                        tlvtype,init_tlvs,remote_addr,3\n\
+                        tlvdata,init_tlvs,remote_addr,data,byte,...\n\
+                        tlvtype,init_tlvs,payment_data,5\n\
+                        tlvdata,init_tlvs,payment_data,payment_secret,byte,32\n\
+                        tlvdata,init_tlvs,payment_data,total_msat,tu64,\n";
+        let symbol_table = parse(contents);
+        let mut backend = RustCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("    pub init_tlvs: InitTlvs,"), "{code}");
+        assert!(code.contains("pub struct InitTlvs {"), "{code}");
+        assert!(
+            code.contains("    pub networks: Option<Vec<ChainHash>>,"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    pub remote_addr: Option<Vec<u8>>,"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    pub payment_data: Option<InitTlvsPaymentData>,"),
+            "{code}"
+        );
+        assert!(code.contains("pub struct InitTlvsPaymentData {"), "{code}");
+        assert!(code.contains("    pub payment_secret: [u8; 32],"), "{code}");
+        assert!(code.contains("    pub total_msat: u64,"), "{code}");
+        assert!(code.contains("impl FromWire for InitTlvs {"), "{code}");
+        assert!(code.contains("mod tlv_helpers {"), "{code}");
+
+        let main = r#"
+            use crate::core::{FromWire, ToWire};
+            use crate::generated::{Init, InitTlvs, InitTlvsPaymentData};
+            use crate::prelude::BitFlag;
+
+            fn main() {
+                let init_tlvs = InitTlvs {
+                    networks: Some(vec![[0x6f; 32], [0x43; 32]]),
+                    remote_addr: None,
+                    payment_data: Some(InitTlvsPaymentData {
+                        payment_secret: [7; 32],
+                        total_msat: 1000,
+                    }),
+                };
+                let init = Init {
+                    ty: 16,
+                    globalfeatures: BitFlag(vec![]),
+                    init_tlvs: init_tlvs.clone(),
+                };
+                let mut wire = Vec::new();
+                init.to_wire(&mut wire).unwrap();
+                let hex = wire.iter().map(|b| format!("{b:02x}")).collect::<String>();
+                println!("{hex}");
+                let decoded = Init::from_wire(&mut wire.as_slice()).unwrap();
+                assert_eq!(decoded.init_tlvs, init_tlvs);
+
+                // the unknown odd records are ignored, the even ones are rejected
+                let mut odd = wire.clone();
+                odd.extend([7, 1, 0xff]);
+                let decoded = Init::from_wire(&mut odd.as_slice()).unwrap();
+                assert_eq!(decoded.init_tlvs, init_tlvs);
+                let mut even = wire.clone();
+                even.extend([6, 0]);
+                assert!(Init::from_wire(&mut even.as_slice()).is_err());
+            }
+        "#;
+        let hex = run_rust("typed_tlv_stream", &code, main);
+        let expected = format!(
+            "001000000140{}{}0522{}03e8\n",
+            "6f".repeat(32),
+            "43".repeat(32),
+            "07".repeat(32)
+        );
+        assert_eq!(hex, expected);
+    }
+
+    #[test]
+    fn rust_helpers_only_when_used() {
+        init();
+        let options = RustCodeGenOptions {
+            zero_copy: true,
+            serde: true,
+            builder: true,
+        };
+        let symbol_table = parse("");
+        let mut backend = RustCodeGen::with_options(&symbol_table, options.clone());
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(!code.contains("_helpers {"), "{code}");
+        assert!(!code.contains("pub mod borrowed"), "{code}");
+
+        let contents = "msgtype,channel_update,258\n\
+                        msgdata,channel_update,short_channel_id,short_channel_id,\n";
+        let symbol_table = parse(contents);
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(!code.contains("mod tlv_helpers {"), "{code}");
+        assert!(
+            code.contains("}\n\n#[allow(dead_code)]\nmod builder_helpers {"),
+            "{code}"
+        );
+        assert!(
+            code.contains("}\n\n#[allow(dead_code)]\nmod serde_helpers {"),
+            "{code}"
+        );
+        assert!(code.contains("}\n\npub mod borrowed {"), "{code}");
+    }
+
+    #[test]
+    fn rust_serde_tlv_json_round_trip() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,flen,u16,\n\
+                        msgdata,init,features,byte,flen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+                        tlvtype,init_tlvs,remote_addr,3\n\
+                        tlvdata,init_tlvs,remote_addr,data,byte,...\n\
+                        tlvtype,init_tlvs,node,5\n\
+                        tlvdata,init_tlvs,node,node_id,point,\n\
+                        tlvdata,init_tlvs,node,scid,short_channel_id,\n";
+        let symbol_table = parse(contents);
+        let options = RustCodeGenOptions {
+            serde: true,
+            ..Default::default()
+        };
+        let mut backend = RustCodeGen::with_options(&symbol_table, options);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("    #[serde(default, skip_serializing_if = \"Option::is_none\", with = \"serde_helpers::wire_hex::option_vec\")]\n    pub networks: Option<Vec<ChainHash>>,"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    #[serde(with = \"serde_helpers::short_channel_id\")]\n    pub scid: ShortChannelId,"),
+            "{code}"
+        );
+
+        let main = r#"
+            use crate::core::{FromWire, ToWire};
+            use crate::generated::{Init, InitTlvs, InitTlvsNode};
+            use crate::prelude::BitFlag;
+
+            fn main() {
+                let init = Init {
+                    ty: 16,
+                    globalfeatures: BitFlag(vec![]),
+                    features: BitFlag(vec![0x02, 0x0a]),
+                    init_tlvs: InitTlvs {
+                        networks: Some(vec![[0x6f; 32]]),
+                        remote_addr: Some(vec![0x7f, 0x01]),
+                        node: Some(InitTlvsNode {
+                            node_id: [0x02; 33],
+                            scid: [0, 0, 1, 0, 0, 2, 0, 3],
+                        }),
+                    },
+                };
+                let json = serde_json::to_string(&init).unwrap();
+                println!("{json}");
+                let decoded: Init = serde_json::from_str(&json).unwrap();
+                let mut wire = Vec::new();
+                init.to_wire(&mut wire).unwrap();
+                let mut decoded_wire = Vec::new();
+                decoded.to_wire(&mut decoded_wire).unwrap();
+                assert_eq!(wire, decoded_wire);
+                let decoded = Init::from_wire(&mut wire.as_slice()).unwrap();
+                assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+            }
+        "#;
+        let json = run_rust("serde_tlv_json_round_trip", &code, main);
+        let networks = format!("\"networks\":[\"{}\"]", "6f".repeat(32));
+        assert!(json.contains(&networks), "{json}");
+        assert!(json.contains("\"remote_addr\":\"7f01\""), "{json}");
+        let node = format!(
+            "\"node\":{{\"node_id\":\"{}\",\"scid\":\"1x2x3\"}}",
+            "02".repeat(33)
+        );
+        assert!(json.contains(&node), "{json}");
+        assert!(json.contains("\"features\":\"020a\""), "{json}");
+    }
+
+    #[test]
+    fn rust_msg_doc() {
+        init();
+        let contents = "msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16,\n";
+        let spec = "### The `ping` and `pong` Messages\n\n\
+                    In order to allow for the existence of long-lived TCP connections, \
+                    a `ping` message is sent.\n\n\
+                    1. type: 18 (`ping`)\n\
+                    2. data:\n   \
+                    * [`u16`:`num_pong_bytes`]\n";
+        let mut symbol_table = parse(contents);
+        SpecDocs::parse(spec, "01-messaging.md").attach(&mut symbol_table);
+        let mut backend = RustCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("/// In order to allow for the existence of long-lived TCP connections, a `ping` message is sent.\n///\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("/// Message type `18`, defined in [BOLT #1: The `ping` and `pong` Messages](https://github.com/lightning/bolts/blob/master/01-messaging.md#the-ping-and-pong-messages).\n#[derive("),
+            "{code}"
+        );
+    }
+
+    #[test]
+    fn rust_tlv_subtype_entries() {
+        init();
+        let contents = "subtype,checksums\n\
+                        subtypedata,checksums,checksum_1,u32,\n\
+                        subtypedata,checksums,checksum_2,u32,\n\
+                        subtype,witness\n\
+                        subtypedata,witness,len,u16,\n\
+                        subtypedata,witness,witness_data,byte,len\n\
+                        tlvtype,foo_tlvs,checksums_tlv,1\n\
+                        tlvdata,foo_tlvs,checksums_tlv,checksums,checksums,\n\
+                        tlvtype,foo_tlvs,witness_tlv,3\n\
+                        tlvdata,foo_tlvs,witness_tlv,witness,witness,\n\
+                        tlvtype,foo_tlvs,flags_tlv,5\n\
+                        tlvdata,foo_tlvs,flags_tlv,flags,u8,\n";
+        let symbol_table = parse(contents);
+        let mut backend = RustCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("pub struct Checksums {"), "{code}");
+        assert!(!code.contains("pub struct Witness {"), "{code}");
+        assert!(
+            code.contains("    pub checksums_tlv: Option<Checksums>,"),
+            "{code}"
+        );
+        // the witness has not a fixed size, so it is kept as raw bytes
+        assert!(
+            code.contains("    pub witness_tlv: Option<Vec<u8>>,"),
+            "{code}"
+        );
+        assert!(code.contains("    pub flags_tlv: Option<u8>,"), "{code}");
+        let main = r#"
+            use crate::core::{FromWire, ToWire};
+            use crate::generated::{Checksums, FooTlvs};
+
+            fn main() {
+                let tlvs = FooTlvs {
+                    checksums_tlv: Some(Checksums { checksum_1: 1, checksum_2: 2 }),
+                    witness_tlv: Some(vec![0, 1, 7]),
+                    flags_tlv: Some(9),
+                    ..Default::default()
+                };
+                let mut buf = Vec::new();
+                tlvs.to_wire(&mut buf).unwrap();
+                let hex: String = buf.iter().map(|b| format!("{b:02x}")).collect();
+                println!("{hex}");
+                assert_eq!(FooTlvs::from_wire(&mut &buf[..]).unwrap(), tlvs);
+            }
+        "#;
+        assert_eq!(
+            run_rust("tlv_subtype_entries", &code, main),
+            "010800000001000000020303000107050109\n"
+        );
+    }
+
+    #[test]
+    fn rust_compile_specs() {
+        init();
+        let path = std::env::var_os("CSV_PATH").unwrap();
+        let mut files = std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+            .collect::<Vec<_>>();
+        files.sort();
+        assert!(!files.is_empty());
+        let options = RustCodeGenOptions {
+            zero_copy: true,
+            serde: true,
+            builder: true,
+        };
+        for file in files {
+            let contents = std::fs::read_to_string(&file).unwrap();
+            let symbol_table = parse(&contents);
+            let mut backend = RustCodeGen::with_options(&symbol_table, options.clone());
+            if let Err(err) = backend.generate(&symbol_table) {
+                // the witnesses of bolt 2 have not a fixed size, so there is no view
+                assert!(
+                    matches!(err, CodeGenError::UnsupportedArray { .. }),
+                    "{err}"
+                );
+                let options = RustCodeGenOptions {
+                    zero_copy: false,
+                    ..options.clone()
+                };
+                backend = RustCodeGen::with_options(&symbol_table, options);
+                backend.generate(&symbol_table).unwrap();
+            }
+            let name = file.file_stem().unwrap().to_string_lossy();
+            run_rust(
+                &format!("specs_{name}"),
+                &backend.to_string(),
+                "fn main() {}",
+            );
+        }
+    }
+}
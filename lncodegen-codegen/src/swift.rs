@@ -0,0 +1,785 @@
+//! Swift code generation for the lightning
+//! network specification.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated structs to read and write
+/// the lightning network types.
+const SWIFT_RUNTIME: &str = indoc! {r#"
+    public enum WireError: Swift.Error {
+        case unexpectedEnd
+        case invalidLength(expected: Int, got: Int)
+        case nonMinimalBigSize
+        case nonMinimalTruncated
+        case tlvNotIncreasing
+        case unknownEvenTlv(UInt64)
+        case unexpectedType(UInt16)
+        case unknownType(UInt16)
+    }
+
+    /// A type that can be written to and read from the lightning wire format.
+    public protocol LNWireCodable {
+        init(from reader: inout WireReader) throws
+        func encode(to writer: inout WireWriter) throws
+    }
+
+    /// A lightning message, that is prefixed by its type on the wire.
+    public protocol LNMessage: LNWireCodable {
+        static var msgType: UInt16 { get }
+    }
+
+    extension LNMessage {
+        /// Encode the message, including the type.
+        public func encode() throws -> Data {
+            var writer = WireWriter()
+            writer.u16(Self.msgType)
+            try encode(to: &writer)
+            return writer.data
+        }
+
+        /// Decode the message, including the type.
+        public init(data: Data) throws {
+            var reader = WireReader(data)
+            let type = try reader.u16()
+            guard type == Self.msgType else { throw WireError.unexpectedType(type) }
+            try self.init(from: &reader)
+        }
+    }
+
+    public struct WireWriter {
+        public private(set) var data = Data()
+
+        public init() {}
+
+        public mutating func bytes(_ value: Data) {
+            data.append(value)
+        }
+
+        public mutating func fixed(_ value: Data, _ size: Int) throws {
+            guard value.count == size else { throw WireError.invalidLength(expected: size, got: value.count) }
+            data.append(value)
+        }
+
+        /// Write the elements of `elemSize` bytes prefixed by their u16 count.
+        public mutating func varBytes(_ value: Data, elemSize: Int = 1) throws {
+            let count = value.count / elemSize
+            guard count * elemSize == value.count else {
+                throw WireError.invalidLength(expected: count * elemSize, got: value.count)
+            }
+            guard count <= 0xFFFF else { throw WireError.invalidLength(expected: 0xFFFF * elemSize, got: value.count) }
+            u16(UInt16(count))
+            data.append(value)
+        }
+
+        public mutating func u8(_ value: UInt8) {
+            data.append(value)
+        }
+
+        public mutating func u16(_ value: UInt16) {
+            withUnsafeBytes(of: value.bigEndian) { data.append(contentsOf: $0) }
+        }
+
+        public mutating func u32(_ value: UInt32) {
+            withUnsafeBytes(of: value.bigEndian) { data.append(contentsOf: $0) }
+        }
+
+        public mutating func u64(_ value: UInt64) {
+            withUnsafeBytes(of: value.bigEndian) { data.append(contentsOf: $0) }
+        }
+
+        public mutating func bigsize(_ value: UInt64) {
+            switch value {
+            case 0..<0xFD:
+                u8(UInt8(value))
+            case 0xFD...0xFFFF:
+                u8(0xFD)
+                u16(UInt16(value))
+            case 0x10000...0xFFFF_FFFF:
+                u8(0xFE)
+                u32(UInt32(value))
+            default:
+                u8(0xFF)
+                u64(value)
+            }
+        }
+    }
+
+    public struct WireReader {
+        private let data: Data
+        private var pos: Int
+
+        public init(_ data: Data) {
+            self.data = data
+            self.pos = data.startIndex
+        }
+
+        public var remaining: Int { data.endIndex - pos }
+
+        public mutating func bytes(_ size: Int) throws -> Data {
+            guard size >= 0, size <= remaining else { throw WireError.unexpectedEnd }
+            let value = data.subdata(in: pos..<pos + size)
+            pos += size
+            return value
+        }
+
+        public mutating func rest() throws -> Data {
+            try bytes(remaining)
+        }
+
+        /// Read the elements of `elemSize` bytes prefixed by their u16 count.
+        public mutating func varBytes(elemSize: Int = 1) throws -> Data {
+            try bytes(Int(try u16()) * elemSize)
+        }
+
+        private mutating func uint<T: FixedWidthInteger & UnsignedInteger>(_: T.Type) throws -> T {
+            try bytes(MemoryLayout<T>.size).reduce(0) { $0 << 8 | T($1) }
+        }
+
+        public mutating func u8() throws -> UInt8 { try uint(UInt8.self) }
+
+        public mutating func u16() throws -> UInt16 { try uint(UInt16.self) }
+
+        public mutating func u32() throws -> UInt32 { try uint(UInt32.self) }
+
+        public mutating func u64() throws -> UInt64 { try uint(UInt64.self) }
+
+        public mutating func bigsize() throws -> UInt64 {
+            let value: UInt64
+            let min: UInt64
+            switch try u8() {
+            case 0xFD:
+                value = UInt64(try u16())
+                min = 0xFD
+            case 0xFE:
+                value = UInt64(try u32())
+                min = 0x10000
+            case 0xFF:
+                value = try u64()
+                min = 0x1_0000_0000
+            case let prefix:
+                return UInt64(prefix)
+            }
+            guard value >= min else { throw WireError.nonMinimalBigSize }
+            return value
+        }
+    }
+
+    /// Decode a truncated integer (tu16, tu32, tu64) of at most `size` bytes.
+    public func decodeTruncated(_ value: Data, _ size: Int) throws -> UInt64 {
+        guard value.count <= size else { throw WireError.invalidLength(expected: size, got: value.count) }
+        guard value.first != 0 else { throw WireError.nonMinimalTruncated }
+        return value.reduce(0) { $0 << 8 | UInt64($1) }
+    }
+
+    public func encodeTruncated(_ value: UInt64) -> Data {
+        var writer = WireWriter()
+        writer.u64(value)
+        return Data(writer.data.drop(while: { $0 == 0 }))
+    }
+
+    /// Read the tlv records till the end of the message.
+    public func readTlvStream(_ reader: inout WireReader) throws -> [(UInt64, Data)] {
+        var records: [(UInt64, Data)] = []
+        while reader.remaining > 0 {
+            let type = try reader.bigsize()
+            if let last = records.last, type <= last.0 { throw WireError.tlvNotIncreasing }
+            let len = try reader.bigsize()
+            guard len <= UInt64(reader.remaining) else { throw WireError.unexpectedEnd }
+            records.append((type, try reader.bytes(Int(len))))
+        }
+        return records
+    }
+
+    /// Write the tlv records ordered by type.
+    public func writeTlvStream(_ writer: inout WireWriter, _ records: [UInt64: Data]) {
+        for (type, value) in records.sorted(by: { $0.key < $1.key }) {
+            writer.bigsize(type)
+            writer.bigsize(UInt64(value.count))
+            writer.bytes(value)
+        }
+    }
+"#};
+
+/// Swift keywords, that need to be escaped with backticks.
+const SWIFT_KEYWORDS: [&str; 44] = [
+    "associatedtype",
+    "class",
+    "deinit",
+    "enum",
+    "extension",
+    "fileprivate",
+    "func",
+    "import",
+    "init",
+    "inout",
+    "internal",
+    "let",
+    "open",
+    "operator",
+    "private",
+    "protocol",
+    "public",
+    "rethrows",
+    "static",
+    "struct",
+    "subscript",
+    "typealias",
+    "var",
+    "break",
+    "case",
+    "continue",
+    "default",
+    "defer",
+    "do",
+    "else",
+    "fallthrough",
+    "for",
+    "guard",
+    "if",
+    "in",
+    "repeat",
+    "return",
+    "switch",
+    "where",
+    "while",
+    "as",
+    "is",
+    "nil",
+    "self",
+];
+
+pub struct SwiftCodeGen {
+    curr_msg: Option<LNMsg>,
+    /// The stored properties of the struct, known only
+    /// while we generate the decode function.
+    properties: String,
+    /// The parameters of the memberwise init, with their type
+    /// and default value.
+    init_params: Vec<(String, String, Option<String>)>,
+    encode_fn: String,
+    decode_fn: String,
+    /// The `switch` cases of the dispatcher.
+    msg_cases: String,
+    file_content: String,
+}
+
+impl SwiftCodeGen {
+    fn swift_type_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    fn swift_ident(name: &str) -> String {
+        let name = name.to_case(Case::Camel);
+        if SWIFT_KEYWORDS.contains(&name.as_str()) {
+            return format!("`{name}`");
+        }
+        name
+    }
+
+    fn build_msg_doc(msg: &LNMsg) -> String {
+        let mut code = format!(
+            "/// The `{}` message, type {}.\n",
+            msg.msg_name, msg.msg_typ
+        );
+        if let Some(doc) = &msg.doc {
+            for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+                code += &format!("///\n/// {paragraph}\n");
+            }
+            code += &format!(
+                "///\n/// See [{}: {}]({}).\n",
+                doc.bolt, doc.section, doc.link
+            );
+        }
+        code
+    }
+
+    /// Add a stored property to the struct, and decode it inside the `init(from:)`.
+    fn build_field(&mut self, name: &str, ty: &str, read: &str) {
+        let name = Self::swift_ident(name);
+        self.properties += &format!("    public var {name}: {ty}\n");
+        self.decode_fn += &format!("        {name} = {read}\n");
+        self.init_params.push((name, ty.to_owned(), None));
+    }
+
+    fn write_field(&mut self, write: &str) {
+        self.encode_fn += &format!("        {write}\n");
+    }
+
+    fn build_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        self.build_field(field.name(), "Data", &format!("try reader.bytes({size})"));
+    }
+
+    fn write_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        let name = Self::swift_ident(field.name());
+        self.write_field(&format!("try writer.fixed({name}, {size})"));
+    }
+
+    /// Build the public memberwise init, swift keeps the implicit one internal.
+    fn build_init(&self) -> String {
+        let params = self
+            .init_params
+            .iter()
+            .map(|(name, ty, default)| match default {
+                Some(default) => format!("{name}: {ty} = {default}"),
+                None => format!("{name}: {ty}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut code = format!("    public init({params}) {{\n");
+        for (name, _, _) in &self.init_params {
+            code += &format!("        self.{name} = {name}\n");
+        }
+        code += "    }\n";
+        code
+    }
+
+    /// Build the struct of a tlv stream, the records are optional raw bytes.
+    fn build_tlv_stream_struct(tlv: &LNTlvStream) -> String {
+        let name = Self::swift_type_name(&tlv.stream_name);
+        let mut properties = String::new();
+        let mut params = String::new();
+        let mut assigns = String::new();
+        let mut encode = String::new();
+        let mut cases = String::new();
+        for record in &tlv.records {
+            let field = Self::swift_ident(&record.type_name);
+            let typ = record.type_len;
+            properties +=
+                &format!("    /// Record of type {typ}.\n    public var {field}: Data?\n");
+            params += &format!("{field}: Data? = nil, ");
+            assigns += &format!("        self.{field} = {field}\n");
+            encode += &format!("        if let {field} = {field} {{ records[{typ}] = {field} }}\n");
+            cases += &format!("            case {typ}: {field} = value\n");
+        }
+        format!(
+            indoc! {r#"
+                /// The `{stream}` tlv stream.
+                public struct {name}: LNWireCodable {{
+                {properties}    /// The unknown odd records.
+                    public var unknown: [UInt64: Data]
+
+                    public init({params}unknown: [UInt64: Data] = [:]) {{
+                {assigns}        self.unknown = unknown
+                    }}
+
+                    public init(from reader: inout WireReader) throws {{
+                        self.init()
+                        for (type, value) in try readTlvStream(&reader) {{
+                            switch type {{
+                {cases}            default:
+                                guard type % 2 == 1 else {{ throw WireError.unknownEvenTlv(type) }}
+                                unknown[type] = value
+                            }}
+                        }}
+                    }}
+
+                    public func encode(to writer: inout WireWriter) throws {{
+                        var records = unknown
+                {encode}        writeTlvStream(&writer, records)
+                    }}
+                }}
+
+            "#},
+            stream = tlv.stream_name,
+            name = name,
+            properties = properties,
+            params = params,
+            assigns = assigns,
+            cases = cases,
+            encode = encode,
+        )
+    }
+}
+
+impl<'g> CodeGen<'g> for SwiftCodeGen {
+    fn new(_symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        SwiftCodeGen {
+            curr_msg: None,
+            properties: String::new(),
+            init_params: Vec::new(),
+            encode_fn: String::new(),
+            decode_fn: String::new(),
+            msg_cases: String::new(),
+            file_content: String::new(),
+        }
+    }
+
+    fn raw_arrays(&self) -> bool {
+        true
+    }
+
+    fn pre_generation(&mut self) {
+        self.file_content += "// code generated by the lncodegen.rs please do not edit\n";
+        self.file_content += "import Foundation\n\n";
+        self.file_content += SWIFT_RUNTIME;
+        self.file_content += "\n";
+    }
+
+    fn post_generation(&mut self) {
+        self.file_content += &format!(
+            indoc! {r#"
+                /// Decode the message, including the type, to the right struct.
+                public func decodeMessage(_ data: Data) throws -> any LNMessage {{
+                    var reader = WireReader(data)
+                    let type = try reader.u16()
+                    switch type {{
+                {cases}    default: throw WireError.unknownType(type)
+                    }}
+                }}
+            "#},
+            cases = self.msg_cases
+        );
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        self.file_content += &Self::build_tlv_stream_struct(tlv);
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_msg = Some(msg.to_owned());
+        let name = Self::swift_type_name(&msg.msg_name);
+        self.msg_cases += &format!("    case {name}.msgType: return try {name}(from: &reader)\n");
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let name = Self::swift_type_name(&msg.msg_name);
+        self.file_content += &Self::build_msg_doc(msg);
+        self.file_content += &format!("public struct {name}: LNMessage {{\n");
+        self.file_content += &format!(
+            "    public static let msgType: UInt16 = {}\n\n",
+            msg.msg_typ
+        );
+        if !self.properties.is_empty() {
+            self.file_content += &format!("{}\n", self.properties);
+        }
+        self.file_content += &self.build_init();
+        self.file_content += &format!(
+            "\n    public init(from reader: inout WireReader) throws {{\n{}    }}\n",
+            self.decode_fn
+        );
+        self.file_content += &format!(
+            "\n    public func encode(to writer: inout WireWriter) throws {{\n{}    }}\n",
+            self.encode_fn
+        );
+        self.file_content += "}\n\n";
+        self.properties.clear();
+        self.init_params.clear();
+        self.encode_fn.clear();
+        self.decode_fn.clear();
+        self.curr_msg = None;
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.build_field(name, "UInt16", "try reader.u16()");
+        }
+    }
+
+    fn write_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.write_field(&format!("writer.u16({})", Self::swift_ident(name)));
+        }
+    }
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_field(name, "UInt32", "try reader.u32()");
+        }
+    }
+
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_field(&format!("writer.u32({})", Self::swift_ident(name)));
+        }
+    }
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_field(name, "UInt64", "try reader.u64()");
+        }
+    }
+
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_field(&format!("writer.u64({})", Self::swift_ident(name)));
+        }
+    }
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.build_field(name, "UInt64", "try reader.bigsize()");
+        }
+    }
+
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.write_field(&format!("writer.bigsize({})", Self::swift_ident(name)));
+        }
+    }
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_chain_hash(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_signature(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_point(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_sha256(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let elem = LNMsData::element_size(ty).unwrap();
+            let read = if let Ok(size) = size.parse::<u64>() {
+                format!("try reader.bytes({})", size * elem)
+            } else if size == "..." {
+                "try reader.rest()".to_owned()
+            } else if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let size = Self::swift_ident(size);
+                match elem {
+                    1 => format!("try reader.bytes(Int({size}))"),
+                    _ => format!("try reader.bytes(Int({size}) * {elem})"),
+                }
+            } else {
+                match elem {
+                    1 => "try reader.varBytes()".to_owned(),
+                    _ => format!("try reader.varBytes(elemSize: {elem})"),
+                }
+            };
+            self.build_field(name, "Data", &read);
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let name = Self::swift_ident(name);
+            let elem = LNMsData::element_size(ty).unwrap();
+            if let Ok(size) = size.parse::<u64>() {
+                self.write_field(&format!("try writer.fixed({name}, {})", size * elem));
+            } else if size == "..."
+                || self
+                    .curr_msg
+                    .as_ref()
+                    .is_some_and(|msg| msg.is_length_field(size))
+            {
+                self.write_field(&format!("writer.bytes({name})"));
+            } else if elem == 1 {
+                self.write_field(&format!("try writer.varBytes({name})"));
+            } else {
+                self.write_field(&format!("try writer.varBytes({name}, elemSize: {elem})"));
+            }
+        }
+    }
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = Self::swift_type_name(&field.stream_name);
+        let name = Self::swift_ident(&field.stream_name);
+        self.properties += &format!("    public var {name}: {ty}\n");
+        self.decode_fn += &format!("        {name} = try {ty}(from: &reader)\n");
+        self.init_params
+            .push((name, ty.clone(), Some(format!("{ty}()"))));
+    }
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = Self::swift_ident(&field.stream_name);
+        self.write_field(&format!("try {name}.encode(to: &writer)"));
+    }
+}
+
+impl Display for SwiftCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::codegen::CodeGen;
+    use crate::swift::SwiftCodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+
+    const INIT_CSV: &str = "msgtype,init,16\n\
+                           msgdata,init,gflen,u16,\n\
+                           msgdata,init,globalfeatures,byte,gflen\n\
+                           msgdata,init,htlc_maximum_msat,u64,\n\
+                           msgdata,init,tlvs,init_tlvs,\n\
+                           tlvtype,init_tlvs,networks,1\n\
+                           tlvdata,init_tlvs,networks,chains,chain_hash,...\n";
+
+    #[test]
+    fn swift_init_msg() {
+        init();
+        let symbol_table = parse(INIT_CSV);
+        let mut backend = SwiftCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(code.contains("public protocol LNWireCodable {"), "{code}");
+        assert!(
+            code.contains(
+                "public struct Init: LNMessage {\n    public static let msgType: UInt16 = 16\n"
+            ),
+            "{code}"
+        );
+        assert!(
+            code.contains("    public var htlcMaximumMsat: UInt64\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    public init(globalfeatures: Data, htlcMaximumMsat: UInt64, initTlvs: InitTlvs = InitTlvs()) {\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("        globalfeatures = try reader.varBytes()\n"),
+            "{code}"
+        );
+        assert!(code.contains("    public var networks: Data?\n"), "{code}");
+        assert!(
+            code.contains("    case Init.msgType: return try Init(from: &reader)\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires swiftc, run by the toolchains job of the CI"]
+    fn swift_init_msg_typecheck() {
+        init();
+        let symbol_table = parse(INIT_CSV);
+        let mut backend = SwiftCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("swift_init_msg");
+        fs::write(dir.join("Lnwire.swift"), backend.to_string()).unwrap();
+        run_cmd(
+            Command::new("swiftc")
+                .args(["-typecheck", "Lnwire.swift"])
+                .current_dir(&dir),
+        );
+    }
+
+    const SIGNATURES_CSV: &str = "msgtype,commitment_signed,132\n\
+                                  msgdata,commitment_signed,num_htlcs,u16,\n\
+                                  msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                                  msgtype,points,1\n\
+                                  msgdata,points,num_points,u16,\n\
+                                  msgdata,points,flags,u16,\n\
+                                  msgdata,points,points,point,num_points\n";
+
+    #[test]
+    fn swift_array_elements() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = SwiftCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains("        htlcSignature = try reader.varBytes(elemSize: 64)\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("try writer.varBytes(htlcSignature, elemSize: 64)"),
+            "{code}"
+        );
+        assert!(
+            code.contains("        points = try reader.bytes(Int(numPoints) * 33)\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires swiftc, run by the toolchains job of the CI"]
+    fn swift_array_elements_round_trip() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = SwiftCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("swift_array_elements");
+        fs::write(dir.join("Lnwire.swift"), backend.to_string()).unwrap();
+        let main = indoc! {r#"
+            import Foundation
+
+            let msg = CommitmentSigned(htlcSignature: Data(repeating: 0x5a, count: 128))
+            let wire = try msg.encode()
+            precondition(wire.count == 2 + 2 + 128 && wire[3] == 2, "unexpected wire")
+            let decoded = try CommitmentSigned(data: wire)
+            precondition(decoded.htlcSignature == msg.htlcSignature)
+            do {
+                _ = try CommitmentSigned(htlcSignature: Data(count: 127)).encode()
+                fatalError("partial signature accepted")
+            } catch is WireError {
+            }
+            print("ok")
+        "#};
+        fs::write(dir.join("main.swift"), main).unwrap();
+        run_cmd(
+            Command::new("swiftc")
+                .args(["-o", "main", "main.swift", "Lnwire.swift"])
+                .current_dir(&dir),
+        );
+        let stdout = run_cmd(&mut Command::new(dir.join("main")));
+        assert_eq!(stdout, "ok\n");
+    }
+}
@@ -0,0 +1,96 @@
+//! Helpers shared by the tests of the backends, to parse
+//! the csv content and to run the generated code.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+use csvlang::parser::ast::LNMsgType;
+use csvlang::parser::parser::Parser;
+use csvlang::scanner::scanner::Scanner;
+
+static INIT: Once = Once::new();
+
+pub(crate) fn init() {
+    // ignore error
+    INIT.call_once(|| {
+        env_logger::init();
+    });
+}
+
+pub(crate) fn parse(contents: &str) -> BTreeMap<String, LNMsgType> {
+    let mut scanner = Scanner::new();
+    let lines = scanner.scan_lines(contents);
+    let mut parser = Parser::new();
+    parser.parse_lines(&lines).unwrap();
+    parser.symbol_table
+}
+
+/// Return the content of a file of the specs directory,
+/// that it is found with the `CSV_PATH` variable.
+pub(crate) fn read_spec(name: &str) -> String {
+    let path = std::env::var_os("CSV_PATH").unwrap();
+    fs::read_to_string(Path::new(&path).join(name)).unwrap()
+}
+
+/// Copy the directory `from` inside `to`, with all its content.
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let to = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &to);
+        } else {
+            fs::copy(&path, &to).unwrap();
+        }
+    }
+}
+
+/// Return an empty directory inside the target directory
+/// where a test can write the generated code.
+pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../target/codegen-tests")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the command and check that it succeeds, return the stdout.
+pub(crate) fn run_cmd(cmd: &mut Command) -> String {
+    let output = cmd.output().unwrap_or_else(|err| panic!("{cmd:?}: {err}"));
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{cmd:?}\n{stderr}\n{stdout}");
+    stdout
+}
+
+/// Compile the generated rust code with the runtime that mocks the
+/// `fundamentals` crate and run the `main` provided, the program
+/// has the generated code in the `generated` module.
+///
+/// Return the stdout of the program.
+pub(crate) fn run_rust(name: &str, code: &str, main: &str) -> String {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = manifest_dir.join("../target/rust-runtime");
+    let project = target_dir.join(name);
+    copy_dir(&manifest_dir.join("tests/rust-runtime"), &project);
+    let main = format!(
+        "mod core;\nmod prelude;\n#[allow(dead_code, unused_imports)]\nmod generated;\n\n{main}"
+    );
+    fs::write(project.join("src/main.rs"), main).unwrap();
+    fs::write(project.join("src/generated.rs"), code).unwrap();
+    let output = Command::new(std::env::var("CARGO").unwrap_or("cargo".to_owned()))
+        .args(["run", "--quiet", "--manifest-path"])
+        .arg(project.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", target_dir.join("target"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}\n{stdout}\n{code}");
+    stdout
+}
@@ -0,0 +1,779 @@
+//! TypeScript code generation for the lightning
+//! network specification.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated functions to read and write
+/// the lightning network types.
+const TS_RUNTIME: &str = indoc! {r#"
+    export class WireError extends Error {}
+
+    export type TlvRecord = [bigint, Uint8Array];
+
+    export class Writer {
+      private buf = new Uint8Array(64);
+      private len = 0;
+
+      private reserve(size: number): DataView {
+        if (this.len + size > this.buf.length) {
+          const buf = new Uint8Array(Math.max(this.buf.length * 2, this.len + size));
+          buf.set(this.buf.subarray(0, this.len));
+          this.buf = buf;
+        }
+        const view = new DataView(this.buf.buffer, this.len, size);
+        this.len += size;
+        return view;
+      }
+
+      private checkRange(value: number, max: number): void {
+        if (!Number.isInteger(value) || value < 0 || value > max) {
+          throw new WireError(`${value} is out of range`);
+        }
+      }
+
+      bytes(value: Uint8Array): void {
+        const len = this.len;
+        this.reserve(value.length);
+        this.buf.set(value, len);
+      }
+
+      /** Write the elements of `elemSize` bytes prefixed by their u16 count. */
+      elements(value: Uint8Array, elemSize: number): void {
+        if (value.length % elemSize !== 0) {
+          throw new WireError(`${value.length} bytes are not elements of ${elemSize} bytes`);
+        }
+        this.u16(value.length / elemSize);
+        this.bytes(value);
+      }
+
+      fixed(value: Uint8Array, size: number): void {
+        if (value.length !== size) {
+          throw new WireError(`expected ${size} bytes, got ${value.length}`);
+        }
+        this.bytes(value);
+      }
+
+      u8(value: number): void {
+        this.checkRange(value, 0xff);
+        this.reserve(1).setUint8(0, value);
+      }
+
+      u16(value: number): void {
+        this.checkRange(value, 0xffff);
+        this.reserve(2).setUint16(0, value);
+      }
+
+      u32(value: number): void {
+        this.checkRange(value, 0xffffffff);
+        this.reserve(4).setUint32(0, value);
+      }
+
+      u64(value: bigint): void {
+        if (value < 0n || value > 0xffffffffffffffffn) {
+          throw new WireError(`${value} is out of range`);
+        }
+        this.reserve(8).setBigUint64(0, value);
+      }
+
+      bigsize(value: bigint): void {
+        if (value < 0xfdn) {
+          this.u8(Number(value));
+        } else if (value <= 0xffffn) {
+          this.u8(0xfd);
+          this.u16(Number(value));
+        } else if (value <= 0xffffffffn) {
+          this.u8(0xfe);
+          this.u32(Number(value));
+        } else {
+          this.u8(0xff);
+          this.u64(value);
+        }
+      }
+
+      finish(): Uint8Array {
+        return this.buf.slice(0, this.len);
+      }
+    }
+
+    export class Reader {
+      private pos = 0;
+      private readonly view: DataView;
+
+      constructor(private readonly buf: Uint8Array) {
+        this.view = new DataView(buf.buffer, buf.byteOffset, buf.byteLength);
+      }
+
+      remaining(): number {
+        return this.buf.length - this.pos;
+      }
+
+      private advance(size: number): number {
+        if (size > this.remaining()) {
+          throw new WireError("unexpected end of message");
+        }
+        const pos = this.pos;
+        this.pos += size;
+        return pos;
+      }
+
+      bytes(size: number): Uint8Array {
+        const pos = this.advance(size);
+        return this.buf.slice(pos, pos + size);
+      }
+
+      rest(): Uint8Array {
+        return this.bytes(this.remaining());
+      }
+
+      u8(): number {
+        return this.view.getUint8(this.advance(1));
+      }
+
+      u16(): number {
+        return this.view.getUint16(this.advance(2));
+      }
+
+      u32(): number {
+        return this.view.getUint32(this.advance(4));
+      }
+
+      u64(): bigint {
+        return this.view.getBigUint64(this.advance(8));
+      }
+
+      bigsize(): bigint {
+        const prefix = this.u8();
+        let value: bigint;
+        let min: bigint;
+        if (prefix === 0xfd) {
+          value = BigInt(this.u16());
+          min = 0xfdn;
+        } else if (prefix === 0xfe) {
+          value = BigInt(this.u32());
+          min = 0x10000n;
+        } else if (prefix === 0xff) {
+          value = this.u64();
+          min = 0x100000000n;
+        } else {
+          return BigInt(prefix);
+        }
+        if (value < min) {
+          throw new WireError("bigsize is not minimally encoded");
+        }
+        return value;
+      }
+    }
+
+    /** Decode a truncated integer (tu16, tu32, tu64) of at most `size` bytes. */
+    export function decodeTruncated(value: Uint8Array, size: number): bigint {
+      if (value.length > size) {
+        throw new WireError(`truncated integer longer than ${size} bytes`);
+      }
+      if (value.length > 0 && value[0] === 0) {
+        throw new WireError("truncated integer is not minimally encoded");
+      }
+      return value.reduce((acc, byte) => (acc << 8n) | BigInt(byte), 0n);
+    }
+
+    export function encodeTruncated(value: bigint): Uint8Array {
+      const bytes: number[] = [];
+      for (let rest = value; rest > 0n; rest >>= 8n) {
+        bytes.unshift(Number(rest & 0xffn));
+      }
+      return Uint8Array.from(bytes);
+    }
+
+    /** Read the tlv records till the end of the message. */
+    export function readTlvStream(reader: Reader): TlvRecord[] {
+      const records: TlvRecord[] = [];
+      while (reader.remaining() > 0) {
+        const type = reader.bigsize();
+        if (records.length > 0 && type <= records[records.length - 1][0]) {
+          throw new WireError("tlv types are not strictly increasing");
+        }
+        const len = reader.bigsize();
+        if (len > BigInt(reader.remaining())) {
+          throw new WireError("unexpected end of message");
+        }
+        records.push([type, reader.bytes(Number(len))]);
+      }
+      return records;
+    }
+
+    /** Write the tlv records ordered by type. */
+    export function writeTlvStream(writer: Writer, records: TlvRecord[]): void {
+      const sorted = [...records].sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+      for (const [type, value] of sorted) {
+        writer.bigsize(type);
+        writer.bigsize(BigInt(value.length));
+        writer.bytes(value);
+      }
+    }
+"#};
+
+/// Names that can not be used for the local variables.
+const TS_RESERVED: [&str; 22] = [
+    "break", "case", "catch", "class", "const", "default", "delete", "do", "else", "enum",
+    "export", "for", "function", "in", "new", "return", "switch", "var", "reader", "writer",
+    "payload", "msg",
+];
+
+pub struct TypeScriptCodeGen {
+    curr_msg: Option<LNMsg>,
+    /// The members of the TypeScript interface of the current message.
+    interface_fields: String,
+    encode_fn: String,
+    decode_fn: String,
+    /// The members of the `Message` union and the `case` of the dispatcher.
+    msg_union: String,
+    msg_cases: String,
+    file_content: String,
+}
+
+impl TypeScriptCodeGen {
+    fn ts_type_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    /// Messages have the `Msg` suffix, so `error` does not shadow the `Error` type.
+    fn ts_msg_name(msg: &LNMsg) -> String {
+        format!("{}Msg", Self::ts_type_name(&msg.msg_name))
+    }
+
+    fn ts_ident(name: &str) -> String {
+        let name = name.to_case(Case::Camel);
+        if TS_RESERVED.contains(&name.as_str()) {
+            return format!("{name}_");
+        }
+        name
+    }
+
+    fn build_msg_doc(msg: &LNMsg) -> String {
+        let mut code = format!(
+            "/**\n * The `{}` message, type {}.\n",
+            msg.msg_name, msg.msg_typ
+        );
+        if let Some(doc) = &msg.doc {
+            for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+                code += &format!(" *\n * {paragraph}\n");
+            }
+            code += &format!(
+                " *\n * @see {{@link {} {}: {}}}\n",
+                doc.link, doc.bolt, doc.section
+            );
+        }
+        code += " */\n";
+        code
+    }
+
+    /// Add a field to the interface, and decode it inside a local variable.
+    fn build_field(&mut self, name: &str, ty: &str, read: &str) {
+        let name = Self::ts_ident(name);
+        self.interface_fields += &format!("  {name}: {ty};\n");
+        self.decode_fn += &format!("  const {name} = {read};\n");
+    }
+
+    fn write_field(&mut self, write: &str) {
+        self.encode_fn += &format!("  writer.{write};\n");
+    }
+
+    fn build_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        self.build_field(field.name(), "Uint8Array", &format!("reader.bytes({size})"));
+    }
+
+    fn write_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap();
+        let name = Self::ts_ident(field.name());
+        self.write_field(&format!("fixed(msg.{name}, {size})"));
+    }
+
+    /// Build the interface of a tlv stream, with an optional `Uint8Array`
+    /// for each record, and the functions to encode and decode it.
+    fn build_tlv_stream_decl(tlv: &LNTlvStream) -> String {
+        let name = Self::ts_type_name(&tlv.stream_name);
+        let mut fields = String::new();
+        let mut encode = String::new();
+        let mut decode = String::new();
+        for record in &tlv.records {
+            let field = Self::ts_ident(&record.type_name);
+            let typ = record.type_len;
+            fields += &format!("  /** Record of type {typ}. */\n  {field}?: Uint8Array;\n");
+            encode += &format!(
+                "  if (tlvs.{field} !== undefined) {{\n    records.push([{typ}n, tlvs.{field}]);\n  }}\n"
+            );
+            decode +=
+                &format!("      case {typ}n:\n        tlvs.{field} = value;\n        break;\n");
+        }
+        format!(
+            indoc! {"
+                /** The `{stream}` tlv stream. */
+                export interface {name} {{
+                {fields}  /** The unknown odd records. */
+                  unknown?: TlvRecord[];
+                }}
+
+                export function encode{name}(writer: Writer, tlvs: {name}): void {{
+                  const records: TlvRecord[] = [...(tlvs.unknown ?? [])];
+                {encode}  writeTlvStream(writer, records);
+                }}
+
+                export function decode{name}(reader: Reader): {name} {{
+                  const tlvs: {name} = {{}};
+                  for (const [type, value] of readTlvStream(reader)) {{
+                    switch (type) {{
+                {decode}      default:
+                        if (type % 2n === 0n) {{
+                          throw new WireError(`unknown even tlv type ${{type}}`);
+                        }}
+                        if (tlvs.unknown === undefined) {{
+                          tlvs.unknown = [];
+                        }}
+                        tlvs.unknown.push([type, value]);
+                    }}
+                  }}
+                  return tlvs;
+                }}
+
+            "},
+            stream = tlv.stream_name,
+            name = name,
+            fields = fields,
+            encode = encode,
+            decode = decode,
+        )
+    }
+}
+
+impl<'g> CodeGen<'g> for TypeScriptCodeGen {
+    fn new(_symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        TypeScriptCodeGen {
+            curr_msg: None,
+            interface_fields: String::new(),
+            encode_fn: String::new(),
+            decode_fn: String::new(),
+            msg_union: String::new(),
+            msg_cases: String::new(),
+            file_content: String::new(),
+        }
+    }
+
+    fn raw_arrays(&self) -> bool {
+        true
+    }
+
+    fn pre_generation(&mut self) {
+        self.file_content += "// code generated by the lncodegen.rs please do not edit\n\n";
+        self.file_content += TS_RUNTIME;
+        self.file_content += "\n";
+    }
+
+    fn post_generation(&mut self) {
+        self.file_content += &format!("export type Message =\n{};\n\n", self.msg_union);
+        self.file_content += &format!(
+            indoc! {"
+                /** Decode the payload, including the type, to the right message. */
+                export function decodeMessage(payload: Uint8Array): Message {{
+                  const type = new Reader(payload).u16();
+                  switch (type) {{
+                {cases}    default:
+                      throw new WireError(`unknown message type ${{type}}`);
+                  }}
+                }}
+            "},
+            cases = self.msg_cases
+        );
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        self.file_content += &Self::build_tlv_stream_decl(tlv);
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_msg = Some(msg.to_owned());
+        let name = Self::ts_msg_name(msg);
+        let typ = msg.msg_typ;
+        self.msg_union += &format!(
+            "  | {{ type: {typ}; name: \"{}\"; value: {name} }}\n",
+            msg.msg_name
+        );
+        self.msg_cases += &format!(
+            "    case {typ}:\n      return {{ type: {typ}, name: \"{}\", value: decode{name}(payload) }};\n",
+            msg.msg_name
+        );
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let name = Self::ts_msg_name(msg);
+        self.file_content += &Self::build_msg_doc(msg);
+        self.file_content += &format!(
+            "export interface {name} {{\n{}}}\n\n",
+            self.interface_fields
+        );
+        self.file_content += &format!(
+            "export function encode{name}(msg: {name}): Uint8Array {{\n{}}}\n\n",
+            self.encode_fn
+        );
+        self.file_content += &format!(
+            "export function decode{name}(payload: Uint8Array): {name} {{\n{}}}\n\n",
+            self.decode_fn
+        );
+        self.interface_fields.clear();
+        self.encode_fn.clear();
+        self.decode_fn.clear();
+        self.curr_msg = None;
+    }
+
+    fn build_decode_fun(&mut self) {
+        let msg = self.curr_msg.as_ref().unwrap();
+        self.decode_fn += "  const reader = new Reader(payload);\n";
+        self.decode_fn += &format!(
+            "  if (reader.u16() !== {}) {{\n    throw new WireError(\"unexpected message type\");\n  }}\n",
+            msg.msg_typ
+        );
+    }
+
+    fn end_decode_fn(&mut self) {
+        let msg = self.curr_msg.as_ref().unwrap();
+        let fields = msg
+            .msg_data
+            .iter()
+            .map(|field| match field {
+                LNMsData::TLVinit(stream, _) => Self::ts_ident(stream),
+                _ => Self::ts_ident(field.name()),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.decode_fn += &format!("  return {{ {fields} }};\n");
+    }
+
+    fn build_encode_fn(&mut self) {
+        let msg = self.curr_msg.as_ref().unwrap();
+        self.encode_fn += "  const writer = new Writer();\n";
+        self.encode_fn += &format!("  writer.u16({});\n", msg.msg_typ);
+    }
+
+    fn end_encode_fn(&mut self) {
+        self.encode_fn += "  return writer.finish();\n";
+    }
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.build_field(name, "number", "reader.u16()");
+        }
+    }
+
+    fn write_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.write_field(&format!("u16(msg.{})", Self::ts_ident(name)));
+        }
+    }
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_field(name, "number", "reader.u32()");
+        }
+    }
+
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_field(&format!("u32(msg.{})", Self::ts_ident(name)));
+        }
+    }
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_field(name, "bigint", "reader.u64()");
+        }
+    }
+
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_field(&format!("u64(msg.{})", Self::ts_ident(name)));
+        }
+    }
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.build_field(name, "bigint", "reader.bigsize()");
+        }
+    }
+
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            self.write_field(&format!("bigsize(msg.{})", Self::ts_ident(name)));
+        }
+    }
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_chain_hash(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_signature(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_point(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.build_fixed(field);
+    }
+
+    fn write_sha256(&mut self, field: &LNMsData) {
+        self.write_fixed(field);
+    }
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let elem = LNMsData::element_size(ty).unwrap();
+            let scale = |count: String| match elem {
+                1 => count,
+                _ => format!("{count} * {elem}"),
+            };
+            let read = if let Ok(size) = size.parse::<u64>() {
+                format!("reader.bytes({})", size * elem)
+            } else if size == "..." {
+                "reader.rest()".to_owned()
+            } else if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                format!("reader.bytes({})", scale(Self::ts_ident(size)))
+            } else {
+                format!("reader.bytes({})", scale("reader.u16()".to_owned()))
+            };
+            self.build_field(name, "Uint8Array", &read);
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let name = Self::ts_ident(name);
+            let elem = LNMsData::element_size(ty).unwrap();
+            if let Ok(size) = size.parse::<u64>() {
+                self.write_field(&format!("fixed(msg.{name}, {})", size * elem));
+            } else if size == "..."
+                || self
+                    .curr_msg
+                    .as_ref()
+                    .is_some_and(|msg| msg.is_length_field(size))
+            {
+                self.write_field(&format!("bytes(msg.{name})"));
+            } else if elem == 1 {
+                self.write_field(&format!("u16(msg.{name}.length)"));
+                self.write_field(&format!("bytes(msg.{name})"));
+            } else {
+                self.write_field(&format!("elements(msg.{name}, {elem})"));
+            }
+        }
+    }
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = Self::ts_type_name(&field.stream_name);
+        self.build_field(&field.stream_name, &ty, &format!("decode{ty}(reader)"));
+    }
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = Self::ts_type_name(&field.stream_name);
+        let name = Self::ts_ident(&field.stream_name);
+        self.encode_fn += &format!("  encode{ty}(writer, msg.{name});\n");
+    }
+}
+
+impl Display for TypeScriptCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::codegen::CodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+    use crate::typescript::TypeScriptCodeGen;
+
+    const CHANNEL_UPDATE_CSV: &str = "msgtype,channel_update,258\n\
+                                     msgdata,channel_update,chain_hash,chain_hash,\n\
+                                     msgdata,channel_update,timestamp,u32,\n\
+                                     msgdata,channel_update,htlc_maximum_msat,u64,\n\
+                                     msgtype,ping,18\n\
+                                     msgdata,ping,num_pong_bytes,u16,\n\
+                                     msgdata,ping,byteslen,u16,\n\
+                                     msgdata,ping,ignored,byte,byteslen\n";
+
+    #[test]
+    fn typescript_channel_update_msg() {
+        init();
+        let symbol_table = parse(CHANNEL_UPDATE_CSV);
+        let mut backend = TypeScriptCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("export interface ChannelUpdateMsg {\n  chainHash: Uint8Array;\n  timestamp: number;\n  htlcMaximumMsat: bigint;\n}"),
+            "{code}"
+        );
+        assert!(
+            code.contains(
+                "export function encodeChannelUpdateMsg(msg: ChannelUpdateMsg): Uint8Array {"
+            ),
+            "{code}"
+        );
+        assert!(
+            code.contains("  const htlcMaximumMsat = reader.u64();\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  const ignored = reader.bytes(reader.u16());\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  | { type: 18; name: \"ping\"; value: PingMsg }\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("export function decodeMessage(payload: Uint8Array): Message {"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires tsc, run by the toolchains job of the CI"]
+    fn typescript_channel_update_msg_tsc() {
+        init();
+        let symbol_table = parse(CHANNEL_UPDATE_CSV);
+        let mut backend = TypeScriptCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("typescript_channel_update_msg");
+        fs::write(dir.join("lnwire.ts"), backend.to_string()).unwrap();
+        run_cmd(
+            Command::new("tsc")
+                .args(["--strict", "--noEmit", "--target", "es2020", "lnwire.ts"])
+                .current_dir(&dir),
+        );
+    }
+
+    const SIGNATURES_CSV: &str = "msgtype,commitment_signed,132\n\
+                                  msgdata,commitment_signed,channel_id,channel_id,\n\
+                                  msgdata,commitment_signed,num_htlcs,u16,\n\
+                                  msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                                  msgtype,points,1\n\
+                                  msgdata,points,num_points,u16,\n\
+                                  msgdata,points,flags,u16,\n\
+                                  msgdata,points,points,point,num_points\n";
+
+    #[test]
+    fn typescript_array_elements() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = TypeScriptCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains("  const htlcSignature = reader.bytes(reader.u16() * 64);\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  writer.elements(msg.htlcSignature, 64);\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  const points = reader.bytes(numPoints * 33);\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires tsc, run by the toolchains job of the CI"]
+    fn typescript_array_elements_round_trip() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = TypeScriptCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("typescript_array_elements");
+        fs::write(dir.join("lnwire.ts"), backend.to_string()).unwrap();
+        let main = indoc! {r#"
+            import * as lnwire from "./lnwire";
+
+            const msg = {
+              channelId: new Uint8Array(32).fill(1),
+              htlcSignature: new Uint8Array(128).fill(0x5a),
+            };
+            const wire = lnwire.encodeCommitmentSignedMsg(msg);
+            if (wire.length !== 2 + 32 + 2 + 128 || wire[35] !== 2) {
+              throw new Error(`unexpected wire ${wire}`);
+            }
+            const decoded = lnwire.decodeCommitmentSignedMsg(wire);
+            if (decoded.htlcSignature.length !== 128) {
+              throw new Error("signatures are truncated");
+            }
+            try {
+              lnwire.encodeCommitmentSignedMsg({ ...msg, htlcSignature: new Uint8Array(127) });
+              throw new Error("partial signature accepted");
+            } catch (err) {
+              if (!(err instanceof lnwire.WireError)) throw err;
+            }
+            const writer = new lnwire.Writer();
+            try {
+              writer.u64(1n << 64n);
+              throw new Error("u64 overflow accepted");
+            } catch (err) {
+              if (!(err instanceof lnwire.WireError)) throw err;
+            }
+            console.log("ok");
+        "#};
+        fs::write(dir.join("main.ts"), main).unwrap();
+        run_cmd(
+            Command::new("tsc")
+                .args([
+                    "--strict", "--target", "es2020", "--module", "commonjs", "main.ts",
+                ])
+                .current_dir(&dir),
+        );
+        let stdout = run_cmd(Command::new("node").arg("main.js").current_dir(&dir));
+        assert_eq!(stdout, "ok\n");
+    }
+}
@@ -0,0 +1,470 @@
+//! Wireshark dissector generation for the lightning
+//! network specification.
+//!
+//! The output is a Lua plugin that dissects the decrypted messages,
+//! it is registered as a "Decode As" protocol of the tcp port table.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated dissectors.
+const LUA_RUNTIME: &str = indoc! {r#"
+    local lnwire = Proto("lnwire", "Lightning Network wire message")
+    local f = lnwire.fields
+
+    -- Read a bigsize, returning the value and the size on the wire.
+    local function read_bigsize(tvb, offset)
+      local prefix = tvb(offset, 1):uint()
+      if prefix == 0xfd then
+        return tvb(offset + 1, 2):uint(), 3
+      elseif prefix == 0xfe then
+        return tvb(offset + 1, 4):uint(), 5
+      elseif prefix == 0xff then
+        return tvb(offset + 1, 8):uint64():tonumber(), 9
+      end
+      return prefix, 1
+    end
+
+    -- Add the bytes to the tree, an empty field is skipped.
+    local function add_bytes(tree, field, tvb, offset, len)
+      if len > 0 then
+        tree:add(field, tvb(offset, len))
+      end
+      return offset + len
+    end
+
+    -- Dissect the tlv records till the end of the buffer.
+    local function dissect_tlv_stream(tvb, offset, tree, name, records)
+      if offset >= tvb:len() then
+        return offset
+      end
+      local stream = tree:add(lnwire, tvb(offset), name)
+      while offset < tvb:len() do
+        local typ, typ_len = read_bigsize(tvb, offset)
+        local len, len_len = read_bigsize(tvb, offset + typ_len)
+        local size = typ_len + len_len + len
+        local label = (records[typ] or "unknown") .. " (type " .. typ .. ")"
+        local record = stream:add(lnwire, tvb(offset, size), label)
+        record:add(tvb(offset, typ_len), "Type: " .. typ)
+        record:add(tvb(offset + typ_len, len_len), "Length: " .. len)
+        add_bytes(record, f["tlv.value"], tvb, offset + typ_len + len_len, len)
+        offset = offset + size
+      end
+      return offset
+    end
+
+    f["type"] = ProtoField.uint16("lnwire.type", "Type", base.DEC, msg_types)
+    f["tlv.value"] = ProtoField.bytes("lnwire.tlv.value", "Value")
+"#};
+
+pub struct WiresharkCodeGen {
+    /// The fields of the message or the subtype that we are building.
+    curr_fields: Vec<LNMsData>,
+    /// The subtypes of the specification, their dissectors are declared
+    /// before so an array can use a subtype defined after it.
+    subtypes: BTreeSet<String>,
+    /// The name of the message or subtype that we are building,
+    /// used as prefix of the fields.
+    prefix: String,
+    /// The declaration of the protocol fields.
+    fields: String,
+    /// The body of the dissector function that we are building.
+    dissect_fn: String,
+    /// The tlv streams and subtypes, declared before the messages.
+    tlv_content: String,
+    msg_content: String,
+    msg_types: String,
+    dissectors: String,
+}
+
+impl WiresharkCodeGen {
+    fn add_field_decl(&mut self, name: &str, proto_field: &str, label: &str) {
+        let key = format!("{}.{name}", self.prefix);
+        self.fields +=
+            &format!("f[\"{key}\"] = ProtoField.{proto_field}(\"lnwire.{key}\", \"{label}\")\n");
+    }
+
+    fn add_int(&mut self, field: &LNMsData, proto_field: &str, size: u64) {
+        let name = field.name();
+        let label = format!("{name} ({})", field.csv_type());
+        self.add_field_decl(name, proto_field, &label);
+        self.dissect_fn += &format!("  v[\"{name}\"] = tvb(offset, {size}):uint()\n");
+        self.dissect_fn += &format!(
+            "  tree:add(f[\"{}.{name}\"], tvb(offset, {size}))\n  offset = offset + {size}\n",
+            self.prefix
+        );
+    }
+
+    fn add_bytes(&mut self, field: &LNMsData, len: &str) {
+        let name = field.name();
+        let label = format!("{name} ({})", field.csv_type());
+        self.add_field_decl(name, "bytes", &label);
+        self.dissect_fn += &format!(
+            "  offset = add_bytes(tree, f[\"{}.{name}\"], tvb, offset, {len})\n",
+            self.prefix
+        );
+    }
+
+    fn add_fixed(&mut self, field: &LNMsData) {
+        let size = field.fixed_size().unwrap().to_string();
+        self.add_bytes(field, &size);
+    }
+
+    /// Add `count` elements of the type `ty`, a subtype is dissected
+    /// per element and the other types are added as bytes.
+    fn add_array(&mut self, field: &LNMsData, count: &str, ty: &str) {
+        match (LNMsData::element_size(ty), count) {
+            (Some(_), "...") => self.add_bytes(field, "tvb:len() - offset"),
+            (Some(1), count) => self.add_bytes(field, count),
+            (Some(size), count) => match count.parse::<u64>() {
+                Ok(count) => self.add_bytes(field, &(count * size).to_string()),
+                Err(_) => self.add_bytes(field, &format!("{count} * {size}")),
+            },
+            (None, "...") if self.subtypes.contains(ty) => {
+                self.dissect_fn += &format!(
+                    "  while offset < tvb:len() do\n    offset = dissect_{ty}(tvb, offset, tree)\n  end\n"
+                );
+            }
+            (None, count) if self.subtypes.contains(ty) => {
+                self.dissect_fn += &format!(
+                    "  for _ = 1, {count} do\n    offset = dissect_{ty}(tvb, offset, tree)\n  end\n"
+                );
+            }
+            // an unknown type uses all the rest of the message
+            (None, _) => self.add_bytes(field, "tvb:len() - offset"),
+        }
+    }
+
+    fn build_dissect_fn(&mut self, doc: &str, name: &str, decl: &str) -> String {
+        let body = std::mem::take(&mut self.dissect_fn);
+        format!(
+            "-- {doc}\n{decl} dissect_{name}(tvb, offset, tree)\n  local v = {{}}\n{body}  return offset\nend\n\n"
+        )
+    }
+}
+
+impl<'g> CodeGen<'g> for WiresharkCodeGen {
+    fn new(symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        let subtypes = symbol_table
+            .values()
+            .filter_map(|item| match item {
+                LNMsgType::SubType(subtyp) => Some(subtyp.ty.to_owned()),
+                _ => None,
+            })
+            .collect();
+        WiresharkCodeGen {
+            curr_fields: Vec::new(),
+            subtypes,
+            prefix: String::new(),
+            fields: String::new(),
+            dissect_fn: String::new(),
+            tlv_content: String::new(),
+            msg_content: String::new(),
+            msg_types: String::new(),
+            dissectors: String::new(),
+        }
+    }
+
+    fn pre_generation(&mut self) {}
+
+    fn post_generation(&mut self) {}
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        let name = &tlv.stream_name;
+        let records = tlv
+            .records
+            .iter()
+            .map(|record| format!("[{}] = \"{}\"", record.type_len, record.type_name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.tlv_content += &format!(
+            indoc! {r#"
+                -- The `{name}` tlv stream.
+                local {name}_records = {{ {records} }}
+                local function dissect_{name}(tvb, offset, tree)
+                  return dissect_tlv_stream(tvb, offset, tree, "{name}", {name}_records)
+                end
+
+            "#},
+            name = name,
+            records = records,
+        );
+    }
+
+    fn generate_subtype(&mut self, subtyp: &LNSubType) {
+        self.prefix = subtyp.ty.to_owned();
+        self.curr_fields = subtyp.ty_data.to_owned();
+        for field in &subtyp.ty_data {
+            match field {
+                LNMsData::Uint16(_) => self.build_u16(field),
+                LNMsData::Uint32(_) => self.build_u32(field),
+                LNMsData::Uint64(_) => self.build_u64(field),
+                LNMsData::BigSize(_) => self.build_bigsize(field),
+                LNMsData::BitfieldStream(..) => self.build_bitfield(field),
+                LNMsData::TLVinit(..) => {}
+                _ => self.add_fixed(field),
+            }
+        }
+        let doc = format!("The `{}` subtype.", subtyp.ty);
+        // the local was declared before all the dissectors
+        let code = self.build_dissect_fn(&doc, &subtyp.ty, "function");
+        self.tlv_content += &code;
+        self.curr_fields.clear();
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_fields = msg.msg_data.to_owned();
+        self.prefix = msg.msg_name.to_owned();
+        self.msg_types += &format!("  [{}] = \"{}\",\n", msg.msg_typ, msg.msg_name);
+        self.dissectors += &format!("  [{}] = dissect_{},\n", msg.msg_typ, msg.msg_name);
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let doc = format!("The `{}` message, type {}.", msg.msg_name, msg.msg_typ);
+        let code = self.build_dissect_fn(&doc, &msg.msg_name, "local function");
+        self.msg_content += &code;
+        self.curr_fields.clear();
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        self.add_int(field, "uint16", 2);
+    }
+
+    fn write_u16(&mut self, _: &LNMsData) {}
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        self.add_int(field, "uint32", 4);
+    }
+
+    fn write_u32(&mut self, _: &LNMsData) {}
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        let name = field.name();
+        self.add_field_decl(name, "uint64", &format!("{name} (u64)"));
+        self.dissect_fn += &format!(
+            "  tree:add(f[\"{}.{name}\"], tvb(offset, 8))\n  offset = offset + 8\n",
+            self.prefix
+        );
+    }
+
+    fn write_u64(&mut self, _: &LNMsData) {}
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        let name = field.name();
+        self.add_field_decl(name, "uint64", &format!("{name} (bigsize)"));
+        self.dissect_fn += &format!(
+            "  local {name}_len\n  v[\"{name}\"], {name}_len = read_bigsize(tvb, offset)\n"
+        );
+        self.dissect_fn += &format!(
+            "  tree:add(f[\"{}.{name}\"], tvb(offset, {name}_len), UInt64.new(v[\"{name}\"]))\n  offset = offset + {name}_len\n",
+            self.prefix
+        );
+    }
+
+    fn write_bigsize(&mut self, _: &LNMsData) {}
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.add_fixed(field);
+    }
+
+    fn write_chain_hash(&mut self, _: &LNMsData) {}
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.add_fixed(field);
+    }
+
+    fn write_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.add_fixed(field);
+    }
+
+    fn write_short_channel_id(&mut self, _: &LNMsData) {}
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.add_fixed(field);
+    }
+
+    fn write_signature(&mut self, _: &LNMsData) {}
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.add_fixed(field);
+    }
+
+    fn write_point(&mut self, _: &LNMsData) {}
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.add_fixed(field);
+    }
+
+    fn write_sha256(&mut self, _: &LNMsData) {}
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            if size == "1"
+                && ["byte", "u8"].contains(&ty.as_str())
+                && self.curr_fields.iter().any(
+                    |field| matches!(field, LNMsData::BitfieldStream(_, count, _) if count == name),
+                )
+            {
+                // a single byte that it is the count of an array
+                self.add_int(field, "uint8", 1);
+            } else if size == "..." || size.parse::<u64>().is_ok() {
+                self.add_array(field, size, ty);
+            } else if LNMsData::is_length_field(&self.curr_fields, size) {
+                self.add_array(field, &format!("v[\"{size}\"]"), ty);
+            } else {
+                // the u16 length was folded inside the bitfield.
+                let len = LNMsData::Uint16(format!("{name}_len"));
+                self.build_u16(&len);
+                self.add_array(field, &format!("v[\"{name}_len\"]"), ty);
+            }
+        }
+    }
+
+    fn write_bitfiled(&mut self, _: &LNMsData) {}
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        self.dissect_fn += &format!(
+            "  offset = dissect_{}(tvb, offset, tree)\n",
+            field.stream_name
+        );
+    }
+
+    fn write_tlv_stream(&mut self, _: &LNTlvStream) {}
+}
+
+impl Display for WiresharkCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "-- code generated by the lncodegen.rs please do not edit"
+        )?;
+        writeln!(f, "local msg_types = {{\n{}}}\n", self.msg_types)?;
+        writeln!(f, "{LUA_RUNTIME}")?;
+        writeln!(f, "{}", self.fields)?;
+        if !self.subtypes.is_empty() {
+            let decls = self
+                .subtypes
+                .iter()
+                .map(|ty| format!("dissect_{ty}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "local {decls}\n")?;
+        }
+        write!(f, "{}", self.tlv_content)?;
+        write!(f, "{}", self.msg_content)?;
+        write!(
+            f,
+            indoc! {r#"
+                local dissectors = {{
+                {dissectors}}}
+
+                function lnwire.dissector(tvb, pinfo, tree)
+                  pinfo.cols.protocol = "LNWIRE"
+                  local subtree = tree:add(lnwire, tvb(), "Lightning Network message")
+                  local msg_type = tvb(0, 2):uint()
+                  subtree:add(f["type"], tvb(0, 2))
+                  pinfo.cols.info = msg_types[msg_type] or ("unknown type " .. msg_type)
+                  local dissect = dissectors[msg_type]
+                  if dissect then
+                    dissect(tvb, 2, subtree)
+                  end
+                end
+
+                DissectorTable.get("tcp.port"):add_for_decode_as(lnwire)
+            "#},
+            dissectors = self.dissectors,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codegen::CodeGen;
+    use crate::testing::{init, parse};
+    use crate::wireshark::WiresharkCodeGen;
+
+    #[test]
+    fn wireshark_ping_msg() {
+        init();
+        let contents = "msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16,\n\
+                        msgdata,ping,byteslen,u16,\n\
+                        msgdata,ping,ignored,byte,byteslen\n";
+        let symbol_table = parse(contents);
+        let mut backend = WiresharkCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("f[\"ping.num_pong_bytes\"] = ProtoField.uint16(\"lnwire.ping.num_pong_bytes\", \"num_pong_bytes (u16)\")\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  offset = add_bytes(tree, f[\"ping.ignored\"], tvb, offset, v[\"ignored_len\"])\n"),
+            "{code}"
+        );
+        assert!(code.contains("  [18] = dissect_ping,\n"), "{code}");
+    }
+
+    #[test]
+    fn wireshark_array_elements() {
+        init();
+        let contents = "subtype,witness\n\
+                        subtypedata,witness,len,u16,\n\
+                        subtypedata,witness,witness_data,byte,len\n\
+                        subtype,blinded_path\n\
+                        subtypedata,blinded_path,num_hops,byte,\n\
+                        subtypedata,blinded_path,path,witness,num_hops\n\
+                        msgtype,commitment_signed,132\n\
+                        msgdata,commitment_signed,num_htlcs,u16,\n\
+                        msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                        msgtype,tx_signatures,71\n\
+                        msgdata,tx_signatures,num_witnesses,u16,\n\
+                        msgdata,tx_signatures,witnesses,witness,num_witnesses\n";
+        let symbol_table = parse(contents);
+        let mut backend = WiresharkCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains("  offset = add_bytes(tree, f[\"commitment_signed.htlc_signature\"], tvb, offset, v[\"htlc_signature_len\"] * 64)\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  for _ = 1, v[\"witnesses_len\"] do\n    offset = dissect_witness(tvb, offset, tree)\n  end\n"),
+            "{code}"
+        );
+        // the count of a subtype is a field of the subtype
+        assert!(
+            code.contains("  v[\"num_hops\"] = tvb(offset, 1):uint()\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("  for _ = 1, v[\"num_hops\"] do\n    offset = dissect_witness(tvb, offset, tree)\n  end\n"),
+            "{code}"
+        );
+        // the subtypes are declared before they are defined
+        assert!(
+            code.contains("local dissect_blinded_path, dissect_witness\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("function dissect_witness(tvb, offset, tree)\n"),
+            "{code}"
+        );
+    }
+}
@@ -0,0 +1,755 @@
+//! Zig code generation for the lightning
+//! network specification.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use convert_case::{Case, Casing};
+use indoc::indoc;
+
+use csvlang::parser::ast::{LNMsData, LNMsg, LNMsgType, LNTlvStream};
+
+use super::codegen::CodeGen;
+
+/// Helpers used by the generated structs to read and write
+/// the lightning network types.
+const ZIG_RUNTIME: &str = indoc! {r#"
+    pub const WireError = error{
+        UnexpectedType,
+        UnknownType,
+        NonMinimalBigSize,
+        TlvNotIncreasing,
+        UnknownEvenTlv,
+        LengthOverflow,
+        PartialElement,
+    };
+
+    pub fn writeBigSize(writer: anytype, value: u64) !void {
+        if (value < 0xfd) {
+            try writer.writeInt(u8, @intCast(value), .big);
+        } else if (value <= 0xffff) {
+            try writer.writeInt(u8, 0xfd, .big);
+            try writer.writeInt(u16, @intCast(value), .big);
+        } else if (value <= 0xffffffff) {
+            try writer.writeInt(u8, 0xfe, .big);
+            try writer.writeInt(u32, @intCast(value), .big);
+        } else {
+            try writer.writeInt(u8, 0xff, .big);
+            try writer.writeInt(u64, value, .big);
+        }
+    }
+
+    pub fn readBigSize(reader: anytype) !u64 {
+        const prefix = try reader.readInt(u8, .big);
+        const value: u64 = switch (prefix) {
+            0xfd => try reader.readInt(u16, .big),
+            0xfe => try reader.readInt(u32, .big),
+            0xff => try reader.readInt(u64, .big),
+            else => return prefix,
+        };
+        const min: u64 = switch (prefix) {
+            0xfd => 0xfd,
+            0xfe => 0x10000,
+            else => 0x100000000,
+        };
+        if (value < min) return WireError.NonMinimalBigSize;
+        return value;
+    }
+
+    /// Write the elements of `elem_size` bytes prefixed by their u16 count.
+    pub fn writeVarBytes(writer: anytype, value: []const u8, elem_size: usize) !void {
+        if (value.len % elem_size != 0) return WireError.PartialElement;
+        const count = value.len / elem_size;
+        if (count > std.math.maxInt(u16)) return WireError.LengthOverflow;
+        try writer.writeInt(u16, @intCast(count), .big);
+        try writer.writeAll(value);
+    }
+
+    pub fn readBytesAlloc(allocator: std.mem.Allocator, reader: anytype, len: usize) ![]u8 {
+        const value = try allocator.alloc(u8, len);
+        errdefer allocator.free(value);
+        try reader.readNoEof(value);
+        return value;
+    }
+
+    /// Read the elements of `elem_size` bytes prefixed by their u16 count.
+    pub fn readVarBytes(allocator: std.mem.Allocator, reader: anytype, elem_size: usize) ![]u8 {
+        const count = try reader.readInt(u16, .big);
+        return readBytesAlloc(allocator, reader, @as(usize, count) * elem_size);
+    }
+
+    pub fn readRestAlloc(allocator: std.mem.Allocator, reader: anytype) ![]u8 {
+        return reader.readAllAlloc(allocator, std.math.maxInt(usize));
+    }
+
+    pub fn writeTlvRecord(writer: anytype, typ: u64, value: []const u8) !void {
+        try writeBigSize(writer, typ);
+        try writeBigSize(writer, value.len);
+        try writer.writeAll(value);
+    }
+"#};
+
+/// Zig keywords and primitive names, that need the `@""` syntax.
+const ZIG_KEYWORDS: [&str; 58] = [
+    "addrspace",
+    "align",
+    "allowzero",
+    "and",
+    "anyframe",
+    "anytype",
+    "asm",
+    "async",
+    "await",
+    "break",
+    "callconv",
+    "catch",
+    "comptime",
+    "const",
+    "continue",
+    "defer",
+    "else",
+    "enum",
+    "errdefer",
+    "error",
+    "export",
+    "extern",
+    "fn",
+    "for",
+    "if",
+    "inline",
+    "linksection",
+    "noalias",
+    "noinline",
+    "nosuspend",
+    "opaque",
+    "or",
+    "orelse",
+    "packed",
+    "pub",
+    "resume",
+    "return",
+    "struct",
+    "suspend",
+    "switch",
+    "test",
+    "threadlocal",
+    "try",
+    "union",
+    "unreachable",
+    "usingnamespace",
+    "var",
+    "volatile",
+    "while",
+    "type",
+    "bool",
+    "void",
+    "anyerror",
+    "anyopaque",
+    "noreturn",
+    "null",
+    "true",
+    "false",
+];
+
+pub struct ZigCodeGen {
+    curr_msg: Option<LNMsg>,
+    struct_fields: String,
+    encode_fn: String,
+    decode_fn: String,
+    deinit_fn: String,
+    /// The size of the message on the wire, `None` when
+    /// the message has a variable length.
+    wire_size: Option<u64>,
+    /// The union members and the `switch` cases of the dispatcher.
+    msg_union: String,
+    msg_cases: String,
+    /// True if at least one message of the dispatcher needs the allocator.
+    dispatch_alloc: bool,
+    file_content: String,
+}
+
+impl ZigCodeGen {
+    fn zig_type_name(name: &str) -> String {
+        name.to_case(Case::Pascal)
+    }
+
+    fn zig_ident(name: &str) -> String {
+        if ZIG_KEYWORDS.contains(&name) {
+            return format!("@\"{name}\"");
+        }
+        name.to_owned()
+    }
+
+    fn build_msg_doc(msg: &LNMsg) -> String {
+        let mut code = format!(
+            "/// The `{}` message, type {}.\n",
+            msg.msg_name, msg.msg_typ
+        );
+        if let Some(doc) = &msg.doc {
+            for paragraph in doc.prose.split("\n\n").filter(|p| !p.is_empty()) {
+                code += &format!("///\n/// {paragraph}\n");
+            }
+            code += &format!(
+                "///\n/// See [{}: {}]({}).\n",
+                doc.bolt, doc.section, doc.link
+            );
+        }
+        code
+    }
+
+    fn build_fixed(&mut self, name: &str, size: u64) {
+        let name = Self::zig_ident(name);
+        self.struct_fields += &format!("    {name}: [{size}]u8,\n");
+        self.decode_fn += &format!("        try reader.readNoEof(&msg.{name});\n");
+        self.wire_size = self.wire_size.map(|wire_size| wire_size + size);
+    }
+
+    fn write_fixed(&mut self, name: &str) {
+        let name = Self::zig_ident(name);
+        self.encode_fn += &format!("        try writer.writeAll(&self.{name});\n");
+    }
+
+    fn build_int(&mut self, name: &str, ty: &str, size: u64) {
+        let name = Self::zig_ident(name);
+        self.struct_fields += &format!("    {name}: {ty},\n");
+        self.decode_fn += &format!("        msg.{name} = try reader.readInt({ty}, .big);\n");
+        self.wire_size = self.wire_size.map(|wire_size| wire_size + size);
+    }
+
+    fn write_int(&mut self, name: &str, ty: &str) {
+        let name = Self::zig_ident(name);
+        self.encode_fn += &format!("        try writer.writeInt({ty}, self.{name}, .big);\n");
+    }
+
+    /// Add a slice allocated while decoding, that makes the message
+    /// of variable length.
+    fn build_slice(&mut self, name: &str, read: &str) {
+        let name = Self::zig_ident(name);
+        self.struct_fields += &format!("    {name}: []const u8,\n");
+        self.decode_fn += &format!("        msg.{name} = try {read};\n");
+        self.decode_fn += &format!("        errdefer allocator.free(msg.{name});\n");
+        self.deinit_fn += &format!("        allocator.free(self.{name});\n");
+        self.wire_size = None;
+    }
+
+    /// Build the struct of a tlv stream, the records are optional slices
+    /// and the unknown odd records are skipped.
+    fn build_tlv_stream_struct(tlv: &LNTlvStream) -> String {
+        let name = Self::zig_type_name(&tlv.stream_name);
+        let mut records = tlv.records.clone();
+        records.sort_by_key(|record| record.type_len);
+        let mut fields = String::new();
+        let mut encode = String::new();
+        let mut cases = String::new();
+        let mut deinit = String::new();
+        for record in &records {
+            let field = Self::zig_ident(&record.type_name);
+            let typ = record.type_len;
+            fields += &format!("    /// Record of type {typ}.\n    {field}: ?[]const u8 = null,\n");
+            encode += &format!(
+                "        if (self.{field}) |value| try writeTlvRecord(writer, {typ}, value);\n"
+            );
+            cases += &format!(
+                "                {typ} => tlvs.{field} = try readBytesAlloc(allocator, r, @intCast(len)),\n"
+            );
+            deinit += &format!("        if (self.{field}) |value| allocator.free(value);\n");
+        }
+        format!(
+            indoc! {r#"
+                /// The `{stream}` tlv stream, the unknown odd records are skipped.
+                pub const {name} = struct {{
+                {fields}
+                    const Self = @This();
+
+                    pub fn encode(self: Self, writer: anytype) !void {{
+                {encode}    }}
+
+                    /// Decode the records till the end of the reader.
+                    pub fn decode(allocator: std.mem.Allocator, reader: anytype) !Self {{
+                        const raw = try readRestAlloc(allocator, reader);
+                        defer allocator.free(raw);
+                        var stream = std.io.fixedBufferStream(raw);
+                        const r = stream.reader();
+                        var tlvs = Self{{}};
+                        errdefer tlvs.deinit(allocator);
+                        var last: ?u64 = null;
+                        while (stream.pos < raw.len) {{
+                            const typ = try readBigSize(r);
+                            if (last != null and typ <= last.?) return WireError.TlvNotIncreasing;
+                            last = typ;
+                            const len = try readBigSize(r);
+                            if (len > raw.len - stream.pos) return error.EndOfStream;
+                            switch (typ) {{
+                {cases}                else => {{
+                                    if (typ % 2 == 0) return WireError.UnknownEvenTlv;
+                                    try r.skipBytes(len, .{{}});
+                                }},
+                            }}
+                        }}
+                        return tlvs;
+                    }}
+
+                    pub fn deinit(self: Self, allocator: std.mem.Allocator) void {{
+                {deinit}    }}
+                }};
+
+            "#},
+            stream = tlv.stream_name,
+            name = name,
+            fields = fields,
+            encode = encode,
+            cases = cases,
+            deinit = deinit,
+        )
+    }
+}
+
+impl<'g> CodeGen<'g> for ZigCodeGen {
+    fn new(_symbol_table: &'g BTreeMap<String, LNMsgType>) -> Self {
+        ZigCodeGen {
+            curr_msg: None,
+            struct_fields: String::new(),
+            encode_fn: String::new(),
+            decode_fn: String::new(),
+            deinit_fn: String::new(),
+            wire_size: Some(2),
+            msg_union: String::new(),
+            msg_cases: String::new(),
+            dispatch_alloc: false,
+            file_content: String::new(),
+        }
+    }
+
+    fn raw_arrays(&self) -> bool {
+        true
+    }
+
+    fn pre_generation(&mut self) {
+        self.file_content += "// code generated by the lncodegen.rs please do not edit\n";
+        self.file_content += "const std = @import(\"std\");\n\n";
+        self.file_content += ZIG_RUNTIME;
+        self.file_content += "\n";
+    }
+
+    fn post_generation(&mut self) {
+        let discard = if self.dispatch_alloc {
+            ""
+        } else {
+            "    _ = allocator;\n"
+        };
+        self.file_content += &format!(
+            indoc! {r#"
+                pub const Message = union(enum) {{
+                {union}}};
+
+                /// Decode the message, including the type, to the right struct.
+                pub fn decodeMessage(allocator: std.mem.Allocator, reader: anytype) !Message {{
+                {discard}    switch (try reader.readInt(u16, .big)) {{
+                {cases}        else => return WireError.UnknownType,
+                    }}
+                }}
+            "#},
+            union = self.msg_union,
+            discard = discard,
+            cases = self.msg_cases,
+        );
+    }
+
+    fn generate_tlv(&mut self, tlv: &LNTlvStream) {
+        self.file_content += &Self::build_tlv_stream_struct(tlv);
+    }
+
+    fn build_msg(&mut self, msg: &LNMsg) {
+        self.curr_msg = Some(msg.to_owned());
+        self.wire_size = Some(2);
+    }
+
+    fn end_msg(&mut self, msg: &LNMsg) {
+        let name = Self::zig_type_name(&msg.msg_name);
+        let tag = Self::zig_ident(&msg.msg_name);
+        self.msg_union += &format!("    {tag}: {name},\n");
+
+        let mut code = Self::build_msg_doc(msg);
+        code += &format!("pub const {name} = struct {{\n");
+        code += &self.struct_fields;
+        if !self.struct_fields.is_empty() {
+            code += "\n";
+        }
+        code += &format!("    pub const msg_type: u16 = {};\n", msg.msg_typ);
+        if let Some(wire_size) = self.wire_size {
+            code += "    /// The size on the wire, including the type.\n";
+            code += &format!("    pub const wire_size: usize = {wire_size};\n");
+        }
+        code += "    const Self = @This();\n\n";
+
+        code += "    pub fn encode(self: Self, writer: anytype) !void {\n";
+        code += "        try writer.writeInt(u16, msg_type, .big);\n";
+        if self.struct_fields.is_empty() {
+            code += "        _ = self;\n";
+        }
+        code += &self.encode_fn;
+        code += "    }\n\n";
+
+        let (params, args) = if self.wire_size.is_some() {
+            ("reader: anytype", "reader")
+        } else {
+            self.dispatch_alloc = true;
+            (
+                "allocator: std.mem.Allocator, reader: anytype",
+                "allocator, reader",
+            )
+        };
+        code += &format!("    pub fn decode({params}) !Self {{\n");
+        code += "        if (try reader.readInt(u16, .big) != msg_type) return WireError.UnexpectedType;\n";
+        code += &format!("        return decodeBody({args});\n    }}\n\n");
+        code += "    /// Decode the message after the type.\n";
+        code += &format!("    pub fn decodeBody({params}) !Self {{\n");
+        // a bigsize makes the size variable without allocating.
+        let unused_alloc = self.wire_size.is_none() && self.deinit_fn.is_empty();
+        if unused_alloc {
+            code += "        _ = allocator;\n";
+        }
+        if self.struct_fields.is_empty() {
+            code += "        _ = reader;\n        return Self{};\n";
+        } else {
+            code += "        var msg: Self = undefined;\n";
+            code += &self.decode_fn;
+            code += "        return msg;\n";
+        }
+        code += "    }\n";
+
+        if self.wire_size.is_none() {
+            code += "\n    pub fn deinit(self: Self, allocator: std.mem.Allocator) void {\n";
+            if unused_alloc {
+                code += "        _ = self;\n        _ = allocator;\n";
+            }
+            code += &self.deinit_fn;
+            code += "    }\n";
+            self.msg_cases += &format!(
+                "        {} => return .{{ .{tag} = try {name}.decodeBody(allocator, reader) }},\n",
+                msg.msg_typ
+            );
+        } else {
+            self.msg_cases += &format!(
+                "        {} => return .{{ .{tag} = try {name}.decodeBody(reader) }},\n",
+                msg.msg_typ
+            );
+        }
+        code += "};\n\n";
+        self.file_content += &code;
+
+        self.struct_fields.clear();
+        self.encode_fn.clear();
+        self.decode_fn.clear();
+        self.deinit_fn.clear();
+        self.curr_msg = None;
+    }
+
+    fn build_decode_fun(&mut self) {}
+
+    fn end_decode_fn(&mut self) {}
+
+    fn build_encode_fn(&mut self) {}
+
+    fn end_encode_fn(&mut self) {}
+
+    fn build_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.build_int(name, "u16", 2);
+        }
+    }
+
+    fn write_u16(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint16(name) = field {
+            self.write_int(name, "u16");
+        }
+    }
+
+    fn build_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.build_int(name, "u32", 4);
+        }
+    }
+
+    fn write_u32(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint32(name) = field {
+            self.write_int(name, "u32");
+        }
+    }
+
+    fn build_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.build_int(name, "u64", 8);
+        }
+    }
+
+    fn write_u64(&mut self, field: &LNMsData) {
+        if let LNMsData::Uint64(name) = field {
+            self.write_int(name, "u64");
+        }
+    }
+
+    fn build_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            let name = Self::zig_ident(name);
+            self.struct_fields += &format!("    {name}: u64,\n");
+            self.decode_fn += &format!("        msg.{name} = try readBigSize(reader);\n");
+            // the size on the wire depends on the value.
+            self.wire_size = None;
+        }
+    }
+
+    fn write_bigsize(&mut self, field: &LNMsData) {
+        if let LNMsData::BigSize(name) = field {
+            let name = Self::zig_ident(name);
+            self.encode_fn += &format!("        try writeBigSize(writer, self.{name});\n");
+        }
+    }
+
+    fn build_chain_hash(&mut self, field: &LNMsData) {
+        self.build_fixed(field.name(), field.fixed_size().unwrap());
+    }
+
+    fn write_chain_hash(&mut self, field: &LNMsData) {
+        self.write_fixed(field.name());
+    }
+
+    fn build_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field.name(), field.fixed_size().unwrap());
+    }
+
+    fn write_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field.name());
+    }
+
+    fn build_short_channel_id(&mut self, field: &LNMsData) {
+        self.build_fixed(field.name(), field.fixed_size().unwrap());
+    }
+
+    fn write_short_channel_id(&mut self, field: &LNMsData) {
+        self.write_fixed(field.name());
+    }
+
+    fn build_signature(&mut self, field: &LNMsData) {
+        self.build_fixed(field.name(), field.fixed_size().unwrap());
+    }
+
+    fn write_signature(&mut self, field: &LNMsData) {
+        self.write_fixed(field.name());
+    }
+
+    fn build_point(&mut self, field: &LNMsData) {
+        self.build_fixed(field.name(), field.fixed_size().unwrap());
+    }
+
+    fn write_point(&mut self, field: &LNMsData) {
+        self.write_fixed(field.name());
+    }
+
+    fn build_sha256(&mut self, field: &LNMsData) {
+        self.build_fixed(field.name(), field.fixed_size().unwrap());
+    }
+
+    fn write_sha256(&mut self, field: &LNMsData) {
+        self.write_fixed(field.name());
+    }
+
+    fn build_bitfield(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            let elem = LNMsData::element_size(ty).unwrap();
+            if let Ok(size) = size.parse::<u64>() {
+                self.build_fixed(name, size * elem);
+            } else if size == "..." {
+                self.build_slice(name, "readRestAlloc(allocator, reader)");
+            } else if self
+                .curr_msg
+                .as_ref()
+                .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let len = match (Self::zig_ident(size), elem) {
+                    (len, 1) => format!("msg.{len}"),
+                    (len, elem) => format!("@as(usize, msg.{len}) * {elem}"),
+                };
+                self.build_slice(name, &format!("readBytesAlloc(allocator, reader, {len})"));
+            } else {
+                self.build_slice(name, &format!("readVarBytes(allocator, reader, {elem})"));
+            }
+        }
+    }
+
+    fn write_bitfiled(&mut self, field: &LNMsData) {
+        if let LNMsData::BitfieldStream(name, size, ty) = field {
+            if size.parse::<u64>().is_ok() {
+                self.write_fixed(name);
+            } else if size == "..."
+                || self
+                    .curr_msg
+                    .as_ref()
+                    .is_some_and(|msg| msg.is_length_field(size))
+            {
+                let name = Self::zig_ident(name);
+                self.encode_fn += &format!("        try writer.writeAll(self.{name});\n");
+            } else {
+                let name = Self::zig_ident(name);
+                let elem = LNMsData::element_size(ty).unwrap();
+                self.encode_fn +=
+                    &format!("        try writeVarBytes(writer, self.{name}, {elem});\n");
+            }
+        }
+    }
+
+    fn build_tlv_stream(&mut self, field: &LNTlvStream) {
+        let ty = Self::zig_type_name(&field.stream_name);
+        let name = Self::zig_ident(&field.stream_name);
+        self.struct_fields += &format!("    {name}: {ty} = .{{}},\n");
+        self.decode_fn += &format!("        msg.{name} = try {ty}.decode(allocator, reader);\n");
+        self.deinit_fn += &format!("        self.{name}.deinit(allocator);\n");
+        self.wire_size = None;
+    }
+
+    fn write_tlv_stream(&mut self, field: &LNTlvStream) {
+        let name = Self::zig_ident(&field.stream_name);
+        self.encode_fn += &format!("        try self.{name}.encode(writer);\n");
+    }
+}
+
+impl Display for ZigCodeGen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use indoc::indoc;
+
+    use crate::codegen::CodeGen;
+    use crate::testing::{init, parse, run_cmd, scratch_dir};
+    use crate::zig::ZigCodeGen;
+
+    const FIXED_AND_VARIABLE_CSV: &str = "msgtype,channel_update,258\n\
+                                         msgdata,channel_update,chain_hash,chain_hash,\n\
+                                         msgdata,channel_update,timestamp,u32,\n\
+                                         msgdata,channel_update,htlc_maximum_msat,u64,\n\
+                                         msgtype,ping,18\n\
+                                         msgdata,ping,num_pong_bytes,u16,\n\
+                                         msgdata,ping,byteslen,u16,\n\
+                                         msgdata,ping,ignored,byte,byteslen\n";
+
+    #[test]
+    fn zig_fixed_and_variable_msg() {
+        init();
+        let symbol_table = parse(FIXED_AND_VARIABLE_CSV);
+        let mut backend = ZigCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        assert!(
+            code.contains("pub const ChannelUpdate = struct {\n    chain_hash: [32]u8,\n    timestamp: u32,\n    htlc_maximum_msat: u64,\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    pub const wire_size: usize = 46;\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("    pub fn decodeBody(reader: anytype) !Self {\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains("        msg.ignored = try readVarBytes(allocator, reader, 1);\n"),
+            "{code}"
+        );
+        assert!(
+            code.contains(
+                "        18 => return .{ .ping = try Ping.decodeBody(allocator, reader) },\n"
+            ),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires zig, run by the toolchains job of the CI"]
+    fn zig_fixed_and_variable_msg_ast_check() {
+        init();
+        let symbol_table = parse(FIXED_AND_VARIABLE_CSV);
+        let mut backend = ZigCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("zig_fixed_and_variable_msg");
+        fs::write(dir.join("lnwire.zig"), backend.to_string()).unwrap();
+        run_cmd(
+            Command::new("zig")
+                .args(["ast-check", "lnwire.zig"])
+                .current_dir(&dir),
+        );
+    }
+
+    const SIGNATURES_CSV: &str = "msgtype,commitment_signed,132\n\
+                                  msgdata,commitment_signed,num_htlcs,u16,\n\
+                                  msgdata,commitment_signed,htlc_signature,signature,num_htlcs\n\
+                                  msgtype,closing_sigs,1\n\
+                                  msgdata,closing_sigs,sigs,signature,2\n";
+
+    #[test]
+    fn zig_array_elements() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = ZigCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let code = backend.to_string();
+        // the folded count is the number of signatures
+        assert!(
+            code.contains(
+                "        msg.htlc_signature = try readVarBytes(allocator, reader, 64);\n"
+            ),
+            "{code}"
+        );
+        assert!(
+            code.contains("        try writeVarBytes(writer, self.htlc_signature, 64);\n"),
+            "{code}"
+        );
+        assert!(code.contains("    sigs: [128]u8,\n"), "{code}");
+        assert!(
+            code.contains("    pub const wire_size: usize = 130;\n"),
+            "{code}"
+        );
+    }
+
+    #[test]
+    #[ignore = "requires zig, run by the toolchains job of the CI"]
+    fn zig_array_elements_round_trip() {
+        init();
+        let symbol_table = parse(SIGNATURES_CSV);
+        let mut backend = ZigCodeGen::new(&symbol_table);
+        backend.generate(&symbol_table).unwrap();
+        let dir = scratch_dir("zig_array_elements");
+        fs::write(dir.join("lnwire.zig"), backend.to_string()).unwrap();
+        let test = indoc! {r#"
+            const std = @import("std");
+            const lnwire = @import("lnwire.zig");
+
+            test "array elements" {
+                const allocator = std.testing.allocator;
+                const sigs = [_]u8{0x5a} ** 128;
+                const msg = lnwire.CommitmentSigned{ .htlc_signature = &sigs };
+                var buf = std.ArrayList(u8).init(allocator);
+                defer buf.deinit();
+                try msg.encode(buf.writer());
+                try std.testing.expectEqual(@as(usize, 2 + 2 + 128), buf.items.len);
+                var stream = std.io.fixedBufferStream(buf.items);
+                const decoded = try lnwire.CommitmentSigned.decode(allocator, stream.reader());
+                defer decoded.deinit(allocator);
+                try std.testing.expectEqualSlices(u8, msg.htlc_signature, decoded.htlc_signature);
+                const partial = lnwire.CommitmentSigned{ .htlc_signature = sigs[0..127] };
+                try std.testing.expectError(lnwire.WireError.PartialElement, partial.encode(buf.writer()));
+            }
+        "#};
+        fs::write(dir.join("main.zig"), test).unwrap();
+        run_cmd(
+            Command::new("zig")
+                .args(["test", "main.zig"])
+                .current_dir(&dir),
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Mock of the `fundamentals_derive` crate, the struct fields are
+//! encoded in order and the `msg_type` is checked on decoding.
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+struct Field {
+    name: String,
+    ty: String,
+    msg_type: Option<String>,
+}
+
+/// Return the name of the struct and its fields.
+fn parse_struct(input: TokenStream) -> (String, Vec<Field>) {
+    let mut tokens = input.into_iter();
+    while let Some(token) = tokens.next() {
+        if !matches!(&token, TokenTree::Ident(ident) if ident.to_string() == "struct") {
+            continue;
+        }
+        let name = tokens.next().expect("struct without name").to_string();
+        let body = tokens
+            .find_map(|token| match token {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                    Some(group.stream())
+                }
+                _ => None,
+            })
+            .expect("struct without fields");
+        return (name, parse_fields(body));
+    }
+    panic!("only structs are supported");
+}
+
+fn parse_fields(body: TokenStream) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut tokens = body.into_iter().peekable();
+    while tokens.peek().is_some() {
+        let mut msg_type = None;
+        let mut name = None;
+        let mut ty = String::new();
+        let mut depth = 0;
+        for token in tokens.by_ref() {
+            match &token {
+                TokenTree::Punct(punct) if punct.as_char() == ',' && depth == 0 => break,
+                TokenTree::Punct(punct) if punct.as_char() == '#' && name.is_none() => {}
+                TokenTree::Group(group) if name.is_none() => {
+                    let attr = group.stream().to_string();
+                    if let Some(value) = attr.strip_prefix("msg_type") {
+                        msg_type = Some(value.trim_start_matches([' ', '=']).to_owned());
+                    }
+                }
+                TokenTree::Ident(ident) if name.is_none() => {
+                    if ident.to_string() != "pub" {
+                        name = Some(ident.to_string());
+                    }
+                }
+                TokenTree::Punct(punct) if punct.as_char() == ':' && ty.is_empty() => {}
+                _ => {
+                    if let TokenTree::Punct(punct) = &token {
+                        match punct.as_char() {
+                            '<' => depth += 1,
+                            '>' => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                    ty += &token.to_string();
+                    ty += " ";
+                }
+            }
+        }
+        if let Some(name) = name {
+            fields.push(Field { name, ty, msg_type });
+        }
+    }
+    fields
+}
+
+#[proc_macro_derive(DecodeWire, attributes(msg_type))]
+pub fn decode_wire(input: TokenStream) -> TokenStream {
+    let (name, fields) = parse_struct(input);
+    let mut code = format!(
+        "impl crate::core::FromWire for {name} {{\n\
+         fn from_wire<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {{\n"
+    );
+    for field in &fields {
+        code += &format!(
+            "let {} = <{} as crate::core::FromWire>::from_wire(reader)?;\n",
+            field.name, field.ty
+        );
+        if let Some(msg_type) = &field.msg_type {
+            code += &format!(
+                "if {} != {msg_type} {{ return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, \"unexpected message type\")); }}\n",
+                field.name
+            );
+        }
+    }
+    let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+    code += &format!("Ok(Self {{ {} }})\n}}\n}}\n", names.join(", "));
+    code.parse().unwrap()
+}
+
+#[proc_macro_derive(EncodeWire, attributes(msg_type))]
+pub fn encode_wire(input: TokenStream) -> TokenStream {
+    let (name, fields) = parse_struct(input);
+    let mut code = format!(
+        "impl crate::core::ToWire for {name} {{\n\
+         fn to_wire<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {{\n"
+    );
+    for field in &fields {
+        code += &format!(
+            "crate::core::ToWire::to_wire(&self.{}, writer)?;\n",
+            field.name
+        );
+    }
+    code += "Ok(())\n}\n}\n";
+    code.parse().unwrap()
+}
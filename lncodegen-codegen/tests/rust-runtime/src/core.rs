@@ -0,0 +1,47 @@
+//! Wire encoding traits, with the same shape of `fundamentals::core`.
+#![allow(dead_code)]
+use std::io::{Read, Write};
+
+pub trait FromWire: Sized {
+    fn from_wire<R: Read>(reader: &mut R) -> std::io::Result<Self>;
+}
+
+pub trait ToWire {
+    fn to_wire<W: Write>(&self, writer: &mut W) -> std::io::Result<()>;
+}
+
+macro_rules! impl_int {
+    ($($ty:ty),*) => {
+        $(
+            impl FromWire for $ty {
+                fn from_wire<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+                    let mut buf = [0; std::mem::size_of::<$ty>()];
+                    reader.read_exact(&mut buf)?;
+                    Ok(<$ty>::from_be_bytes(buf))
+                }
+            }
+
+            impl ToWire for $ty {
+                fn to_wire<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                    writer.write_all(&self.to_be_bytes())
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64);
+
+impl<const N: usize> FromWire for [u8; N] {
+    fn from_wire<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut buf = [0; N];
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl<const N: usize> ToWire for [u8; N] {
+    fn to_wire<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(self)
+    }
+}
@@ -0,0 +1,75 @@
+//! Types used by the generated code, with the same
+//! names of the `fundamentals::prelude` ones.
+#![allow(dead_code)]
+use std::io::{Read, Write};
+
+use crate::core::{FromWire, ToWire};
+
+pub type ChainHash = [u8; 32];
+pub type ChannelId = [u8; 32];
+pub type Sha256 = [u8; 32];
+pub type Point = [u8; 33];
+pub type Signature = [u8; 64];
+pub type ShortChannelId = [u8; 8];
+
+/// Bytes with the u16 length as prefix.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BitFlag(pub Vec<u8>);
+
+impl FromWire for BitFlag {
+    fn from_wire<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let len = u16::from_wire(reader)?;
+        let mut buf = vec![0; len as usize];
+        reader.read_exact(&mut buf)?;
+        Ok(BitFlag(buf))
+    }
+}
+
+impl ToWire for BitFlag {
+    fn to_wire<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        (self.0.len() as u16).to_wire(writer)?;
+        writer.write_all(&self.0)
+    }
+}
+
+/// Integer encoded in 1 to 9 bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BigSize(pub u64);
+
+impl FromWire for BigSize {
+    fn from_wire<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let (value, min) = match u8::from_wire(reader)? {
+            0xFF => (u64::from_wire(reader)?, 0x100000000),
+            0xFE => (u32::from_wire(reader)? as u64, 0x10000),
+            0xFD => (u16::from_wire(reader)? as u64, 0xFD),
+            value => return Ok(BigSize(value as u64)),
+        };
+        if value < min {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "bigsize not minimally encoded",
+            ));
+        }
+        Ok(BigSize(value))
+    }
+}
+
+impl ToWire for BigSize {
+    fn to_wire<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self.0 {
+            0..=0xFC => (self.0 as u8).to_wire(writer),
+            0xFD..=0xFFFF => {
+                0xFDu8.to_wire(writer)?;
+                (self.0 as u16).to_wire(writer)
+            }
+            0x10000..=0xFFFFFFFF => {
+                0xFEu8.to_wire(writer)?;
+                (self.0 as u32).to_wire(writer)
+            }
+            _ => {
+                0xFFu8.to_wire(writer)?;
+                self.0.to_wire(writer)
+            }
+        }
+    }
+}
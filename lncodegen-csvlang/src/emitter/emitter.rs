@@ -0,0 +1,194 @@
+//! Emitter that write back the AST in the csv format.
+//!
+//! The output is the canonical form of the file: every line
+//! has all the columns of its kind, the data of a declaration
+//! follows the declaration, the tlv streams follow the first
+//! message that use them, and the records are sorted by type.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::parser::ast::{LNMsData, LNMsg, LNMsgType, LNSubType, LNTlvStream};
+
+pub struct Emitter {
+    /// The comments at the end of the file.
+    trailing_trivia: Vec<String>,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emitter {
+    pub fn new() -> Self {
+        Emitter {
+            trailing_trivia: Vec::new(),
+        }
+    }
+
+    /// Build an emitter that write the trivia provided at the end
+    /// of the file, e.g. the `trailing_trivia` of the parser.
+    pub fn with_trailing_trivia(trivia: &[String]) -> Self {
+        Emitter {
+            trailing_trivia: trivia.to_vec(),
+        }
+    }
+
+    fn push_line(code: &mut String, trivia: &[String], columns: &[&str]) {
+        for line in trivia {
+            *code += &format!("{line}\n");
+        }
+        let columns = columns
+            .iter()
+            .map(|column| {
+                if column.contains([',', '"']) {
+                    format!("\"{}\"", column.replace('"', "\"\""))
+                } else {
+                    column.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        *code += &format!("{}\n", columns.join(","));
+    }
+
+    fn emit_fields(
+        code: &mut String,
+        kind: &str,
+        owner: &str,
+        fields: &[LNMsData],
+        data_trivia: &BTreeMap<String, Vec<String>>,
+        data_aliases: &BTreeMap<String, String>,
+    ) {
+        let trivia = |name: &str| data_trivia.get(name).map(Vec::as_slice).unwrap_or_default();
+        // the folded lengths already written, a length can be
+        // shared by more than one array.
+        let mut lengths = BTreeSet::new();
+        for field in fields {
+            let name = field.name();
+            match field {
+                LNMsData::BitfieldStream(_, size, ty) => {
+                    let is_field = LNMsData::is_length_field(fields, size);
+                    if size.parse::<u64>().is_err()
+                        && size != "..."
+                        && !is_field
+                        && lengths.insert(size)
+                    {
+                        // the u16 length was folded inside the bitfield.
+                        Self::push_line(code, trivia(size), &[kind, owner, size, "u16", ""]);
+                    }
+                    let count = if size == "1" { "" } else { size.as_str() };
+                    Self::push_line(code, trivia(name), &[kind, owner, name, ty, count]);
+                }
+                LNMsData::TLVinit(ty, _) => {
+                    Self::push_line(code, trivia(name), &[kind, owner, name, ty, ""]);
+                }
+                _ => {
+                    let ty = match data_aliases.get(name) {
+                        Some(alias) => alias.to_owned(),
+                        None => field.csv_type(),
+                    };
+                    Self::push_line(code, trivia(name), &[kind, owner, name, &ty, ""]);
+                }
+            }
+        }
+    }
+
+    fn emit_msg(code: &mut String, msg: &LNMsg) {
+        let typ = msg.msg_typ.to_string();
+        let mut columns = vec!["msgtype", &msg.msg_name, &typ];
+        if let Some(option) = &msg.option {
+            columns.push(option);
+        } else if msg.is_gossip_query {
+            columns.push("gossip_queries");
+        }
+        Self::push_line(code, &msg.trivia, &columns);
+        Self::emit_fields(
+            code,
+            "msgdata",
+            &msg.msg_name,
+            &msg.msg_data,
+            &msg.data_trivia,
+            &msg.data_aliases,
+        );
+    }
+
+    fn emit_subtype(code: &mut String, subtype: &LNSubType) {
+        Self::push_line(code, &subtype.trivia, &["subtype", &subtype.ty]);
+        Self::emit_fields(
+            code,
+            "subtypedata",
+            &subtype.ty,
+            &subtype.ty_data,
+            &subtype.data_trivia,
+            &subtype.data_aliases,
+        );
+    }
+
+    fn emit_tlv(code: &mut String, tlv: &LNTlvStream) {
+        let mut records = tlv.records.iter().collect::<Vec<_>>();
+        records.sort_by_key(|record| record.type_len);
+        for record in records {
+            let typ = record.type_len.to_string();
+            let columns = ["tlvtype", &tlv.stream_name, &record.type_name, &typ];
+            Self::push_line(code, &record.trivia, &columns);
+            for entry in &record.record_entry {
+                let count = entry.count.as_deref().unwrap_or_default();
+                let columns = [
+                    "tlvdata",
+                    &tlv.stream_name,
+                    &record.type_name,
+                    &entry.entry_name,
+                    &entry.entry_ty,
+                    count,
+                ];
+                Self::push_line(code, &entry.trivia, &columns);
+            }
+        }
+    }
+
+    /// Write the symbol table in the canonical csv form, the subtypes
+    /// come first and the messages are sorted by type.
+    pub fn emit(&self, symbol_table: &BTreeMap<String, LNMsgType>) -> String {
+        let mut code = String::new();
+        for item in symbol_table.values() {
+            if let LNMsgType::SubType(subtype) = item {
+                Self::emit_subtype(&mut code, subtype);
+            }
+        }
+
+        let mut msgs = symbol_table
+            .values()
+            .filter_map(|item| match item {
+                LNMsgType::Msg(msg) => Some(msg),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        msgs.sort_by_key(|msg| msg.msg_typ);
+        let mut streams = BTreeSet::new();
+        for msg in msgs {
+            Self::emit_msg(&mut code, msg);
+            for field in &msg.msg_data {
+                let LNMsData::TLVinit(stream, _) = field else {
+                    continue;
+                };
+                if let Some(LNMsgType::Tlv(tlv)) = symbol_table.get(stream) {
+                    if streams.insert(stream.to_owned()) {
+                        Self::emit_tlv(&mut code, tlv);
+                    }
+                }
+            }
+        }
+        for item in symbol_table.values() {
+            if let LNMsgType::Tlv(tlv) = item {
+                if !streams.contains(&tlv.stream_name) {
+                    Self::emit_tlv(&mut code, tlv);
+                }
+            }
+        }
+
+        for line in &self.trailing_trivia {
+            code += &format!("{line}\n");
+        }
+        code
+    }
+}
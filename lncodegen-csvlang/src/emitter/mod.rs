@@ -0,0 +1,141 @@
+/// Csv emitter implementation!
+#[allow(clippy::module_inception)]
+pub mod emitter;
+
+#[cfg(test)]
+mod test {
+    use crate::emitter::emitter::Emitter;
+    use crate::parser::parser;
+    use crate::scanner::scanner;
+    use crate::scanner::token::parse_number;
+    use std::fs;
+
+    fn format(contents: &str) -> String {
+        let mut scanner = scanner::Scanner::new();
+        let tokens = scanner.scan(contents);
+        let mut parser = parser::Parser::new();
+        parser.parse(&tokens).unwrap();
+        Emitter::with_trailing_trivia(&parser.trailing_trivia).emit(&parser.symbol_table)
+    }
+
+    /// Return the name, the type and the count of the data lines,
+    /// with the numeric counts written in decimal.
+    fn data_columns(contents: &str) -> Vec<Vec<String>> {
+        let mut columns = contents
+            .lines()
+            .filter(|line| line.starts_with("msgdata,") || line.starts_with("subtypedata,"))
+            .map(|line| {
+                let mut columns = line.split(',').map(str::to_owned).collect::<Vec<_>>();
+                columns.resize(5, String::new());
+                if let Some(Ok(count)) = parse_number(&columns[4]) {
+                    columns[4] = count.to_string();
+                }
+                columns
+            })
+            .collect::<Vec<_>>();
+        columns.sort();
+        columns
+    }
+
+    #[test]
+    fn emit_canonical_form() {
+        let contents = "# the ping message\n\
+                        msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16\n\
+                        msgdata,ping,byteslen,u16,\n\
+                        msgdata,ping,ignored,byte,byteslen\n\
+                        tlvtype,init_tlvs,remote_addr,3\n\
+                        tlvdata,init_tlvs,remote_addr,data,byte,...\n\
+                        msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        # the chains of the node\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+                        # end of file\n";
+        let expected = "msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        # the chains of the node\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+                        tlvtype,init_tlvs,remote_addr,3\n\
+                        tlvdata,init_tlvs,remote_addr,data,byte,...\n\
+                        # the ping message\n\
+                        msgtype,ping,18\n\
+                        msgdata,ping,num_pong_bytes,u16,\n\
+                        msgdata,ping,byteslen,u16,\n\
+                        msgdata,ping,ignored,byte,byteslen\n\
+                        # end of file\n";
+        let result = format(contents);
+        assert_eq!(result, expected, "{result}");
+        assert_eq!(format(&result), result);
+    }
+
+    #[test]
+    fn emit_keep_written_form() {
+        let contents = "msgtype,open_channel,32,option_anchors\n\
+                        msgdata,open_channel,funding_satoshis,amount_sat,\n\
+                        msgdata,open_channel,len,u16,\n\
+                        msgdata,open_channel,first,byte,len\n\
+                        msgdata,open_channel,second,byte,len\n\
+                        subtype,funding\n\
+                        subtypedata,funding,amount,amount_sat,\n";
+        let expected = "subtype,funding\n\
+                        subtypedata,funding,amount,amount_sat,\n\
+                        msgtype,open_channel,32,option_anchors\n\
+                        msgdata,open_channel,funding_satoshis,amount_sat,\n\
+                        msgdata,open_channel,len,u16,\n\
+                        msgdata,open_channel,first,byte,len\n\
+                        msgdata,open_channel,second,byte,len\n";
+        let result = format(contents);
+        assert_eq!(result, expected, "{result}");
+        assert_eq!(format(&result), result);
+    }
+
+    #[test]
+    fn emit_bolt_idempotent() {
+        let path_file = std::env::var_os("CSV_PATH").unwrap();
+        let contents = fs::read_to_string(format!("{}/bolt7.csv", path_file.to_str().unwrap()))
+            .expect("Something went wrong reading the file");
+        let result = format(&contents);
+        assert!(result.contains("msgtype,query_short_channel_ids,261\n"));
+        assert_eq!(format(&result), result);
+    }
+
+    #[test]
+    fn emit_specs_round_trip() {
+        let path_file = std::env::var_os("CSV_PATH").unwrap();
+        let mut paths = fs::read_dir(path_file)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            let contents = fs::read_to_string(&path).unwrap();
+            let mut scanner = scanner::Scanner::new();
+            let mut parser = parser::Parser::new();
+            parser.parse_lines(&scanner.scan_lines(&contents)).unwrap();
+            let result =
+                Emitter::with_trailing_trivia(&parser.trailing_trivia).emit(&parser.symbol_table);
+            let mut emitted = parser::Parser::new();
+            emitted.parse_lines(&scanner.scan_lines(&result)).unwrap();
+            assert_eq!(
+                emitted.symbol_table,
+                parser.symbol_table,
+                "{}",
+                path.display()
+            );
+            assert_eq!(
+                data_columns(&result),
+                data_columns(&contents),
+                "{}",
+                path.display()
+            );
+        }
+    }
+}
@@ -25,5 +25,7 @@
 //! ```
 //!
 //! Author: Vincenzo Palazzo <vincenzopalazzo@member.fsf.org>
+pub mod emitter;
+pub mod markdown;
 pub mod parser;
 pub mod scanner;
@@ -0,0 +1,127 @@
+//! Extract the documentation of the messages from the BOLT markdown.
+use std::collections::BTreeMap;
+
+use log::trace;
+
+use crate::parser::ast::{LNMsgDoc, LNMsgType};
+
+/// Base url where the BOLTs are published.
+pub const BOLTS_URL: &str = "https://github.com/lightning/bolts/blob/master";
+
+pub struct SpecDocs {
+    pub docs: BTreeMap<String, LNMsgDoc>,
+}
+
+impl SpecDocs {
+    /// Parse the markdown of a BOLT, where `file_name` is the name of
+    /// the file inside the bolts repository (e.g. `01-messaging.md`).
+    pub fn parse(content: &str, file_name: &str) -> Self {
+        let bolt = bolt_name(file_name);
+        let mut docs = BTreeMap::new();
+        let mut section = String::new();
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut paragraph = String::new();
+        let mut in_code = false;
+        for line in content.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                continue;
+            }
+            if in_code {
+                continue;
+            }
+            if let Some(title) = line.strip_prefix('#') {
+                section = title.trim_start_matches('#').trim().to_owned();
+                paragraphs.clear();
+                paragraph.clear();
+                continue;
+            }
+            // only the message types are at the top level, the tlv types are indented
+            if let Some(msg) = line.strip_prefix("1. type: ") {
+                let Some((_, name)) = msg.split_once('(') else {
+                    continue;
+                };
+                let name = name.trim_end_matches(')').trim_matches('`');
+                if !paragraph.is_empty() {
+                    paragraphs.push(std::mem::take(&mut paragraph));
+                }
+                trace!("found doc of `{name}` inside the section `{section}`");
+                docs.insert(
+                    name.to_owned(),
+                    LNMsgDoc {
+                        bolt: bolt.to_owned(),
+                        section: section.to_owned(),
+                        link: format!("{BOLTS_URL}/{file_name}#{}", anchor(&section)),
+                        prose: paragraphs.join("\n\n"),
+                    },
+                );
+                // the prose of the next message start after this one
+                paragraphs.clear();
+                continue;
+            }
+            let text = line.trim();
+            if text.is_empty() {
+                if !paragraph.is_empty() {
+                    paragraphs.push(std::mem::take(&mut paragraph));
+                }
+            } else if is_prose(line) {
+                if !paragraph.is_empty() {
+                    paragraph.push(' ');
+                }
+                paragraph.push_str(text);
+            }
+        }
+        SpecDocs { docs }
+    }
+
+    pub fn get(&self, msg_name: &str) -> Option<&LNMsgDoc> {
+        self.docs.get(msg_name)
+    }
+
+    /// Attach the documentation to the messages inside the symbol table.
+    pub fn attach(&self, symbol_table: &mut BTreeMap<String, LNMsgType>) {
+        for ast_item in symbol_table.values_mut() {
+            if let LNMsgType::Msg(msg) = ast_item {
+                if let Some(doc) = self.get(&msg.msg_name) {
+                    msg.doc = Some(doc.to_owned());
+                }
+            }
+        }
+    }
+}
+
+/// The lines of the data definition and the lists
+/// are not part of the prose.
+fn is_prose(line: &str) -> bool {
+    let text = line.trim_start();
+    !(line.starts_with(' ')
+        || line.starts_with('\t')
+        || text.starts_with('*')
+        || text.starts_with('-')
+        || text.starts_with('|')
+        || text.starts_with("1.")
+        || text.starts_with("2."))
+}
+
+/// Return the name of the BOLT from the name of the file, e.g.
+/// `01-messaging.md` is `BOLT #1`.
+fn bolt_name(file_name: &str) -> String {
+    let number = file_name
+        .split(['-', '.'])
+        .next()
+        .and_then(|number| number.parse::<u64>().ok());
+    match number {
+        Some(number) => format!("BOLT #{number}"),
+        None => file_name.to_owned(),
+    }
+}
+
+/// Build the anchor that github generates for a markdown title.
+fn anchor(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
@@ -0,0 +1,159 @@
+//! Extract the csv format of the messages from the BOLT markdown,
+//! this is the rust version of the `tools/extract-formats.py` script
+//! that lives inside the bolts repository.
+//!
+//! The markdown uses the following format
+//!
+//! ```ignore
+//! 1. type: 16 (`init`)
+//! 2. data:
+//!    * [`u16`:`gflen`]
+//!    * [`gflen*byte`:`globalfeatures`]
+//!
+//! 1. `tlv_stream`: `init_tlvs`
+//! 2. types:
+//!     1. type: 1 (`networks`)
+//!     2. data:
+//!         * [`...*chain_hash`:`chains`]
+//!
+//! 1. subtype: `witness`
+//! 2. data:
+//!     * [`u16`:`len`]
+//!     * [`len*byte`:`witness_data`]
+//! ```
+use log::trace;
+
+/// The declaration that own the data lines that we are reading.
+enum Owner {
+    Msg(String),
+    SubType(String),
+    TlvStream(String),
+    TlvRecord(String, String),
+}
+
+pub struct Extractor {
+    owner: Option<Owner>,
+    lines: Vec<String>,
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extractor {
+    pub fn new() -> Self {
+        Extractor {
+            owner: None,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Take the content of a BOLT markdown and return the
+    /// csv content that can be passed to the scanner.
+    pub fn extract(&mut self, content: &str) -> String {
+        let mut in_code = false;
+        for line in content.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                continue;
+            }
+            if in_code {
+                continue;
+            }
+            self.extract_line(line);
+        }
+        let mut csv = self.lines.join("\n");
+        if !csv.is_empty() {
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn extract_line(&mut self, line: &str) {
+        let text = line.trim();
+        let is_indented = line.starts_with(' ') || line.starts_with('\t');
+        if let Some(decl) = text.strip_prefix("1. type: ") {
+            let mut values = backquoted(decl);
+            let Some(number) = decl.split_whitespace().next() else {
+                return;
+            };
+            if values.is_empty() {
+                return;
+            }
+            let name = values.remove(0);
+            match &self.owner {
+                Some(Owner::TlvStream(stream)) | Some(Owner::TlvRecord(stream, _))
+                    if is_indented =>
+                {
+                    let stream = stream.to_owned();
+                    self.push(format!("tlvtype,{stream},{name},{number}"));
+                    self.owner = Some(Owner::TlvRecord(stream, name));
+                }
+                _ => {
+                    // the optional value is the feature that enable the message
+                    let mut csv = format!("msgtype,{name},{number}");
+                    if let Some(option) = values.first() {
+                        csv += &format!(",{option}");
+                    }
+                    self.push(csv);
+                    self.owner = Some(Owner::Msg(name));
+                }
+            }
+        } else if let Some(decl) = text.strip_prefix("1. subtype: ") {
+            if let Some(name) = backquoted(decl).first() {
+                self.push(format!("subtype,{name}"));
+                self.owner = Some(Owner::SubType(name.to_owned()));
+            }
+        } else if let Some(decl) = text.strip_prefix("1. `tlv_stream`: ") {
+            if let Some(name) = backquoted(decl).first() {
+                self.owner = Some(Owner::TlvStream(name.to_owned()));
+            }
+        } else if let (Some(data), true) = (text.strip_prefix("* ["), is_indented) {
+            // the data is always indented, otherwise it is a list inside the prose
+            self.extract_data(data);
+        } else if text.starts_with("2. data:") || text.starts_with("2. types:") || text.is_empty() {
+            // nothing to do, the data follow
+        } else if !is_indented {
+            // the prose terminate the declaration
+            self.owner = None;
+        }
+    }
+
+    /// Extract a data line in the format [`<count>*<type>`:`<name>`]
+    fn extract_data(&mut self, data: &str) {
+        let values = backquoted(data);
+        let [ty, name] = &values[..] else {
+            return;
+        };
+        let (ty, count) = match ty.split_once('*') {
+            Some((count, ty)) => (ty, count),
+            None => (ty.as_str(), ""),
+        };
+        let prefix = match &self.owner {
+            Some(Owner::Msg(msg)) => format!("msgdata,{msg}"),
+            Some(Owner::SubType(subtype)) => format!("subtypedata,{subtype}"),
+            Some(Owner::TlvRecord(stream, record)) => format!("tlvdata,{stream},{record}"),
+            Some(Owner::TlvStream(_)) | None => {
+                trace!("data `{data}` without a declaration, skipping it");
+                return;
+            }
+        };
+        self.push(format!("{prefix},{name},{ty},{count}"));
+    }
+
+    fn push(&mut self, line: String) {
+        trace!("extracted line: {line}");
+        self.lines.push(line);
+    }
+}
+
+/// Return all the values between backquote inside the line.
+fn backquoted(line: &str) -> Vec<String> {
+    line.split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|value| value.to_owned())
+        .collect()
+}
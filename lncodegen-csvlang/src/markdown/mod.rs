@@ -0,0 +1,160 @@
+/// BOLT markdown implementation!
+pub mod docs;
+pub mod extractor;
+
+#[cfg(test)]
+mod test {
+    use crate::markdown::docs::SpecDocs;
+    use crate::markdown::extractor::Extractor;
+    use crate::parser::ast::LNMsgType;
+    use crate::parser::parser;
+    use crate::scanner::scanner;
+
+    const MESSAGING: &str = "# BOLT #1: Base Protocol
+
+## Setup Messages
+
+### The `init` Message
+
+Once authentication is complete, the first message reveals the features
+supported or required by this node, even if this is a reconnection.
+
+1. type: 16 (`init`)
+2. data:
+   * [`u16`:`gflen`]
+   * [`gflen*byte`:`globalfeatures`]
+
+1. `tlv_stream`: `init_tlvs`
+2. types:
+    1. type: 1 (`networks`)
+    2. data:
+        * [`...*chain_hash`:`chains`]
+
+The optional `networks` indicates the chains the node is interested in.
+
+### The `error` and `warning` Messages
+
+For simplicity of diagnosis, it's often useful to tell a peer that something is incorrect.
+
+1. type: 17 (`error`)
+2. data:
+   * [`channel_id`:`channel_id`]
+
+1. type: 1 (`warning`)
+2. data:
+   * [`channel_id`:`channel_id`]
+";
+
+    const GOSSIP: &str = "### The `query_short_channel_ids`/`reply_short_channel_ids_end` Messages
+
+1. type: 261 (`query_short_channel_ids`) (`gossip_queries`)
+2. data:
+    * [`chain_hash`:`chain_hash`]
+    * [`u16`:`len`]
+    * [`len*byte`:`encoded_short_ids`]
+    * [`query_short_channel_ids_tlvs`:`tlvs`]
+
+1. `tlv_stream`: `query_short_channel_ids_tlvs`
+2. types:
+    1. type: 1 (`query_flags`)
+    2. data:
+        * [`byte`:`encoding_type`]
+        * [`...*byte`:`encoded_query_flags`]
+    1. type: 2 (`dummy`)
+
+The `chain_hash` is the genesis block of the chain.
+
+1. subtype: `witness`
+2. data:
+    * [`u16`:`len`]
+    * [`len*byte`:`witness_data`]
+
+* [`u16`:`not_a_field`]
+";
+
+    #[test]
+    fn parse_message_docs() {
+        let docs = SpecDocs::parse(MESSAGING, "01-messaging.md");
+        let init = docs.get("init").unwrap();
+        assert_eq!(init.bolt, "BOLT #1");
+        assert_eq!(init.section, "The `init` Message");
+        assert_eq!(
+            init.link,
+            "https://github.com/lightning/bolts/blob/master/01-messaging.md#the-init-message"
+        );
+        assert_eq!(
+            init.prose,
+            "Once authentication is complete, the first message reveals the features \
+             supported or required by this node, even if this is a reconnection."
+        );
+        // the tlv types are not messages
+        assert!(docs.get("networks").is_none());
+        let error = docs.get("error").unwrap();
+        assert!(error.prose.starts_with("For simplicity of diagnosis"));
+        assert_eq!(docs.get("warning").unwrap().prose, "");
+    }
+
+    #[test]
+    fn attach_docs_to_symbol_table() {
+        let contents = "msgtype,init,16\nmsgdata,init,gflen,u16,\n";
+        let mut scanner = scanner::Scanner::new();
+        let tokens = scanner.scan(contents);
+        let mut parser = parser::Parser::new();
+        parser.parse(&tokens).unwrap();
+        let docs = SpecDocs::parse(MESSAGING, "01-messaging.md");
+        docs.attach(&mut parser.symbol_table);
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("init") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(msg.doc.as_ref().unwrap().section, "The `init` Message");
+    }
+
+    #[test]
+    fn extract_messages() {
+        let csv = Extractor::new().extract(MESSAGING);
+        assert_eq!(
+            csv,
+            "msgtype,init,16\n\
+             msgdata,init,gflen,u16,\n\
+             msgdata,init,globalfeatures,byte,gflen\n\
+             tlvtype,init_tlvs,networks,1\n\
+             tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+             msgtype,error,17\n\
+             msgdata,error,channel_id,channel_id,\n\
+             msgtype,warning,1\n\
+             msgdata,warning,channel_id,channel_id,\n"
+        );
+    }
+
+    #[test]
+    fn extract_tlv_and_subtypes() {
+        let csv = Extractor::new().extract(GOSSIP);
+        assert_eq!(
+            csv,
+            "msgtype,query_short_channel_ids,261,gossip_queries\n\
+             msgdata,query_short_channel_ids,chain_hash,chain_hash,\n\
+             msgdata,query_short_channel_ids,len,u16,\n\
+             msgdata,query_short_channel_ids,encoded_short_ids,byte,len\n\
+             msgdata,query_short_channel_ids,tlvs,query_short_channel_ids_tlvs,\n\
+             tlvtype,query_short_channel_ids_tlvs,query_flags,1\n\
+             tlvdata,query_short_channel_ids_tlvs,query_flags,encoding_type,byte,\n\
+             tlvdata,query_short_channel_ids_tlvs,query_flags,encoded_query_flags,byte,...\n\
+             tlvtype,query_short_channel_ids_tlvs,dummy,2\n\
+             subtype,witness\n\
+             subtypedata,witness,len,u16,\n\
+             subtypedata,witness,witness_data,byte,len\n"
+        );
+    }
+
+    #[test]
+    fn parse_extracted_messages() {
+        let csv = Extractor::new().extract(MESSAGING);
+        let mut scanner = scanner::Scanner::new();
+        let tokens = scanner.scan(&csv);
+        let mut parser = parser::Parser::new();
+        parser.parse(&tokens).unwrap();
+        assert!(parser.symbol_table.contains_key("init"));
+        assert!(parser.symbol_table.contains_key("init_tlvs"));
+        assert!(parser.symbol_table.contains_key("warning"));
+    }
+}
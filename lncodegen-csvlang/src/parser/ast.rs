@@ -1,11 +1,12 @@
 //! Abstract Syntax Tree implementation
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::vec::Vec;
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum LNMsgType {
     Msg(LNMsg),
-    Tlv(LNTlvRecord),
+    Tlv(LNTlvStream),
     SubType(LNSubType),
 }
 
@@ -17,6 +18,30 @@ pub struct LNMsg {
     pub msg_name: String,
     pub msg_data: Vec<LNMsData>,
     pub is_gossip_query: bool,
+    /// The option of the `msgtype` line as written in the csv file,
+    /// e.g. `gossip_queries`.
+    pub option: Option<String>,
+    /// Documentation of the message taken from the spec, if any.
+    pub doc: Option<LNMsgDoc>,
+    /// The comments before the `msgtype` line.
+    pub trivia: Vec<String>,
+    /// The comments before the `msgdata` lines, by field name.
+    pub data_trivia: BTreeMap<String, Vec<String>>,
+    /// The alias types of the `msgdata` lines, e.g. `amount_sat`, by field name.
+    pub data_aliases: BTreeMap<String, String>,
+}
+
+/// Documentation of a message taken from the BOLT markdown.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LNMsgDoc {
+    /// Name of the BOLT, e.g. `BOLT #1`.
+    pub bolt: String,
+    /// Title of the section where the message is defined.
+    pub section: String,
+    /// Link to the section of the BOLT.
+    pub link: String,
+    /// The prose that describe the message.
+    pub prose: String,
 }
 
 /// All the Msg Data supported by the LN
@@ -25,6 +50,8 @@ pub enum LNMsData {
     Uint16(String),
     Uint32(String),
     Uint64(String),
+    /// Integer encoded in 1 to 9 bytes, as the types of the tlv records.
+    BigSize(String),
     /// Chain Hash type
     ChainHash(String, String),
     /// Channel id
@@ -33,11 +60,117 @@ pub enum LNMsData {
     ShortChannelId(String),
     Point(String),
     Sha256(String),
-    /// The array can be bounded or we can read till the EOF
-    BitfieldStream(String, String),
+    /// The array can be bounded or we can read till the EOF,
+    /// with the name, the count and the type of the elements
+    /// as written in the csv file, e.g. `byte` or `signature`.
+    BitfieldStream(String, String, String),
     TLVinit(String, String),
 }
 
+impl LNMsData {
+    /// Return the name of the field as declared in the csv file.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Uint16(name)
+            | Self::Uint32(name)
+            | Self::Uint64(name)
+            | Self::BigSize(name)
+            | Self::ChainHash(name, _)
+            | Self::ChannelId(name)
+            | Self::Signature(name)
+            | Self::ShortChannelId(name)
+            | Self::Point(name)
+            | Self::Sha256(name)
+            | Self::BitfieldStream(name, ..)
+            | Self::TLVinit(_, name) => name,
+        }
+    }
+
+    /// Return true if the count `size` of an array is one of the `fields`,
+    /// otherwise the parser folded the `u16` length inside the array, and
+    /// the length is written on the wire just before the elements.
+    pub fn is_length_field(fields: &[LNMsData], size: &str) -> bool {
+        fields.iter().any(|field| field.name() == size)
+    }
+
+    /// Return the size in bytes of a single element of an array with
+    /// the type `ty`, if the type is a builtin type with a fixed size.
+    pub fn element_size(ty: &str) -> Option<u64> {
+        match ty {
+            "byte" | "u8" => Some(1),
+            "u16" => Some(2),
+            "u32" => Some(4),
+            "u64" | "short_channel_id" => Some(8),
+            "chain_hash" | "channel_id" | "sha256" => Some(32),
+            "point" => Some(33),
+            "signature" => Some(64),
+            _ => None,
+        }
+    }
+
+    /// Return the size in bytes of the field on the wire
+    /// when it is known without looking at the message content.
+    pub fn fixed_size(&self) -> Option<u64> {
+        match self {
+            Self::Uint16(_) => Some(2),
+            Self::Uint32(_) => Some(4),
+            Self::Uint64(_) | Self::ShortChannelId(_) => Some(8),
+            Self::ChainHash(..) | Self::ChannelId(_) | Self::Sha256(_) => Some(32),
+            Self::Point(_) => Some(33),
+            Self::Signature(_) => Some(64),
+            Self::BitfieldStream(_, size, ty) => {
+                let size = size.parse::<u64>().ok()?;
+                Some(size * Self::element_size(ty)?)
+            }
+            Self::BigSize(_) | Self::TLVinit(..) => None,
+        }
+    }
+
+    /// Return the type of the field as written in the csv file,
+    /// e.g. `u16`, `byte[32]` or the name of the tlv stream.
+    pub fn csv_type(&self) -> String {
+        match self {
+            Self::Uint16(_) => "u16".to_owned(),
+            Self::Uint32(_) => "u32".to_owned(),
+            Self::Uint64(_) => "u64".to_owned(),
+            Self::BigSize(_) => "bigsize".to_owned(),
+            Self::ChainHash(..) => "chain_hash".to_owned(),
+            Self::ChannelId(_) => "channel_id".to_owned(),
+            Self::Signature(_) => "signature".to_owned(),
+            Self::ShortChannelId(_) => "short_channel_id".to_owned(),
+            Self::Point(_) => "point".to_owned(),
+            Self::Sha256(_) => "sha256".to_owned(),
+            Self::BitfieldStream(_, size, ty) => format!("{ty}[{size}]"),
+            Self::TLVinit(stream, _) => stream.to_owned(),
+        }
+    }
+}
+
+/// A tlv_stream is a series of (possibly zero) tlv_records,
+/// ordered by type.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LNTlvStream {
+    pub stream_name: String,
+    pub records: Vec<LNTlvRecord>,
+}
+
+impl LNTlvStream {
+    pub fn new(name: &str) -> Self {
+        LNTlvStream {
+            stream_name: name.to_string(),
+            records: Vec::new(),
+        }
+    }
+
+    /// add a new record, keeping the records ordered by type.
+    pub fn add_record(&mut self, record: &LNTlvRecord) {
+        let pos = self
+            .records
+            .partition_point(|other| other.type_len <= record.type_len);
+        self.records.insert(pos, record.to_owned());
+    }
+}
+
 ///
 /// A tlv_record represents a single field, encoded in the form:
 /// `[bigsize: type]`
@@ -49,6 +182,8 @@ pub struct LNTlvRecord {
     pub type_name: String,
     pub type_len: u64,
     pub record_entry: Vec<LNTlvEntry>,
+    /// The comments before the `tlvtype` line.
+    pub trivia: Vec<String>,
 }
 
 impl LNTlvRecord {
@@ -58,6 +193,7 @@ impl LNTlvRecord {
             type_name: name.to_string(),
             type_len: len,
             record_entry: Vec::new(),
+            trivia: Vec::new(),
         }
     }
 
@@ -70,7 +206,12 @@ impl LNTlvRecord {
 pub struct LNTlvEntry {
     pub entry_name: String,
     pub entry_ty: String,
+    /// The optional count of the entry, that can be a fixed
+    /// value, a named value read before, or `...`.
+    pub count: Option<String>,
     pub encoding: Option<EncodingType>,
+    /// The comments before the `tlvdata` line.
+    pub trivia: Vec<String>,
 }
 
 impl LNTlvEntry {
@@ -78,11 +219,22 @@ impl LNTlvEntry {
         LNTlvEntry {
             entry_name: name.to_string(),
             entry_ty: ty.to_string(),
+            count: None,
             encoding: None,
+            trivia: Vec::new(),
         }
     }
 
     pub fn add_encoding() {}
+
+    /// Return the type of the entry as written in the csv file,
+    /// with the count if any, e.g. `chain_hash[...]`.
+    pub fn csv_type(&self) -> String {
+        match &self.count {
+            Some(count) => format!("{}[{count}]", self.entry_ty),
+            None => self.entry_ty.to_owned(),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -100,6 +252,11 @@ impl LNMsg {
             msg_name: msg_name.to_string(),
             msg_data: Vec::new(),
             is_gossip_query: false,
+            option: None,
+            doc: None,
+            trivia: Vec::new(),
+            data_trivia: BTreeMap::new(),
+            data_aliases: BTreeMap::new(),
         }
     }
 
@@ -107,6 +264,11 @@ impl LNMsg {
         self.msg_data.push(data.clone());
     }
 
+    /// See [`LNMsData::is_length_field`], with the fields of the message.
+    pub fn is_length_field(&self, size: &str) -> bool {
+        LNMsData::is_length_field(&self.msg_data, size)
+    }
+
     pub fn peek(&self) -> Option<&LNMsData> {
         self.msg_data.last()
     }
@@ -121,6 +283,12 @@ impl LNMsg {
 pub struct LNSubType {
     pub ty: String,
     pub ty_data: Vec<LNMsData>,
+    /// The comments before the `subtype` line.
+    pub trivia: Vec<String>,
+    /// The comments before the `subtypedata` lines, by field name.
+    pub data_trivia: BTreeMap<String, Vec<String>>,
+    /// The alias types of the `subtypedata` lines, by field name.
+    pub data_aliases: BTreeMap<String, String>,
 }
 
 impl LNSubType {
@@ -129,6 +297,9 @@ impl LNSubType {
         LNSubType {
             ty: ty.to_string(),
             ty_data: Vec::new(),
+            trivia: Vec::new(),
+            data_trivia: BTreeMap::new(),
+            data_aliases: BTreeMap::new(),
         }
     }
 
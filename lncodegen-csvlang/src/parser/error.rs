@@ -0,0 +1,88 @@
+//! Errors reported by the parser, with the line where they are found.
+use std::fmt::Display;
+
+use crate::scanner::token::NumberError;
+
+/// An error in a line of the csv file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: u64,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// The number of fields is not in the arity of the line kind.
+    Arity {
+        kind: String,
+        min: usize,
+        max: usize,
+        found: usize,
+    },
+    /// A field that is not optional has no value, the field
+    /// position starts from 1.
+    EmptyField { kind: String, field: usize },
+    /// A numeric field that is not an unsigned integer of 64 bits.
+    InvalidNumber {
+        kind: String,
+        field: usize,
+        value: String,
+        error: NumberError,
+    },
+    /// A value that is not expected in its position, e.g.
+    /// a name instead of the type of a message.
+    UnknownToken(String),
+    /// A `msgdata` line before its `msgtype`.
+    UnknownMessage(String),
+    /// A `subtypedata` line before its `subtype`.
+    UnknownSubtype(String),
+    /// A `tlvdata` line before its `tlvtype`.
+    UnknownRecord { stream: String, record: String },
+    /// A `tlvtype` line with the name of a message or of a subtype.
+    NotTlvStream(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Arity {
+                kind,
+                min,
+                max,
+                found,
+            } => write!(
+                f,
+                "`{kind}` expects from {min} to {max} fields, found {found}"
+            ),
+            Self::EmptyField { kind, field } => {
+                write!(f, "the field {field} of `{kind}` is empty")
+            }
+            Self::InvalidNumber {
+                kind,
+                field,
+                value,
+                error,
+            } => write!(f, "the field {field} of `{kind}` {error}: `{value}`"),
+            Self::UnknownToken(value) => write!(f, "unexpected value `{value}`"),
+            Self::UnknownMessage(msg) => write!(f, "`msgdata` of the unknown message `{msg}`"),
+            Self::UnknownSubtype(subtype) => {
+                write!(f, "`subtypedata` of the unknown subtype `{subtype}`")
+            }
+            Self::UnknownRecord { stream, record } => {
+                write!(
+                    f,
+                    "`tlvdata` of the unknown record `{record}` of `{stream}`"
+                )
+            }
+            Self::NotTlvStream(name) => write!(f, "`{name}` is not a tlv stream"),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,5 +1,6 @@
 /// Parse implementation!
 pub mod ast;
+pub mod error;
 #[allow(clippy::module_inception)]
 pub mod parser;
 
@@ -8,9 +9,10 @@ mod test {
 
     use crate::parser::ast;
     use crate::parser::ast::LNMsgType;
+    use crate::parser::error::{ParseError, ParseErrorKind};
     use crate::parser::parser;
     use crate::scanner::scanner;
-    use crate::scanner::token::CSVToken;
+    use crate::scanner::token::{CSVToken, NumberError};
     use std::fs;
     use std::sync::Once;
 
@@ -30,7 +32,7 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result: Vec<CSVToken> = scanner.scan(contents);
         let mut parser = parser::Parser::new();
-        parser.parse(&result);
+        parser.parse(&result).unwrap();
         if let LNMsgType::Msg(msg) = parser.symbol_table.get("init").unwrap() {
             assert_eq!(msg.msg_data[0], ast::LNMsData::Uint16("gflen".to_string()));
         } else {
@@ -39,7 +41,6 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     fn parse_simple_failure_line() {
         init();
         let contents = "msgtype,init,16 \
@@ -47,7 +48,7 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result: Vec<CSVToken> = scanner.scan(contents);
         let mut parser = parser::Parser::new();
-        parser.parse(&result);
+        assert!(parser.parse(&result).is_err());
     }
 
     #[test]
@@ -68,17 +69,31 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result: Vec<CSVToken> = scanner.scan(contents);
         let mut parser = parser::Parser::new();
-        parser.parse(&result);
+        parser.parse(&result).unwrap();
         // check bytes line
         match parser.symbol_table.get("init").unwrap() {
             LNMsgType::Msg(msg) => assert_eq!(
                 msg.to_owned().msg_data[0],
-                ast::LNMsData::BitfieldStream("globalfeatures".to_string(), "gflen".to_string())
+                ast::LNMsData::BitfieldStream(
+                    "globalfeatures".to_string(),
+                    "gflen".to_string(),
+                    "byte".to_string()
+                )
             ),
             _ => panic!("wrong value in the symbol table"),
         }
         // check TLV line
-        assert!(parser.symbol_table.contains_key("init_tlvs"));
+        match parser.symbol_table.get("init_tlvs").unwrap() {
+            LNMsgType::Tlv(stream) => {
+                assert_eq!(stream.records.len(), 2);
+                assert_eq!(stream.records[0].type_name, "networks");
+                let entry = &stream.records[0].record_entry[0];
+                assert_eq!(entry.entry_ty, "chain_hash");
+                assert_eq!(entry.count, Some("...".to_owned()));
+                assert_eq!(stream.records[1].type_name, "remote_addr");
+            }
+            _ => panic!("wrong value in the symbol table"),
+        }
     }
 
     #[test]
@@ -90,8 +105,212 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result = scanner.scan(&contents);
         let mut parser = parser::Parser::new();
-        parser.parse(&result);
+        parser.parse(&result).unwrap();
 
         // TODO: make check
     }
+
+    #[test]
+    fn parse_trivia() {
+        init();
+        let contents = "# the init message\n\
+                        msgtype,init,16\n\
+                        msgdata,init,gflen,u16,\n\
+                        # legacy features\n\
+                        msgdata,init,globalfeatures,byte,gflen\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        unknown,line\n\
+                        tlvtype,init_tlvs,networks,1\n\
+                        # the chains\n\
+                        tlvdata,init_tlvs,networks,chains,chain_hash,...\n\
+                        # end of file\n";
+        let mut scanner = scanner::Scanner::new();
+        let result = scanner.scan(contents);
+        let mut parser = parser::Parser::new();
+        parser.parse(&result).unwrap();
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("init") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(msg.trivia, ["# the init message"]);
+        assert_eq!(msg.data_trivia["globalfeatures"], ["# legacy features"]);
+        let Some(LNMsgType::Tlv(stream)) = parser.symbol_table.get("init_tlvs") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(stream.records[0].trivia, ["unknown,line"]);
+        assert_eq!(stream.records[0].record_entry[0].trivia, ["# the chains"]);
+        assert_eq!(parser.trailing_trivia, ["# end of file"]);
+    }
+
+    #[test]
+    fn parse_empty_msg_name() {
+        init();
+        let contents = "msgtype, ,16\n";
+        let mut scanner = scanner::Scanner::new();
+        let result = scanner.scan(contents);
+        let mut parser = parser::Parser::new();
+        let err = parser.parse(&result).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: the field 2 of `msgtype` is empty");
+    }
+
+    #[test]
+    fn parse_wrong_arity() {
+        init();
+        let contents = "tlvtype,init_tlvs,networks,1\n\
+                        tlvdata,init_tlvs,networks,chains\n";
+        let mut scanner = scanner::Scanner::new();
+        let result = scanner.scan(contents);
+        let mut parser = parser::Parser::new();
+        let err = parser.parse(&result).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: `tlvdata` expects from 5 to 6 fields, found 4"
+        );
+    }
+
+    #[test]
+    fn parse_subtypes() {
+        init();
+        let contents = "subtype,witness\n\
+                        subtypedata,witness,len,u16,\n\
+                        subtypedata,witness,witness_data,byte,len\n\
+                        msgtype,tx_signatures,71\n\
+                        msgdata,tx_signatures,channel_id,channel_id,\n\
+                        msgdata,tx_signatures,txid,sha256\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        let mut parser = parser::Parser::new();
+        parser.parse_lines(&lines).unwrap();
+        let Some(LNMsgType::SubType(subtype)) = parser.symbol_table.get("witness") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(
+            subtype.ty_data,
+            [ast::LNMsData::BitfieldStream(
+                "witness_data".to_owned(),
+                "len".to_owned(),
+                "byte".to_owned()
+            )]
+        );
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("tx_signatures") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(msg.msg_data[1], ast::LNMsData::Sha256("txid".to_owned()));
+    }
+
+    #[test]
+    fn parse_data_without_declaration() {
+        init();
+        let contents = "msgtype,init,16\n\
+                        msgdata,ping,num_pong_bytes,u16,\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        let mut parser = parser::Parser::new();
+        let err = parser.parse_lines(&lines).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: `msgdata` of the unknown message `ping`"
+        );
+    }
+
+    #[test]
+    fn parse_hex_types() {
+        init();
+        let contents = "msgtype,init,0x10\n\
+                        msgdata,init,tlvs,init_tlvs,\n\
+                        tlvtype,init_tlvs,remote_addr,0x3\n\
+                        tlvdata,init_tlvs,remote_addr,data,byte,0x20\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        let mut parser = parser::Parser::new();
+        parser.parse_lines(&lines).unwrap();
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("init") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(msg.msg_typ, 16);
+        let Some(LNMsgType::Tlv(tlv)) = parser.symbol_table.get("init_tlvs") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(tlv.records[0].type_len, 3);
+        assert_eq!(tlv.records[0].record_entry[0].count.as_deref(), Some("32"));
+    }
+
+    #[test]
+    fn parse_float_type() {
+        init();
+        let contents = "msgtype,init,1e3\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        let mut parser = parser::Parser::new();
+        let err = parser.parse_lines(&lines).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::InvalidNumber {
+                    kind: "msgtype".to_owned(),
+                    field: 3,
+                    value: "1e3".to_owned(),
+                    error: NumberError::NotInteger,
+                },
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "line 1: the field 3 of `msgtype` is not an unsigned integer: `1e3`"
+        );
+    }
+
+    #[test]
+    fn parse_failure_msg_types() {
+        init();
+        let contents = "msgtype,invalid_onion_version,BADONION|PERM|4\n\
+                        msgtype,temporary_node_failure,NODE|2\n\
+                        msgtype,unknown_flag,LOUD|2\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        let mut parser = parser::Parser::new();
+        let err = parser.parse_lines(&lines).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: unexpected value `LOUD|2`");
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("invalid_onion_version") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(msg.msg_typ, 0xC004);
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("temporary_node_failure") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(msg.msg_typ, 0x2002);
+    }
+
+    #[test]
+    fn parse_builtin_arrays() {
+        init();
+        let contents = "msgtype,foo,1\n\
+                        msgdata,foo,num_points,u16,\n\
+                        msgdata,foo,points,point,num_points\n\
+                        msgdata,foo,amounts,u64,2\n\
+                        msgdata,foo,type,bigsize,\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        let mut parser = parser::Parser::new();
+        parser.parse_lines(&lines).unwrap();
+        let Some(LNMsgType::Msg(msg)) = parser.symbol_table.get("foo") else {
+            panic!("wrong value in the symbol table");
+        };
+        assert_eq!(
+            msg.msg_data,
+            [
+                ast::LNMsData::BitfieldStream(
+                    "points".to_owned(),
+                    "num_points".to_owned(),
+                    "point".to_owned()
+                ),
+                ast::LNMsData::BitfieldStream(
+                    "amounts".to_owned(),
+                    "2".to_owned(),
+                    "u64".to_owned()
+                ),
+                ast::LNMsData::BigSize("type".to_owned()),
+            ]
+        );
+    }
 }
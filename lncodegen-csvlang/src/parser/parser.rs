@@ -1,19 +1,35 @@
-//! Core Parser implementation for the csv tokens
+//! Core Parser implementation for the csv lines
 use log::trace;
 use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
 
 use crate::parser::ast::LNMsData;
 use crate::parser::ast::LNMsg;
 use crate::parser::ast::LNTlvEntry;
 use crate::parser::ast::LNTlvRecord;
-use crate::scanner::token::{CSVToken, CSVTokenType};
+use crate::parser::ast::LNTlvStream;
+use crate::scanner::token::{parse_number, CSVLine, CSVToken, CSVTokenType};
 
 use super::ast::LNMsgType;
 use super::ast::LNSubType;
+use super::error::{ParseError, ParseErrorKind};
+
+/// The flags of the failure messages of BOLT 4, that
+/// are part of the type, e.g. `PERM|15`.
+const FAILURE_FLAGS: [(&str, u64); 4] = [
+    ("BADONION", 0x8000),
+    ("PERM", 0x4000),
+    ("NODE", 0x2000),
+    ("UPDATE", 0x1000),
+];
+
+/// The cln alias types of an `u64`.
+const U64_ALIASES: [&str; 1] = ["amount_sat"];
 
 pub struct Parser {
     pub symbol_table: BTreeMap<String, LNMsgType>,
-    pos: usize,
+    /// The comments and the unknown lines at the end of the file.
+    pub trailing_trivia: Vec<String>,
 }
 
 impl Default for Parser {
@@ -26,8 +42,8 @@ impl Parser {
     /// Build a new parser
     pub fn new() -> Self {
         Self {
-            pos: 0,
             symbol_table: BTreeMap::new(),
+            trailing_trivia: Vec::new(),
         }
     }
 
@@ -36,293 +52,356 @@ impl Parser {
             .insert(msg.msg_name.to_string(), LNMsgType::Msg(msg.to_owned()));
     }
 
-    fn symbol_table_add_tlv(&mut self, tlv: &LNTlvRecord) {
-        self.symbol_table
-            .insert(tlv.stream_name.to_string(), LNMsgType::Tlv(tlv.to_owned()));
+    /// Add the tlv record to the stream that it belongs to, the
+    /// stream is created the first time that a record is found.
+    fn symbol_table_add_tlv(&mut self, tlv: &LNTlvRecord) -> Result<(), ParseErrorKind> {
+        let stream = self
+            .symbol_table
+            .entry(tlv.stream_name.to_string())
+            .or_insert_with(|| LNMsgType::Tlv(LNTlvStream::new(&tlv.stream_name)));
+        match stream {
+            LNMsgType::Tlv(stream) => stream.add_record(tlv),
+            _ => return Err(ParseErrorKind::NotTlvStream(tlv.stream_name.to_owned())),
+        }
+        Ok(())
     }
 
-    /// Take the element in the current position of the stream
-    fn peek<'p>(&self, tokens: &'p [CSVToken]) -> &'p CSVToken {
-        &tokens[self.pos]
+    /// Check that the number of fields is in the arity of the line kind,
+    /// that the fields that are not optional have a value, and that
+    /// there are no invalid numbers.
+    fn check_arity(line: &CSVLine, arity: RangeInclusive<usize>) -> Result<(), ParseErrorKind> {
+        let kind = line.tokens[0].val.to_owned();
+        let len = line.tokens.len();
+        if !arity.contains(&len) {
+            return Err(ParseErrorKind::Arity {
+                kind,
+                min: *arity.start(),
+                max: *arity.end(),
+                found: len,
+            });
+        }
+        if let Some(idx) = (0..*arity.start()).find(|idx| line.field(*idx).is_none()) {
+            return Err(ParseErrorKind::EmptyField {
+                kind,
+                field: idx + 1,
+            });
+        }
+        for (idx, token) in line.tokens.iter().enumerate() {
+            if let CSVTokenType::InvalidNumber(error) = &token.ty {
+                return Err(ParseErrorKind::InvalidNumber {
+                    kind,
+                    field: idx + 1,
+                    value: token.val.to_owned(),
+                    error: error.to_owned(),
+                });
+            }
+        }
+        Ok(())
     }
 
-    /// Take the element in the current position of the stream
-    /// and increase the position by one
-    fn advance<'p>(&mut self, tokens: &'p [CSVToken]) -> &'p CSVToken {
-        self.pos += 1;
-        &tokens[self.pos - 1]
+    /// Return the value of a field that is not optional.
+    fn value(line: &CSVLine, idx: usize) -> &str {
+        &line.tokens[idx].val
     }
 
-    /// Return the last element insert inside the token view.
-    fn lookup_last<'p>(&self, tokens: &'p [CSVToken]) -> Option<&'p CSVToken> {
-        tokens.get(self.pos - 2)
+    /// Return the value of a numeric field that is not optional.
+    fn number(line: &CSVLine, idx: usize) -> Result<u64, ParseErrorKind> {
+        let token = &line.tokens[idx];
+        match (&token.ty, parse_number(&token.val)) {
+            (CSVTokenType::Number, Some(Ok(number))) => Ok(number),
+            _ => Err(ParseErrorKind::UnknownToken(token.val.to_owned())),
+        }
     }
 
-    fn lookup_next<'p>(&self, tokens: &'p [CSVToken]) -> Option<&'p CSVToken> {
-        tokens.get(self.pos + 1)
+    /// Return the type of a message, that can have the
+    /// flags of the failure messages, e.g. `PERM|NODE|2`.
+    fn msg_type(line: &CSVLine, idx: usize) -> Result<u64, ParseErrorKind> {
+        let token = &line.tokens[idx];
+        let Some((flags, number)) = token.val.rsplit_once('|') else {
+            return Self::number(line, idx);
+        };
+        let mut msg_type = match parse_number(number) {
+            Some(Ok(number)) => number,
+            _ => return Err(ParseErrorKind::UnknownToken(token.val.to_owned())),
+        };
+        for flag in flags.split('|') {
+            let Some((_, value)) = FAILURE_FLAGS.iter().find(|(name, _)| *name == flag) else {
+                return Err(ParseErrorKind::UnknownToken(token.val.to_owned()));
+            };
+            msg_type |= value;
+        }
+        Ok(msg_type)
+    }
+
+    /// Return the count of a data line if any, a numeric count
+    /// is written in decimal, e.g. `0x20` is `32`.
+    fn count(line: &CSVLine, idx: usize) -> Result<Option<String>, ParseErrorKind> {
+        let Some(count) = line.field(idx) else {
+            return Ok(None);
+        };
+        match count.ty {
+            CSVTokenType::Number => Ok(Some(Self::number(line, idx)?.to_string())),
+            _ => Ok(Some(count.val.to_owned())),
+        }
     }
 
     /// Parse a message type line of the csv file, where the format looks like
     /// the following one:
     ///
-    /// `msgtype,init,16`
-    fn parse_msg_typ(&mut self, tokens: &[CSVToken]) -> LNMsg {
-        let msg_name = self.advance(tokens);
-        let msg_type = self.advance(tokens);
-        match msg_type.ty {
-            CSVTokenType::Number => {
-                let mut msg = LNMsg::new(
-                    msg_type.val.parse::<u64>().unwrap(),
-                    msg_name.val.to_owned().as_str(),
-                );
-                if self.peek(tokens).val == "gossip_queries" {
-                    msg.is_gossip_query = true;
-                    let _ = self.advance(tokens);
-                }
-                msg
-            }
-            _ => panic!("Unknown Token {:?}", self.peek(tokens)),
+    /// `msgtype,init,16[,gossip_queries]`
+    ///
+    /// The type can be written in hex too, e.g. `msgtype,init,0x10`.
+    fn parse_msg_typ(&mut self, line: &CSVLine) -> Result<(), ParseErrorKind> {
+        Self::check_arity(line, 3..=4)?;
+        let msg_name = Self::value(line, 1);
+        let mut msg = LNMsg::new(Self::msg_type(line, 2)?, msg_name);
+        if let Some(option) = line.field(3) {
+            msg.is_gossip_query = option.val == "gossip_queries";
+            msg.option = Some(option.val.to_owned());
         }
+        msg.trivia = line.trivia.to_owned();
+        trace!("Insert message in the symbol table: {:#?}", msg);
+        self.symbol_table_add_lnmsg(&msg);
+        Ok(())
     }
 
-    /// peek the next value and check if the next one is not a declaration type,
-    /// in the cvs file is the first token in the line.
-    fn peek_and_check_if_type_declaration(&self, tokens: &[CSVToken]) -> bool {
-        let next = self.peek(tokens);
-        matches!(
-            next.ty,
-            CSVTokenType::MsgData
-                | CSVTokenType::MsgTy
-                | CSVTokenType::TlvType
-                | CSVTokenType::TlvData,
-        )
-    }
-
-    /// Parse a message data entry
+    /// Parse the field of a data line, the count is optional
     ///  msgdata,init,globalfeatures,byte,gflen
     ///  msgdata,init,gflen,u16,
-    fn parse_msg_data(&mut self, target_msg: &mut LNMsg, tokens: &[CSVToken]) {
-        assert!(
-            self.peek(tokens).ty == CSVTokenType::MsgData
-                || self.peek(tokens).ty == CSVTokenType::SubMsgData
-        );
-        let _ = self.advance(tokens);
-        assert!(
-            self.advance(tokens).val == target_msg.msg_name,
-            "{}",
-            target_msg.msg_name
-        );
-
-        let token = self.advance(tokens);
-        let msg_data_name = token.val.to_string();
-        trace!("Data token after prefix: {:?}", token);
-        let token = self.advance(tokens);
-        trace!("Data type after prefix {:?}", token);
-
-        let msg_data = match token.ty {
-            CSVTokenType::U16 => {
-                let tok = self.lookup_last(tokens).unwrap();
-                LNMsData::Uint16(tok.val.to_owned())
-            }
-            CSVTokenType::U32 => {
-                let tok = self.lookup_last(tokens).unwrap();
-                LNMsData::Uint32(tok.val.to_owned())
-            }
-            CSVTokenType::U64 => {
-                let tok = self.lookup_last(tokens).unwrap();
-                LNMsData::Uint64(tok.val.to_owned())
-            }
-            CSVTokenType::ChainHash => {
-                let msg_val = self.lookup_last(tokens).unwrap();
-                LNMsData::ChainHash(msg_data_name, msg_val.val.to_owned())
-            }
-            CSVTokenType::ChannelId => {
-                let msg_val = self.lookup_last(tokens).unwrap();
-                LNMsData::ChannelId(msg_val.val.to_owned())
-            }
-            CSVTokenType::Signature => {
-                if self.is_bitfield(tokens) {
-                    self.make_bitfield(tokens)
-                } else {
-                    let msg_val = self.lookup_last(tokens).unwrap();
-                    LNMsData::Signature(msg_val.val.to_owned())
-                }
-            }
-            CSVTokenType::ShortChannelId => {
-                let msg_val = self.lookup_last(tokens).unwrap();
-                LNMsData::ShortChannelId(msg_val.val.to_owned())
-            }
-            CSVTokenType::Point => {
-                let msg_val = self.lookup_last(tokens).unwrap();
-                LNMsData::Point(msg_val.val.to_owned())
-            }
-            CSVTokenType::Sha256 => {
-                let msg_val = self.lookup_last(tokens).unwrap();
-                LNMsData::Sha256(msg_val.val.to_owned())
-            }
-            CSVTokenType::Byte => self.make_bitfield(tokens),
+    fn parse_field(line: &CSVLine) -> Result<LNMsData, ParseErrorKind> {
+        let name = Self::value(line, 2).to_owned();
+        let token = &line.tokens[3];
+        let count = Self::count(line, 4)?;
+        trace!("Data type of `{name}`: {:?}", token);
+        let data = match (&token.ty, count) {
+            // an array of a builtin type, e.g. `signature,num_htlcs`
+            (
+                CSVTokenType::U16
+                | CSVTokenType::U32
+                | CSVTokenType::U64
+                | CSVTokenType::ChainHash
+                | CSVTokenType::ChannelId
+                | CSVTokenType::Signature
+                | CSVTokenType::ShortChannelId
+                | CSVTokenType::Point
+                | CSVTokenType::Sha256
+                | CSVTokenType::BigSize,
+                Some(count),
+            ) => LNMsData::BitfieldStream(name, count, token.val.to_owned()),
+            (CSVTokenType::U16, None) => LNMsData::Uint16(name),
+            (CSVTokenType::U32, None) => LNMsData::Uint32(name),
+            (CSVTokenType::U64, None) => LNMsData::Uint64(name),
+            (CSVTokenType::ChainHash, None) => LNMsData::ChainHash(name.to_owned(), name),
+            (CSVTokenType::ChannelId, None) => LNMsData::ChannelId(name),
+            (CSVTokenType::Signature, None) => LNMsData::Signature(name),
+            (CSVTokenType::ShortChannelId, None) => LNMsData::ShortChannelId(name),
+            (CSVTokenType::Point, None) => LNMsData::Point(name),
+            (CSVTokenType::Sha256, None) => LNMsData::Sha256(name),
+            (CSVTokenType::BigSize, None) => LNMsData::BigSize(name),
+            (CSVTokenType::Byte, count) => LNMsData::BitfieldStream(
+                name,
+                count.unwrap_or("1".to_owned()),
+                token.val.to_owned(),
+            ),
             // FIXME: this is a start point for a tlv stream
-            CSVTokenType::LiteralString => {
-                log::debug!("token ****** `{:?}`", token);
+            (CSVTokenType::LiteralString, count) => {
                 // be compatible with cln csv
                 //
                 //  The difference is that cln use some alias type
                 //
                 // search for u64 alias
-                if ["amount_sat"].contains(&token.val.to_string().as_str()) {
-                    let tok = self.lookup_last(tokens).unwrap();
-                    LNMsData::Uint64(tok.val.to_owned())
+                if U64_ALIASES.contains(&token.val.as_str()) {
+                    LNMsData::Uint64(name)
                 } else if ["u8", "witness"].contains(&token.val.as_str()) {
-                    let tok = self.lookup_last(tokens).unwrap();
-                    let size = if !self.peek_and_check_if_type_declaration(tokens) {
-                        self.advance(tokens).val.to_owned()
-                    } else {
-                        "1".to_string()
-                    };
-                    trace!("bytes name {:?}\n", tok);
-                    LNMsData::BitfieldStream(tok.val.to_owned(), size)
+                    let count = count.unwrap_or("1".to_owned());
+                    LNMsData::BitfieldStream(name, count, token.val.to_owned())
+                } else if let Some(count) = count {
+                    // an array of subtypes, e.g. `onionmsg_hop,num_hops`
+                    LNMsData::BitfieldStream(name, count, token.val.to_owned())
                 } else {
-                    LNMsData::TLVinit(token.val.to_string(), msg_data_name)
+                    LNMsData::TLVinit(token.val.to_string(), name)
                 }
             }
-            _ => panic!("Unknown Token {:?}", token),
+            (_, _) => return Err(ParseErrorKind::UnknownToken(token.val.to_owned())),
         };
+        Ok(data)
+    }
+
+    /// Return the alias type of a data line, if any.
+    fn alias(line: &CSVLine) -> Option<String> {
+        let ty = Self::value(line, 3);
+        U64_ALIASES.contains(&ty).then(|| ty.to_owned())
+    }
+
+    /// Append the field to the data of a message or a subtype.
+    fn push_field(fields: &mut Vec<LNMsData>, data: LNMsData) {
         /* HACK: the bitfiled struct usually is able to length, so at this point
          * we should trip the last element and put jut the bit field */
-        if let Some(LNMsData::Uint16(_)) = target_msg.peek() {
-            if let LNMsData::BitfieldStream(_, _) = msg_data {
-                trace!("replace the last msg data (u16) with the bitfiled");
-                target_msg.replace_last_with(msg_data);
-                return;
-            }
+        let is_len = matches!(
+            (fields.last(), &data),
+            (Some(LNMsData::Uint16(len)), LNMsData::BitfieldStream(_, size, _)) if len == size
+        );
+        if is_len {
+            trace!("replace the last msg data (u16) with the bitfiled");
+            let last = fields.len() - 1;
+            fields[last] = data;
+            return;
         }
-
-        trace!("Append msg data {:?} to msg {:?}", msg_data, target_msg);
-        target_msg.add_msg_data(&msg_data);
+        trace!("Append msg data {:?}", data);
+        fields.push(data);
     }
 
-    /// PArse a TLV type declaration
-    fn parse_tlv_typ(&mut self, tokens: &[CSVToken]) -> LNTlvRecord {
-        assert_eq!(self.advance(tokens).ty, CSVTokenType::TlvType);
-        // init_tlvs,networks,1
-        match self.peek(tokens).ty {
-            CSVTokenType::LiteralString => {
-                let tlv_record_name = self.advance(tokens).val.to_string();
-                trace!("Record name {:?}", tlv_record_name);
-                let tlv_name = self.advance(tokens).val.to_string();
-                let tlv_type = self.advance(tokens).val.parse::<u64>().unwrap();
-                LNTlvRecord::new(&tlv_record_name, &tlv_name, tlv_type)
-            }
-            _ => panic!("Unknown Token {:?}", self.peek(tokens)),
+    /// Parse a message data line, the message must be declared before.
+    fn parse_msg_data(&mut self, line: &CSVLine) -> Result<(), ParseErrorKind> {
+        Self::check_arity(line, 4..=5)?;
+        let msg_name = Self::value(line, 1);
+        let data = Self::parse_field(line)?;
+        let Some(LNMsgType::Msg(msg)) = self.symbol_table.get_mut(msg_name) else {
+            return Err(ParseErrorKind::UnknownMessage(msg_name.to_owned()));
+        };
+        if !line.trivia.is_empty() {
+            msg.data_trivia
+                .insert(data.name().to_owned(), line.trivia.to_owned());
+        }
+        if let Some(alias) = Self::alias(line) {
+            msg.data_aliases.insert(data.name().to_owned(), alias);
         }
+        Self::push_field(&mut msg.msg_data, data);
+        Ok(())
     }
 
-    fn peek_and_check_if_dotdot(&self, tokens: &[CSVToken]) -> bool {
-        let CSVToken {
-            ty: CSVTokenType::Dotdotdot,
-            ..
-        } = self.peek(tokens)
-        else {
-            return false;
-        };
-        true
+    /// PArse a TLV type declaration
+    ///  tlvtype,init_tlvs,networks,1
+    fn parse_tlv_typ(&mut self, line: &CSVLine) -> Result<(), ParseErrorKind> {
+        Self::check_arity(line, 4..=4)?;
+        let tlv_record_name = Self::value(line, 1);
+        trace!("Record name {:?}", tlv_record_name);
+        let tlv_name = Self::value(line, 2);
+        let tlv_type = Self::number(line, 3)?;
+        let mut record = LNTlvRecord::new(tlv_record_name, tlv_name, tlv_type);
+        record.trivia = line.trivia.to_owned();
+        self.symbol_table_add_tlv(&record)
     }
 
-    fn parse_tlv_data(&mut self, record: &mut LNTlvRecord, tokens: &[CSVToken]) {
-        assert_eq!(self.advance(tokens).ty, CSVTokenType::TlvData);
-        assert_eq!(self.advance(tokens).val, record.stream_name);
-        assert_eq!(self.advance(tokens).val, record.type_name);
-        let tok_name = self.advance(tokens);
-        let tok_ty = self.advance(tokens);
+    /// Parse a TLV data line, the record must be declared before.
+    ///  tlvdata,init_tlvs,networks,chains,chain_hash,...
+    fn parse_tlv_data(&mut self, line: &CSVLine) -> Result<(), ParseErrorKind> {
+        Self::check_arity(line, 5..=6)?;
+        let stream_name = Self::value(line, 1);
+        let record_name = Self::value(line, 2);
+        let tok_name = Self::value(line, 3);
+        let tok_ty = Self::value(line, 4);
 
         // TODO: we should support the encoding as different field?
-        let _is_gossip_query = tok_name.val.starts_with("encoded_");
-        let _is_encoding_flag = tok_name.val.eq("encoding_type");
+        let _is_gossip_query = tok_name.starts_with("encoded_");
+        let _is_encoding_flag = tok_name.eq("encoding_type");
 
-        if self.peek_and_check_if_dotdot(tokens) {
-            // FIXME: how we manage this token
-            let _ = self.advance(tokens);
-        }
-
-        trace!(
-            "add tlv record inside the stream {:?} - {:?}",
-            tok_name,
-            tok_ty
-        );
-
-        let entry = LNTlvEntry::new(tok_name.val.as_str(), tok_ty.val.as_str());
+        let mut entry = LNTlvEntry::new(tok_name, tok_ty);
+        // the count can be `...`, a number or the name of a previous entry
+        entry.count = Self::count(line, 5)?;
+        entry.trivia = line.trivia.to_owned();
         trace!("TLV entry: {:?}", entry);
-        //trace!("TLV encoding: {:?}", encoding_typ);
-        //entry.encoding = encoding_typ;
-        record.add_entry(&entry);
-    }
 
-    fn parse_msg(&mut self, tokens: &[CSVToken]) {
-        assert!(self.advance(tokens).ty == CSVTokenType::MsgTy);
-        let mut msg_typ = self.parse_msg_typ(tokens);
-        while let CSVTokenType::MsgData = self.peek(tokens).ty {
-            self.parse_msg_data(&mut msg_typ, tokens);
-        }
-        trace!("Insert message in the symbol table: {:#?}", msg_typ);
-        self.symbol_table_add_lnmsg(&msg_typ);
-    }
-
-    fn parse_tlv(&mut self, tokens: &[CSVToken]) {
-        let mut tlv_typ = self.parse_tlv_typ(tokens);
-        trace!("parsing tlv type {:?}", tlv_typ);
-        while let CSVTokenType::TlvData = self.peek(tokens).ty {
-            self.parse_tlv_data(&mut tlv_typ, tokens);
-        }
-        self.symbol_table_add_tlv(&tlv_typ);
+        let record = match self.symbol_table.get_mut(stream_name) {
+            Some(LNMsgType::Tlv(stream)) => stream
+                .records
+                .iter_mut()
+                .rfind(|record| record.type_name == record_name),
+            _ => None,
+        };
+        let Some(record) = record else {
+            return Err(ParseErrorKind::UnknownRecord {
+                stream: stream_name.to_owned(),
+                record: record_name.to_owned(),
+            });
+        };
+        record.add_entry(&entry);
+        Ok(())
     }
 
-    fn parse_subtype_ty(&mut self, tokens: &[CSVToken]) -> LNSubType {
-        let subtype_name = self.advance(tokens);
+    /// Parse a subtype declaration
+    ///  subtype,witness
+    fn parse_subtype(&mut self, line: &CSVLine) -> Result<(), ParseErrorKind> {
+        Self::check_arity(line, 2..=2)?;
+        let subtype_name = Self::value(line, 1);
         trace!("parsing subtype name {:?}", subtype_name);
-        LNSubType::new(subtype_name.val.as_str())
-    }
-
-    fn parse_subtype(&mut self, tokens: &[CSVToken]) {
-        assert_eq!(self.advance(tokens).ty, CSVTokenType::SubTy);
-        let mut typ = self.parse_subtype_ty(tokens);
-        trace!("parsing subtype");
-        // FIXME: remove this trick and decode a real subtype!
-        let mut fake_lnmessage = LNMsg::new(0, typ.ty.as_str());
-        while let CSVTokenType::SubMsgData = self.peek(tokens).ty {
-            self.parse_msg_data(&mut fake_lnmessage, tokens);
-        }
-        typ.ty_data = fake_lnmessage.msg_data;
+        let mut subtype = LNSubType::new(subtype_name);
+        subtype.trivia = line.trivia.to_owned();
+        self.symbol_table
+            .insert(subtype_name.to_owned(), LNMsgType::SubType(subtype));
+        Ok(())
     }
 
-    fn make_bitfield(&mut self, tokens: &[CSVToken]) -> LNMsData {
-        let tok = self.lookup_last(tokens).unwrap();
-        let size = if !self.peek_and_check_if_type_declaration(tokens) {
-            self.advance(tokens).val.to_owned()
-        } else {
-            "1".to_string()
+    /// Parse a subtype data line, the subtype must be declared before.
+    ///  subtypedata,witness,len,u16,
+    fn parse_subtype_data(&mut self, line: &CSVLine) -> Result<(), ParseErrorKind> {
+        Self::check_arity(line, 4..=5)?;
+        let subtype_name = Self::value(line, 1);
+        let data = Self::parse_field(line)?;
+        let Some(LNMsgType::SubType(subtype)) = self.symbol_table.get_mut(subtype_name) else {
+            return Err(ParseErrorKind::UnknownSubtype(subtype_name.to_owned()));
         };
-        trace!("bytes name {:?}\n", tok);
-        LNMsData::BitfieldStream(tok.val.to_owned(), size)
+        if !line.trivia.is_empty() {
+            subtype
+                .data_trivia
+                .insert(data.name().to_owned(), line.trivia.to_owned());
+        }
+        if let Some(alias) = Self::alias(line) {
+            subtype.data_aliases.insert(data.name().to_owned(), alias);
+        }
+        Self::push_field(&mut subtype.ty_data, data);
+        Ok(())
     }
 
-    fn is_bitfield(&mut self, tokens: &[CSVToken]) -> bool {
-        !self.peek_and_check_if_type_declaration(tokens)
+    /// Entry point of the parser for the lines of the scanner!
+    ///
+    /// The parsing stops at the first line with an error.
+    pub fn parse_lines(&mut self, lines: &[CSVLine]) -> Result<(), ParseError> {
+        for line in lines {
+            let result = match line.kind() {
+                CSVTokenType::MsgTy => self.parse_msg_typ(line),
+                CSVTokenType::MsgData => self.parse_msg_data(line),
+                CSVTokenType::SubTy => self.parse_subtype(line),
+                CSVTokenType::SubMsgData => self.parse_subtype_data(line),
+                CSVTokenType::TlvType => self.parse_tlv_typ(line),
+                CSVTokenType::TlvData => self.parse_tlv_data(line),
+                CSVTokenType::EOF => {
+                    self.trailing_trivia = line.trivia.to_owned();
+                    break;
+                }
+                _ => Err(ParseErrorKind::UnknownToken(line.tokens[0].val.to_owned())),
+            };
+            result.map_err(|kind| ParseError {
+                line: line.code_line,
+                kind,
+            })?;
+        }
+        trace!("Terminating with Parser: {:#?}", self.symbol_table);
+        Ok(())
     }
 
     /// Entry point of the parser!
-    pub fn parse(&mut self, tokens: &[CSVToken]) {
-        while self.peek(tokens).ty != CSVTokenType::EOF {
-            match self.peek(tokens).ty {
-                CSVTokenType::MsgTy => self.parse_msg(tokens),
-                CSVTokenType::SubTy => self.parse_subtype(tokens),
-                CSVTokenType::TlvType => self.parse_tlv(tokens),
-                _ => {
-                    trace!("look up token {:?}", self.lookup_last(tokens));
-                    trace!("previous token {:?}", tokens.get(self.pos - 1).unwrap());
-                    panic!("Unknown Token {:?}", self.peek(tokens))
+    ///
+    /// The tokens are grouped by line before parsing them.
+    pub fn parse(&mut self, tokens: &[CSVToken]) -> Result<(), ParseError> {
+        let mut lines: Vec<CSVLine> = Vec::new();
+        for token in tokens {
+            let code_line = token.code_line.unwrap_or_default();
+            match lines.last_mut() {
+                Some(line)
+                    if line.code_line == code_line
+                        && token.ty != CSVTokenType::EOF
+                        && *line.kind() != CSVTokenType::EOF =>
+                {
+                    line.tokens.push(token.to_owned())
                 }
+                _ => lines.push(CSVLine {
+                    code_line,
+                    tokens: vec![token.to_owned()],
+                    trivia: token.trivia.to_owned(),
+                }),
             }
         }
-        trace!("Terminating with Parser: {:#?}", self.symbol_table);
+        self.parse_lines(&lines)
     }
 }
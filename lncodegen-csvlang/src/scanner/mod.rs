@@ -37,6 +37,7 @@ mod test {
                 token::CSVTokenType::BigSize => assert_eq!(c.val, "bigsize"),
                 token::CSVTokenType::LiteralString => continue,
                 token::CSVTokenType::Number => continue,
+                token::CSVTokenType::InvalidNumber(_) => continue,
                 token::CSVTokenType::Tu32 => assert_eq!(c.val, "tu32"),
                 token::CSVTokenType::Tu64 => assert_eq!(c.val, "tu64"),
                 token::CSVTokenType::Tlvs => assert_eq!(c.val, "tlvs"),
@@ -44,6 +45,7 @@ mod test {
                 token::CSVTokenType::Data => assert_eq!(c.val, "data"),
                 token::CSVTokenType::SubTy => assert_eq!(c.val, "subtype"),
                 token::CSVTokenType::SubMsgData => assert_eq!(c.val, "subtypedata"),
+                token::CSVTokenType::Empty => assert_eq!(c.val, ""),
                 token::CSVTokenType::EOF => continue,
             }
         }
@@ -56,21 +58,24 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result = scanner.scan(contents);
         assert!(!result.is_empty());
-        let expected = vec![
+        let expected = [
             token::CSVToken {
                 ty: token::CSVTokenType::MsgTy,
                 val: "msgtype".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "init".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::Number,
                 val: "16".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
         ];
         for c in 0..expected.len() - 1 {
@@ -87,26 +92,30 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result = scanner.scan(contents);
         assert!(!result.is_empty());
-        let expected = vec![
+        let expected = [
             token::CSVToken {
                 ty: token::CSVTokenType::MsgData,
                 val: "msgdata".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "init".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "gflen".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::U16,
                 val: "u16".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
         ];
         for c in 0..expected.len() - 1 {
@@ -124,36 +133,42 @@ mod test {
         let mut result = scanner.scan(contents);
         result.reverse();
         assert!(!result.is_empty());
-        let expected = vec![
+        let expected = [
             token::CSVToken {
                 ty: token::CSVTokenType::EOF,
                 val: "EOF".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "gflen".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::Byte,
                 val: "byte".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "globalfeatures".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "init".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::MsgData,
                 val: "msgdata".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
         ];
         for c in 0..expected.len() - 1 {
@@ -179,26 +194,30 @@ mod test {
         let mut scanner = scanner::Scanner::new();
         let result = scanner.scan(contents);
         assert!(!result.is_empty());
-        let expected = vec![
+        let expected = [
             token::CSVToken {
                 ty: token::CSVTokenType::MsgData,
                 val: "msgdata".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "init".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::LiteralString,
                 val: "gflen".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
             token::CSVToken {
                 ty: token::CSVTokenType::U16,
                 val: "u16".to_string(),
                 code_line: None,
+                trivia: Vec::new(),
             },
         ];
         for c in 0..expected.len() - 1 {
@@ -216,4 +235,86 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_comments_as_trivia() {
+        let contents =
+            "# first comment\n# second comment\nmsgtype,init,16\nfoo,bar\n# last comment\n";
+        let mut scanner = scanner::Scanner::new();
+        let result = scanner.scan(contents);
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[0].ty, token::CSVTokenType::MsgTy);
+        assert_eq!(result[0].trivia, ["# first comment", "# second comment"]);
+        assert!(result[1].trivia.is_empty());
+        assert_eq!(result[3].ty, token::CSVTokenType::EOF);
+        assert_eq!(result[3].trivia, ["foo,bar", "# last comment"]);
+    }
+
+    #[test]
+    fn test_empty_and_quoted_fields() {
+        let contents = "msgtype,init,,16\r\nmsgdata,init,\"a,b\",\"u16\",\r\n";
+        let mut scanner = scanner::Scanner::new();
+        let result = scanner.scan(contents);
+        let types = result
+            .iter()
+            .map(|token| token.ty.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                token::CSVTokenType::MsgTy,
+                token::CSVTokenType::LiteralString,
+                token::CSVTokenType::Empty,
+                token::CSVTokenType::Number,
+                token::CSVTokenType::MsgData,
+                token::CSVTokenType::LiteralString,
+                token::CSVTokenType::LiteralString,
+                token::CSVTokenType::LiteralString,
+                token::CSVTokenType::Empty,
+                token::CSVTokenType::EOF,
+            ]
+        );
+        assert_eq!(result[3].val, "16");
+        assert_eq!(result[6].val, "a,b");
+        assert_eq!(result[7].val, "u16");
+        assert_eq!(result[4].code_line, Some(2));
+    }
+
+    #[test]
+    fn test_scan_lines() {
+        let contents = "msgtype,init,16\n\n# flags\nmsgdata,init,gflen,u16,\n";
+        let mut scanner = scanner::Scanner::new();
+        let lines = scanner.scan_lines(contents);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].code_line, 4);
+        assert_eq!(*lines[1].kind(), token::CSVTokenType::MsgData);
+        assert_eq!(lines[1].trivia, ["# flags"]);
+        assert_eq!(lines[1].field(3).unwrap().val, "u16");
+        assert!(lines[1].field(4).is_none());
+        assert_eq!(*lines[2].kind(), token::CSVTokenType::EOF);
+    }
+
+    #[test]
+    fn test_scan_numbers() {
+        let contents =
+            "tlvtype,n1,tlv1,0x10\ntlvdata,n1,tlv1,amt,byte,1.5\nmsgtype,m,18446744073709551616\n";
+        let mut scanner = scanner::Scanner::new();
+        let result = scanner.scan(contents);
+        assert_eq!(result[3].ty, token::CSVTokenType::Number);
+        assert_eq!(
+            result[9].ty,
+            token::CSVTokenType::InvalidNumber(token::NumberError::NotInteger)
+        );
+        assert_eq!(
+            result[12].ty,
+            token::CSVTokenType::InvalidNumber(token::NumberError::Overflow)
+        );
+        assert_eq!(token::parse_number("0x1F"), Some(Ok(31)));
+        assert_eq!(
+            token::parse_number("0xg"),
+            Some(Err(token::NumberError::InvalidHex))
+        );
+        assert_eq!(token::parse_number("inf"), None);
+        assert_eq!(token::parse_number("tlv1"), None);
+    }
 }
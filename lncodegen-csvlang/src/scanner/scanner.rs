@@ -1,3 +1,5 @@
+use super::token::parse_number;
+use super::token::CSVLine;
 use super::token::CSVToken;
 use super::token::CSVTokenType;
 use log::trace;
@@ -23,6 +25,7 @@ impl Scanner {
                     ty: CSVTokenType::MsgTy,
                     val: "msgtype".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -31,6 +34,7 @@ impl Scanner {
                     ty: CSVTokenType::SubTy,
                     val: "subtype".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -39,6 +43,7 @@ impl Scanner {
                     ty: CSVTokenType::SubMsgData,
                     val: "subtypedata".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -47,6 +52,7 @@ impl Scanner {
                     ty: CSVTokenType::MsgData,
                     val: "msgdata".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -55,6 +61,7 @@ impl Scanner {
                     ty: CSVTokenType::TlvType,
                     val: "tlvtype".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -63,6 +70,7 @@ impl Scanner {
                     ty: CSVTokenType::TlvData,
                     val: "tlvdata".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -71,6 +79,7 @@ impl Scanner {
                     ty: CSVTokenType::ShortChannelId,
                     val: "short_channel_id".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -79,6 +88,7 @@ impl Scanner {
                     ty: CSVTokenType::Sha256,
                     val: "sha256".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -87,6 +97,7 @@ impl Scanner {
                     ty: CSVTokenType::U16,
                     val: "u16".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -95,6 +106,7 @@ impl Scanner {
                     ty: CSVTokenType::U32,
                     val: "u32".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -103,6 +115,7 @@ impl Scanner {
                     ty: CSVTokenType::U64,
                     val: "u64".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -111,6 +124,7 @@ impl Scanner {
                     ty: CSVTokenType::ChannelId,
                     val: "channel_id".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -119,6 +133,7 @@ impl Scanner {
                     ty: CSVTokenType::Signature,
                     val: "signature".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -127,6 +142,7 @@ impl Scanner {
                     ty: CSVTokenType::Point,
                     val: "point".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -135,6 +151,7 @@ impl Scanner {
                     ty: CSVTokenType::ChainHash,
                     val: "chain_hash".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -143,6 +160,7 @@ impl Scanner {
                     ty: CSVTokenType::Byte,
                     val: "byte".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -151,6 +169,7 @@ impl Scanner {
                     ty: CSVTokenType::BigSize,
                     val: "bigsize".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -159,6 +178,7 @@ impl Scanner {
                     ty: CSVTokenType::Tu32,
                     val: "tu32".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -167,6 +187,7 @@ impl Scanner {
                     ty: CSVTokenType::Tu64,
                     val: "tu64".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -175,6 +196,7 @@ impl Scanner {
                     ty: CSVTokenType::Tlvs,
                     val: "tlvs".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -183,6 +205,7 @@ impl Scanner {
                     ty: CSVTokenType::Dotdotdot,
                     val: "...".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
             (
@@ -191,6 +214,7 @@ impl Scanner {
                     ty: CSVTokenType::Data,
                     val: "data".to_string(),
                     code_line: None,
+                    trivia: Vec::new(),
                 },
             ),
         ]);
@@ -198,54 +222,162 @@ impl Scanner {
     }
 
     pub fn add_token(&mut self, tokenize: &mut Vec<CSVToken>, buffer: &str, pos: u64) {
-        // sanity check if the buffer is empty we can not perform any operation.
-        // FIXME: condition like the following on `init, ,16` are not valid?
-        if !buffer.is_empty() {
-            if buffer.trim().parse::<f64>().is_ok() {
-                tokenize.push(CSVToken {
-                    ty: CSVTokenType::Number,
-                    val: buffer.to_owned(),
-                    code_line: Some(pos),
-                });
-            } else {
-                tokenize.push(CSVToken {
-                    ty: CSVTokenType::LiteralString,
-                    val: buffer.to_owned(),
-                    code_line: Some(pos),
-                });
+        let ty = if buffer.is_empty() {
+            CSVTokenType::Empty
+        } else {
+            match parse_number(buffer) {
+                Some(Ok(_)) => CSVTokenType::Number,
+                // the parser reports the error with the line
+                Some(Err(err)) => CSVTokenType::InvalidNumber(err),
+                None => CSVTokenType::LiteralString,
+            }
+        };
+        tokenize.push(CSVToken {
+            ty,
+            val: buffer.to_owned(),
+            code_line: Some(pos),
+            trivia: Vec::new(),
+        });
+    }
+
+    /// Split the line in fields, a field between double quotes can
+    /// contain `,`, and `""` is a quote inside it. The quoted fields
+    /// are returned with the quoted flag set.
+    fn split_fields(line: &str) -> Vec<(String, bool)> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if in_quotes && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    let _ = chars.next();
+                }
+                '"' if in_quotes => in_quotes = false,
+                '"' if field.trim().is_empty() => {
+                    field.clear();
+                    quoted = true;
+                    in_quotes = true;
+                }
+                ',' if !in_quotes => {
+                    let value = std::mem::take(&mut field);
+                    let value = if quoted {
+                        value
+                    } else {
+                        value.trim().to_owned()
+                    };
+                    fields.push((value, quoted));
+                    quoted = false;
+                }
+                c => field.push(c),
             }
         }
+        let value = if quoted {
+            field
+        } else {
+            field.trim().to_owned()
+        };
+        fields.push((value, quoted));
+        fields
     }
 
-    pub fn scan(&mut self, content: &str) -> Vec<CSVToken> {
-        // We can split the content by new line terminator
-        let lines = content.split_terminator('\n');
-        let mut tokenize: Vec<CSVToken> = Vec::new();
+    /// Return true if the line starts with a declaration keyword.
+    fn is_declaration(&self, line: &str) -> bool {
+        let fields = Self::split_fields(line);
+        let first = fields[0].0.as_str();
+        matches!(
+            self.keywords.get(first).map(|keyword| &keyword.ty),
+            Some(
+                CSVTokenType::MsgTy
+                    | CSVTokenType::MsgData
+                    | CSVTokenType::SubTy
+                    | CSVTokenType::SubMsgData
+                    | CSVTokenType::TlvType
+                    | CSVTokenType::TlvData
+            )
+        )
+    }
+
+    /// Split the content in lines of tokens, the last line contains
+    /// only the `EOF` token with the trivia at the end of the file.
+    pub fn scan_lines(&mut self, content: &str) -> Vec<CSVLine> {
+        // We can split the content by new line terminator, `\r\n` included
+        let lines = content.lines();
+        let mut result: Vec<CSVLine> = Vec::new();
+        // the comments and the unknown lines that are waiting
+        // for the next declaration.
+        let mut trivia = Vec::new();
         let mut line_pos = 1;
         for line in lines {
             log::debug!("looking at the line: {line}");
-            if line.trim().starts_with('#') {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
                 line_pos += 1;
+                continue;
+            }
+            if trimmed.starts_with('#') {
                 // it is a comment
+                trivia.push(trimmed.to_owned());
+                line_pos += 1;
+                continue;
+            }
+            if !self.is_declaration(trimmed) {
+                log::warn!("line {line_pos}: unknown declaration `{trimmed}`, kept as trivia");
+                trivia.push(trimmed.to_owned());
+                line_pos += 1;
                 continue;
             }
-            // Splitting the line in tokens by `,`
-            let tokens = line.split(',');
-            for token in tokens {
-                let token = token.trim();
-                if let Some(keyword) = self.keywords.get(token) {
-                    tokenize.push(keyword.to_owned());
-                } else {
-                    self.add_token(&mut tokenize, token, line_pos);
+            let mut tokenize = Vec::new();
+            // Splitting the line in tokens by `,`, the empty fields are
+            // kept so the position of the following ones does not change.
+            for (field, quoted) in Self::split_fields(line) {
+                match self.keywords.get(&field) {
+                    Some(keyword) if !quoted => {
+                        let mut keyword = keyword.to_owned();
+                        keyword.code_line = Some(line_pos);
+                        tokenize.push(keyword);
+                    }
+                    _ if quoted && !field.is_empty() => tokenize.push(CSVToken {
+                        ty: CSVTokenType::LiteralString,
+                        val: field,
+                        code_line: Some(line_pos),
+                        trivia: Vec::new(),
+                    }),
+                    _ => self.add_token(&mut tokenize, &field, line_pos),
                 }
             }
+            result.push(CSVLine {
+                code_line: line_pos,
+                tokens: tokenize,
+                trivia: std::mem::take(&mut trivia),
+            });
             line_pos += 1;
         }
-        tokenize.push(CSVToken {
-            ty: CSVTokenType::EOF,
-            val: "EOF".to_string(),
-            code_line: Some(line_pos),
+        result.push(CSVLine {
+            code_line: line_pos,
+            tokens: vec![CSVToken {
+                ty: CSVTokenType::EOF,
+                val: "EOF".to_string(),
+                code_line: Some(line_pos),
+                trivia: Vec::new(),
+            }],
+            trivia,
         });
+        trace!("lines list: {:?}", result);
+        result
+    }
+
+    /// Split the content in a flat list of tokens, the trivia of
+    /// a line is attached to its first token.
+    pub fn scan(&mut self, content: &str) -> Vec<CSVToken> {
+        let mut tokenize = Vec::new();
+        for line in self.scan_lines(content) {
+            let first = tokenize.len();
+            tokenize.extend(line.tokens);
+            tokenize[first].trivia = line.trivia;
+        }
         trace!("tokens list: {:?}", tokenize);
         tokenize
     }
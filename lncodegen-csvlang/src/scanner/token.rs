@@ -21,11 +21,15 @@ pub enum CSVTokenType {
     BigSize,
     LiteralString,
     Number,
+    /// A numeric field that is not an unsigned integer of 64 bits, e.g. `1.5`.
+    InvalidNumber(NumberError),
     Tu32,
     Tu64,
     Tlvs,
     Dotdotdot,
     Data,
+    /// A field without value, e.g. the count in `msgdata,init,gflen,u16,`.
+    Empty,
     EOF,
 }
 
@@ -55,16 +59,91 @@ impl fmt::Display for CSVTokenType {
             Self::Dotdotdot => "...",
             Self::Data => "data",
             Self::Number => "number",
+            Self::InvalidNumber(_) => "invalid number",
             Self::LiteralString => "str",
+            Self::Empty => "empty",
             Self::EOF => "eof",
         };
         write!(f, "{name}")
     }
 }
 
+/// The reason why a numeric field is not valid.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NumberError {
+    /// The value is a float or a negative number.
+    NotInteger,
+    /// The value does not fit in 64 bits.
+    Overflow,
+    /// The value has the `0x` prefix but not hex digits.
+    InvalidHex,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Self::NotInteger => "is not an unsigned integer",
+            Self::Overflow => "does not fit in 64 bits",
+            Self::InvalidHex => "is not a valid hex number",
+        };
+        write!(f, "{msg}")
+    }
+}
+
+/// Parse a numeric field, that is an unsigned integer in decimal
+/// or in hex with the `0x` prefix, e.g. `16` or `0x10`.
+///
+/// Return None if the value does not look like a number.
+pub fn parse_number(value: &str) -> Option<Result<u64, NumberError>> {
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Some(Err(NumberError::InvalidHex));
+        }
+        return Some(u64::from_str_radix(hex, 16).map_err(|_| NumberError::Overflow));
+    }
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        return Some(value.parse::<u64>().map_err(|_| NumberError::Overflow));
+    }
+    // `inf` and `nan` are floats for rust, but they are names here.
+    let numeric_start = value.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c));
+    if numeric_start && value.parse::<f64>().is_ok() {
+        return Some(Err(NumberError::NotInteger));
+    }
+    None
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct CSVToken {
     pub ty: CSVTokenType,
     pub val: String,
     pub code_line: Option<u64>,
+    /// The comments and the unknown lines that come before the
+    /// declaration, only the first token of a line has trivia.
+    pub trivia: Vec<String>,
+}
+
+/// A line of the csv file, with a token for every field.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CSVLine {
+    pub code_line: u64,
+    pub tokens: Vec<CSVToken>,
+    /// The comments and the unknown lines that come before the line.
+    pub trivia: Vec<String>,
+}
+
+impl CSVLine {
+    /// Return the kind of the line, that is the type of the first token.
+    pub fn kind(&self) -> &CSVTokenType {
+        &self.tokens[0].ty
+    }
+
+    /// Return the field in the position provided, if it has a value.
+    pub fn field(&self, idx: usize) -> Option<&CSVToken> {
+        self.tokens
+            .get(idx)
+            .filter(|token| token.ty != CSVTokenType::Empty)
+    }
 }